@@ -0,0 +1,178 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use serde::Deserialize;
+use serde_bencode::{from_bytes, to_bytes, Validated, Value, ValueRef};
+
+// a `get_peers` query, the most common DHT message
+const KRPC: &[u8] = b"d1:ad2:id20:abcdefghij01234567899:info_hash20:mnopqrstuvwxyz123456e\
+                      1:q9:get_peers1:t2:aa1:y1:qe";
+
+#[derive(Deserialize)]
+struct Query<'a> {
+    #[serde(borrow)]
+    a: Args<'a>,
+    q: &'a str,
+    t: &'a [u8],
+    y: &'a str,
+}
+
+#[derive(Deserialize)]
+struct Args<'a> {
+    id: &'a [u8],
+    info_hash: &'a [u8],
+}
+
+#[derive(Deserialize)]
+struct Torrent {
+    announce: String,
+    info: Info,
+}
+
+#[derive(Deserialize)]
+struct Info {
+    files: Vec<File>,
+    name: String,
+    #[serde(rename = "piece length")]
+    piece_length: u64,
+    #[serde(with = "serde_bytes_compat")]
+    pieces: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+struct File {
+    length: u64,
+    path: Vec<String>,
+}
+
+// `Vec<u8>` deserializes from a sequence, so read the pieces as bytes
+mod serde_bytes_compat {
+    use serde::de::{Deserializer, Visitor};
+    use std::fmt;
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+        struct BytesVisitor;
+
+        impl Visitor<'_> for BytesVisitor {
+            type Value = Vec<u8>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a byte string")
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Vec<u8>, E> {
+                Ok(v.to_vec())
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Vec<u8>, E> {
+                Ok(v.as_bytes().to_vec())
+            }
+        }
+
+        d.deserialize_bytes(BytesVisitor)
+    }
+}
+
+fn large_torrent(files: usize) -> Vec<u8> {
+    let mut list = Value::List(Vec::new());
+    for i in 0..files {
+        list.push(serde_bencode::bencode!({
+            "length": (i as i64) * 4099 + 17,
+            "path": ["some directory", format!("file number {}.bin", i)],
+        }));
+    }
+    let torrent = serde_bencode::bencode!({
+        "announce": "http://tracker.example.com:6969/announce",
+        "info": {
+            "files": list,
+            "name": "a large multi-file torrent",
+            "piece length": 262144,
+            "pieces": vec![0x5au8; files * 20],
+        },
+    });
+    to_bytes(&torrent).unwrap()
+}
+
+fn deep_nesting(depth: usize) -> Vec<u8> {
+    let mut input = Vec::with_capacity(depth * 8 + 3);
+    for i in 0..depth {
+        input.extend_from_slice(if i % 2 == 0 { b"l" } else { b"d1:k" });
+    }
+    input.extend_from_slice(b"i1e");
+    input.resize(input.len() + depth, b'e');
+    input
+}
+
+fn ints(count: usize) -> Vec<u8> {
+    let list: Vec<i64> = (0..count as i64).map(|i| i * 7919 - 1_000_000).collect();
+    to_bytes(&list).unwrap()
+}
+
+fn krpc(c: &mut Criterion) {
+    let mut group = c.benchmark_group("krpc");
+    group.throughput(Throughput::Bytes(KRPC.len() as u64));
+    group.bench_function("struct", |b| {
+        b.iter(|| {
+            let q: Query = from_bytes(KRPC).unwrap();
+            (q.a.id.len(), q.a.info_hash.len(), q.q, q.t, q.y)
+        })
+    });
+    group.bench_function("value", |b| b.iter(|| from_bytes::<Value>(KRPC).unwrap()));
+    group.bench_function("value_ref", |b| {
+        b.iter(|| from_bytes::<ValueRef>(KRPC).unwrap())
+    });
+    group.finish();
+}
+
+fn torrent(c: &mut Criterion) {
+    let input = large_torrent(10_000);
+    let mut group = c.benchmark_group("large_torrent");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    group.bench_function("struct", |b| {
+        b.iter(|| {
+            let t: Torrent = from_bytes(&input).unwrap();
+            (t.announce.len(), t.info.files.len(), t.info.name.len())
+        })
+    });
+    group.bench_function("value", |b| b.iter(|| from_bytes::<Value>(&input).unwrap()));
+    group.bench_function("value_ref", |b| {
+        b.iter(|| from_bytes::<ValueRef>(&input).unwrap())
+    });
+    let validated = Validated::new(&input).unwrap();
+    group.bench_function("validated_struct", |b| {
+        b.iter(|| {
+            let t: Torrent = validated.deserialize().unwrap();
+            (t.announce.len(), t.info.files.len(), t.info.name.len())
+        })
+    });
+    group.finish();
+
+    let t: Torrent = from_bytes(&input).unwrap();
+    assert_eq!(262144, t.info.piece_length);
+    assert_eq!(200_000, t.info.pieces.len());
+    assert_eq!(17, t.info.files[0].length);
+    assert_eq!(2, t.info.files[0].path.len());
+}
+
+fn nesting(c: &mut Criterion) {
+    let input = deep_nesting(100);
+    let mut group = c.benchmark_group("deep_nesting");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    group.bench_function("value", |b| b.iter(|| from_bytes::<Value>(&input).unwrap()));
+    group.finish();
+}
+
+fn integers(c: &mut Criterion) {
+    let input = ints(10_000);
+    let mut group = c.benchmark_group("integers");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    group.bench_function("i64", |b| {
+        b.iter(|| from_bytes::<Vec<i64>>(&input).unwrap())
+    });
+    group.bench_function("byte_string_lengths", |b| {
+        let strings = to_bytes(&vec!["x".repeat(100); 1000]).unwrap();
+        b.iter(|| from_bytes::<Vec<&str>>(&strings).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, krpc, torrent, nesting, integers);
+criterion_main!(benches);
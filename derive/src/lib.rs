@@ -0,0 +1,291 @@
+//! Derives for `serde-bencode`; use them through its `derive` feature.
+
+use proc_macro::TokenStream;
+use proc_macro2::{TokenStream as TokenStream2, TokenTree};
+use quote::{format_ident, quote, ToTokens};
+use syn::punctuated::Punctuated;
+use syn::{
+    parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Error, Expr, ExprLit, Fields,
+    Ident, Lit, LitStr, Meta, Token,
+};
+
+/// Derives `Deserialize` like serde's derive, and also understands
+/// `#[bencode(raw)]` on a field: the field is decoded as usual, and its
+/// exact encoded bytes go into the sibling field `<name>_raw`, or the one
+/// given by `#[bencode(raw = "...")]`. The sibling can be any type that is
+/// `From<Vec<u8>>`.
+///
+/// ```ignore
+/// #[derive(serde_bencode::BencodeDeserialize)]
+/// struct Torrent {
+///     announce: String,
+///     #[bencode(raw)]
+///     info: Info,
+///     info_raw: Vec<u8>,
+/// }
+/// ```
+///
+/// `#[serde(...)]` attributes are passed on to serde's derive. A container
+/// `#[serde(default)]` takes the defaults from the struct's own `Default`
+/// (or the given function); a captured field filled in that way has no
+/// bytes, so its sibling gets an empty `Vec`.
+#[proc_macro_derive(BencodeDeserialize, attributes(bencode, serde))]
+pub fn derive_deserialize(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+// the items of every `#[serde(...)]` attribute in `attrs`
+fn serde_metas(attrs: &[Attribute]) -> syn::Result<Vec<Meta>> {
+    let mut metas = Vec::new();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("serde")) {
+        metas.extend(attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?);
+    }
+    Ok(metas)
+}
+
+// whether `tokens` name one of `lifetimes`; `'static` never needs borrowing
+fn mentions_lifetime(tokens: TokenStream2, lifetimes: &[&Ident]) -> bool {
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        let found = match token {
+            TokenTree::Punct(p) if p.as_char() == '\'' => {
+                matches!(tokens.peek(), Some(TokenTree::Ident(name)) if lifetimes.contains(&name))
+            }
+            TokenTree::Group(g) => mentions_lifetime(g.stream(), lifetimes),
+            _ => false,
+        };
+        if found {
+            return true;
+        }
+    }
+    false
+}
+
+struct Field<'a> {
+    field: &'a syn::Field,
+    ident: &'a Ident,
+    // the field that receives this one's bytes
+    raw: Option<Ident>,
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    input,
+                    "expected a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                input,
+                "expected a struct with named fields",
+            ))
+        }
+    };
+    for attr in &input.attrs {
+        if attr.path().is_ident("bencode") {
+            return Err(Error::new_spanned(attr, "unknown bencode attribute"));
+        }
+    }
+
+    let mut parsed = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let mut raw = None;
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("bencode")) {
+            attr.parse_nested_meta(|meta| {
+                if !meta.path.is_ident("raw") {
+                    return Err(meta.error("unknown bencode attribute"));
+                }
+                raw = Some(if meta.input.peek(syn::Token![=]) {
+                    let name: LitStr = meta.value()?.parse()?;
+                    name.parse()?
+                } else {
+                    format_ident!("{}_raw", ident)
+                });
+                Ok(())
+            })?;
+        }
+        parsed.push(Field { field, ident, raw });
+    }
+
+    // each sibling must exist, and can only take one field's bytes
+    let mut siblings: Vec<&Ident> = Vec::new();
+    for f in &parsed {
+        if let Some(raw) = &f.raw {
+            match parsed.iter().find(|g| g.ident == raw) {
+                None => return Err(Error::new(raw.span(), format!("no field named `{}`", raw))),
+                Some(g) if g.raw.is_some() || siblings.contains(&g.ident) => {
+                    return Err(Error::new(
+                        raw.span(),
+                        format!("`{}` can't take raw bytes", raw),
+                    ));
+                }
+                Some(g) => siblings.push(g.ident),
+            }
+        }
+    }
+
+    let serde = quote!(::serde_bencode::__private::serde);
+    let lifetimes: Vec<&Ident> = input
+        .generics
+        .lifetimes()
+        .map(|l| &l.lifetime.ident)
+        .collect();
+    let mut shadow_fields = Vec::new();
+    for f in parsed.iter().filter(|f| !siblings.contains(&f.ident)) {
+        let attrs: Vec<_> = f
+            .field
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("serde"))
+            .collect();
+        let ident = f.ident;
+        let ty = &f.field.ty;
+        if f.raw.is_none() {
+            shadow_fields.push(quote!(#(#attrs)* #ident: #ty));
+            continue;
+        }
+        // serde only borrows through `&str` and `&[u8]` on its own, not
+        // through the `Captured` around them
+        let borrows = mentions_lifetime(ty.to_token_stream(), &lifetimes);
+        let borrowed = serde_metas(&f.field.attrs)?
+            .iter()
+            .any(|m| m.path().is_ident("borrow"));
+        let borrow = (borrows && !borrowed).then(|| quote!(#[serde(borrow)]));
+        shadow_fields.push(quote!(#(#attrs)* #borrow #ident: ::serde_bencode::Captured<#ty>));
+    }
+    let inits = parsed.iter().map(|f| {
+        let ident = f.ident;
+        if f.raw.is_some() {
+            quote!(#ident: __shadow.#ident.value)
+        } else if let Some(from) = parsed.iter().find(|g| g.raw.as_ref() == Some(ident)) {
+            let from = from.ident;
+            quote!(#ident: ::core::convert::From::from(__shadow.#from.bytes))
+        } else {
+            quote!(#ident: __shadow.#ident)
+        }
+    });
+
+    let name = &input.ident;
+    let generics = &input.generics;
+    let (plain_impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    // `default` would ask for `__Shadow: Default`, so it is taken out and
+    // the shadow's `Default` built from the struct's instead
+    let mut container_metas = serde_metas(&input.attrs)?;
+    let mut default = None;
+    container_metas.retain(|m| {
+        if !m.path().is_ident("default") {
+            return true;
+        }
+        default = Some(m.clone());
+        false
+    });
+    let shadow_default = match default {
+        None => None,
+        Some(meta) => {
+            let value = match meta {
+                Meta::Path(_) => quote!(::core::default::Default::default()),
+                Meta::NameValue(nv) => match nv.value {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(path),
+                        ..
+                    }) => {
+                        let path: syn::ExprPath = path.parse()?;
+                        quote!(#path())
+                    }
+                    value => return Err(Error::new_spanned(value, "expected a function path")),
+                },
+                Meta::List(list) => {
+                    return Err(Error::new_spanned(
+                        list,
+                        "expected `default` or `default = \"...\"`",
+                    ))
+                }
+            };
+            let fields = parsed
+                .iter()
+                .filter(|f| !siblings.contains(&f.ident))
+                .map(|f| {
+                    let ident = f.ident;
+                    if f.raw.is_some() {
+                        quote!(#ident: ::serde_bencode::Captured {
+                            value: __default.#ident,
+                            bytes: ::serde_bencode::__private::vec::Vec::new(),
+                        })
+                    } else {
+                        quote!(#ident: __default.#ident)
+                    }
+                });
+            Some(quote! {
+                impl #plain_impl_generics ::core::default::Default for __Shadow #ty_generics
+                #where_clause
+                {
+                    fn default() -> Self {
+                        let __default: #name #ty_generics = #value;
+                        __Shadow {
+                            #(#fields,)*
+                        }
+                    }
+                }
+            })
+        }
+    };
+    let default_attr = shadow_default.is_some().then(|| quote!(#[serde(default)]));
+    // so errors name the struct rather than its shadow
+    let expecting = container_metas
+        .iter()
+        .all(|m| !m.path().is_ident("expecting"))
+        .then(|| {
+            let expecting = format!("struct {}", name);
+            quote!(#[serde(expecting = #expecting)])
+        });
+    let container_attrs = (!container_metas.is_empty()).then(|| {
+        let metas = container_metas.iter();
+        quote!(#[serde(#(#metas),*)])
+    });
+    let mut impl_generics = generics.clone();
+    impl_generics.params.insert(0, parse_quote!('de));
+    impl_generics
+        .make_where_clause()
+        .predicates
+        .push(parse_quote!(__Shadow #ty_generics: #serde::Deserialize<'de>));
+    let (impl_generics, _, impl_where_clause) = impl_generics.split_for_impl();
+
+    Ok(quote! {
+        const _: () = {
+            #[derive(#serde::Deserialize)]
+            #[serde(crate = "::serde_bencode::__private::serde")]
+            #expecting
+            #container_attrs
+            #default_attr
+            struct __Shadow #generics #where_clause {
+                #(#shadow_fields,)*
+            }
+
+            #shadow_default
+
+            impl #impl_generics #serde::Deserialize<'de> for #name #ty_generics #impl_where_clause {
+                fn deserialize<__D>(deserializer: __D) -> ::core::result::Result<Self, __D::Error>
+                where
+                    __D: #serde::Deserializer<'de>,
+                {
+                    let __shadow = <__Shadow #ty_generics as #serde::Deserialize<'de>>::deserialize(
+                        deserializer,
+                    )?;
+                    ::core::result::Result::Ok(#name {
+                        #(#inits,)*
+                    })
+                }
+            }
+        };
+    })
+}
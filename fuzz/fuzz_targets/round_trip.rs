@@ -0,0 +1,17 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use serde_bencode::{from_bytes, to_bytes, Value};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(value) = from_bytes::<Value>(data) else {
+        return;
+    };
+    let bytes = to_bytes(&value).unwrap();
+    assert_eq!(value, from_bytes::<Value>(&bytes).unwrap());
+    // the encoding is canonical, so it's a fixed point
+    assert_eq!(
+        bytes,
+        to_bytes(&from_bytes::<Value>(&bytes).unwrap()).unwrap()
+    );
+});
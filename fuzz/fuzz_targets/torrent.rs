@@ -0,0 +1,19 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use serde_bencode::from_bytes;
+use serde_bencode::torrent::tracker::{AnnounceResponse, ScrapeResponse};
+use serde_bencode::torrent::Torrent;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(torrent) = from_bytes::<Torrent>(data) {
+        let _ = torrent.validate();
+        let _ = torrent.trackers();
+        if let Ok(info_hash) = serde_bencode::info_hash_v1(data) {
+            let _ = torrent.magnet(&info_hash);
+        }
+        let _ = serde_bencode::to_bytes(&torrent);
+    }
+    let _ = from_bytes::<AnnounceResponse>(data);
+    let _ = from_bytes::<ScrapeResponse>(data);
+});
@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use serde_bencode::{from_bytes, from_reader, Value};
+
+fuzz_target!(|data: &[u8]| {
+    let value = from_bytes::<Value>(data);
+    // reading in chunks must agree with reading from memory
+    assert_eq!(value.ok(), from_reader::<_, Value>(data).ok());
+    let _ = serde_bencode::validate(data);
+});
@@ -0,0 +1,324 @@
+use futures_core::Stream;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+use crate::de::from_bytes;
+use crate::error::{Error, Result};
+use crate::frame::{Frame, Framer, DEFAULT_MAX_FRAME_LENGTH};
+use crate::ser::{to_bytes_with_options, SerializerOptions};
+
+/// Reads one value from `reader` and deserializes it. Reads go through the
+/// reader's buffer, a chunk at a time, and only the bytes of the value are
+/// consumed: anything after it stays buffered for the next call. Wrap a
+/// plain `AsyncRead` in a `tokio::io::BufReader`.
+///
+/// Reading doesn't block, but decoding isn't incremental: deserializing
+/// can't wait on the reader part way through, so the bytes of the whole
+/// value are collected in a `Vec` and only decoded once the last of them
+/// arrives. Memory use follows the encoded length of the value, which can
+/// be at most 8 MiB; see `from_async_reader_with`.
+pub async fn from_async_reader<R, T>(reader: R) -> Result<T>
+where
+    R: AsyncBufRead + Unpin,
+    T: DeserializeOwned,
+{
+    from_async_reader_with(reader, DEFAULT_MAX_FRAME_LENGTH).await
+}
+
+/// Like `from_async_reader`, but fails with `Error::FrameTooLong` once the
+/// value is longer than `max_frame_length`.
+pub async fn from_async_reader_with<R, T>(mut reader: R, max_frame_length: usize) -> Result<T>
+where
+    R: AsyncBufRead + Unpin,
+    T: DeserializeOwned,
+{
+    let mut buf = Vec::new();
+    let mut framer = Framer::new();
+    loop {
+        let chunk = reader.fill_buf().await?;
+        if chunk.is_empty() {
+            return Err(Error::Eof);
+        }
+        let start = buf.len();
+        buf.extend_from_slice(chunk);
+        match framer.advance_capped(&buf, max_frame_length)? {
+            Frame::Complete(len) => {
+                reader.consume(len - start);
+                return from_bytes(&buf[..len]);
+            }
+            Frame::Incomplete(_) => reader.consume(buf.len() - start),
+        }
+    }
+}
+
+/// Yields each value read from an `AsyncRead` in turn, ending cleanly when
+/// the input ends between values. Like `from_async_reader`, each value is
+/// held in memory in full before it's decoded.
+pub struct AsyncStreamDeserializer<R, T> {
+    reader: R,
+    buf: Vec<u8>,
+    framer: Framer,
+    max_frame_length: usize,
+    done: bool,
+    marker: PhantomData<fn() -> T>,
+}
+
+// how much is read at a time
+const CHUNK: usize = 8 * 1024;
+
+impl<R, T> AsyncStreamDeserializer<R, T>
+where
+    R: AsyncRead + Unpin,
+    T: DeserializeOwned,
+{
+    pub fn new(reader: R) -> Self {
+        AsyncStreamDeserializer {
+            reader,
+            buf: Vec::new(),
+            framer: Framer::new(),
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+            done: false,
+            marker: PhantomData,
+        }
+    }
+
+    /// Limits how long one encoded value can be, 8 MiB by default. Past it,
+    /// the stream fails with `Error::FrameTooLong`.
+    pub fn set_max_frame_length(&mut self, len: usize) {
+        self.max_frame_length = len;
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn fail(&mut self, e: Error) -> Poll<Option<Result<T>>> {
+        self.done = true;
+        Poll::Ready(Some(Err(e)))
+    }
+}
+
+impl<R, T> Stream for AsyncStreamDeserializer<R, T>
+where
+    R: AsyncRead + Unpin,
+    T: DeserializeOwned,
+{
+    type Item = Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<T>>> {
+        let this = self.get_mut();
+        while !this.done {
+            match this.framer.advance_capped(&this.buf, this.max_frame_length) {
+                Err(e) => return this.fail(e),
+                Ok(Frame::Complete(len)) => {
+                    let item = from_bytes(&this.buf[..len]);
+                    this.buf.drain(..len);
+                    return Poll::Ready(Some(item));
+                }
+                Ok(Frame::Incomplete(_)) => {}
+            }
+
+            let start = this.buf.len();
+            this.buf.resize(start + CHUNK, 0);
+            let mut read = ReadBuf::new(&mut this.buf[start..]);
+            let polled = Pin::new(&mut this.reader).poll_read(cx, &mut read);
+            let filled = read.filled().len();
+            this.buf.truncate(start + filled);
+            match polled {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(e)) => return this.fail(e.into()),
+                Poll::Ready(Ok(())) if filled == 0 => {
+                    if !this.buf.is_empty() {
+                        return this.fail(Error::Eof);
+                    }
+                    this.done = true;
+                }
+                Poll::Ready(Ok(())) => {}
+            }
+        }
+        Poll::Ready(None)
+    }
+}
+
+/// Serializes `value` and writes it to `writer`.
+///
+/// The whole encoding is built in a `Vec` first, so memory use follows the
+/// encoded size of `value`, and then written without blocking.
+/// Serialization can't wait on the writer part way through, and a
+/// dictionary has to be complete before it can be sorted and written.
+pub async fn to_async_writer<W, T>(writer: W, value: &T) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+    T: ?Sized + Serialize,
+{
+    to_async_writer_with_options(writer, value, SerializerOptions::default()).await
+}
+
+pub async fn to_async_writer_with_options<W, T>(
+    mut writer: W,
+    value: &T,
+    options: SerializerOptions,
+) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+    T: ?Sized + Serialize,
+{
+    let bytes = to_bytes_with_options(value, options)?;
+    Ok(writer.write_all(&bytes).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use tokio::io::{AsyncReadExt, BufReader};
+
+    use std::collections::HashMap;
+    use std::future::poll_fn;
+    use std::pin::Pin;
+
+    use futures_core::Stream;
+
+    use super::{
+        from_async_reader, from_async_reader_with, to_async_writer, to_async_writer_with_options,
+        AsyncStreamDeserializer,
+    };
+    use crate::error::Error;
+    use crate::ser::{NonePolicy, SerializerOptions};
+    use crate::value::Value;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Response {
+        interval: i64,
+        peers: Vec<u8>,
+    }
+
+    #[tokio::test]
+    async fn test_from_async_reader() {
+        let (mut tx, rx) = tokio::io::duplex(4);
+        tokio::spawn(async move {
+            use tokio::io::AsyncWriteExt;
+            tx.write_all(b"d8:intervali1800e5:peersli1ei2eeei7ei8e")
+                .await
+                .unwrap();
+        });
+        let mut rx = BufReader::new(rx);
+        let response: Response = from_async_reader(&mut rx).await.unwrap();
+        assert_eq!(
+            Response {
+                interval: 1800,
+                peers: vec![1, 2],
+            },
+            response
+        );
+        // what was read past the value is kept for the next one
+        assert_eq!(7, from_async_reader::<_, i64>(&mut rx).await.unwrap());
+        let mut rest = Vec::new();
+        rx.read_to_end(&mut rest).await.unwrap();
+        assert_eq!(b"i8e", &rest[..]);
+
+        // one value spread over many chunks
+        let input = crate::to_bytes(&vec!["x".repeat(10_000); 4]).unwrap();
+        let mut reader = BufReader::new(&input[..]);
+        let value: Vec<String> = from_async_reader(&mut reader).await.unwrap();
+        assert_eq!(vec!["x".repeat(10_000); 4], value);
+    }
+
+    #[tokio::test]
+    async fn test_from_async_reader_eof() {
+        let result: Result<i64, _> = from_async_reader(&b"i12"[..]).await;
+        assert_eq!(Err(Error::Eof), result);
+    }
+
+    #[tokio::test]
+    async fn test_from_async_reader_max_frame_length() {
+        let result: Result<Value, _> = from_async_reader(&b"999999999999:"[..]).await;
+        assert_eq!(Err(Error::FrameTooLong), result);
+        let result: Result<Value, _> = from_async_reader_with(&b"li1ei2ei3ee"[..], 8).await;
+        assert_eq!(Err(Error::FrameTooLong), result);
+        let result: Result<Value, _> = from_async_reader_with(&b"l1:ae"[..], 8).await;
+        assert_eq!(Ok(bencode!(["a"])), result);
+    }
+
+    #[tokio::test]
+    async fn test_to_async_writer() {
+        let (tx, rx) = tokio::io::duplex(4);
+        let writer = tokio::spawn(async move {
+            let mut tx = tx;
+            to_async_writer(&mut tx, &(1800, "peers")).await
+        });
+        let value: (i64, String) = from_async_reader(BufReader::new(rx)).await.unwrap();
+        assert_eq!((1800, "peers".to_string()), value);
+        assert_eq!(Ok(()), writer.await.unwrap());
+
+        let mut out = Vec::new();
+        let options = SerializerOptions::new().none_policy(NonePolicy::Error);
+        assert_eq!(
+            Err(Error::Unsupported("none")),
+            to_async_writer_with_options(&mut out, &None::<i64>, options).await
+        );
+        assert!(out.is_empty());
+
+        // a value much bigger than the pipe
+        let value: HashMap<String, Vec<String>> = (0..100)
+            .map(|i| (i.to_string(), vec!["x".repeat(100); 10]))
+            .collect();
+        let (tx, rx) = tokio::io::duplex(64);
+        let expected = crate::to_bytes(&value).unwrap();
+        let writer = tokio::spawn(async move {
+            let mut tx = tx;
+            to_async_writer(&mut tx, &value).await
+        });
+        let mut written = Vec::new();
+        BufReader::new(rx).read_to_end(&mut written).await.unwrap();
+        assert_eq!(Ok(()), writer.await.unwrap());
+        assert_eq!(expected, written);
+    }
+
+    async fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+        poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+    }
+
+    #[tokio::test]
+    async fn test_stream_deserializer() {
+        let (tx, rx) = tokio::io::duplex(3);
+        tokio::spawn(async move {
+            use tokio::io::AsyncWriteExt;
+            let mut tx = tx;
+            tx.write_all(b"i1ed1:ai2ee").await.unwrap();
+            tx.write_all(b"l3:abce").await.unwrap();
+        });
+        let mut stream = AsyncStreamDeserializer::<_, Value>::new(rx);
+        let mut values = Vec::new();
+        while let Some(v) = next(&mut stream).await {
+            values.push(v.unwrap());
+        }
+        assert_eq!(
+            vec![bencode!(1), bencode!({ "a": 2 }), bencode!(["abc"])],
+            values
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_deserializer_truncated() {
+        let mut stream = AsyncStreamDeserializer::<_, i64>::new(&b"i1ei2"[..]);
+        assert_eq!(Some(Ok(1)), next(&mut stream).await);
+        assert_eq!(Some(Err(Error::Eof)), next(&mut stream).await);
+        assert_eq!(None, next(&mut stream).await);
+    }
+    #[tokio::test]
+    async fn test_stream_deserializer_max_frame_length() {
+        let mut stream = AsyncStreamDeserializer::<_, Value>::new(&b"l1:aeli1ei2ei3ee"[..]);
+        stream.set_max_frame_length(8);
+        assert_eq!(Some(Ok(bencode!(["a"]))), next(&mut stream).await);
+        assert_eq!(Some(Err(Error::FrameTooLong)), next(&mut stream).await);
+        assert_eq!(None, next(&mut stream).await);
+
+        let mut stream = AsyncStreamDeserializer::<_, Value>::new(&b"9:a"[..]);
+        stream.set_max_frame_length(8);
+        assert_eq!(Some(Err(Error::FrameTooLong)), next(&mut stream).await);
+    }
+}
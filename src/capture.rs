@@ -0,0 +1,224 @@
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+
+// The newtype struct name the `Deserializer` recognizes; nothing else
+// should ask for it.
+pub(crate) const CAPTURE: &str = "$serde_bencode::private::Captured";
+
+/// A value along with the exact bytes it was decoded from, such as a
+/// torrent's `info` dictionary and the input to its info-hash. The bytes
+/// are copied as they were, even where they aren't canonical.
+///
+/// Only this crate's `Deserializer` can capture bytes; others fail.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct Captured<T> {
+    pub value: T,
+    pub bytes: Vec<u8>,
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Captured<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Captured<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(CAPTURE, CapturedVisitor(PhantomData))
+    }
+}
+
+struct CapturedVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for CapturedVisitor<T> {
+    type Value = Captured<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a value read by serde_bencode's Deserializer")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Captured<T>, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let value = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let RawBytes(bytes) = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        Ok(Captured { value, bytes })
+    }
+
+    fn visit_newtype_struct<D>(self, _: D) -> Result<Captured<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Err(de::Error::custom(
+            "raw bytes can only be captured by serde_bencode's Deserializer",
+        ))
+    }
+}
+
+// `Vec<u8>` only deserializes from a sequence
+struct RawBytes(Vec<u8>);
+
+impl<'de> Deserialize<'de> for RawBytes {
+    fn deserialize<D>(deserializer: D) -> Result<RawBytes, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct BytesVisitor;
+
+        impl Visitor<'_> for BytesVisitor {
+            type Value = RawBytes;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("bytes")
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<RawBytes, E> {
+                Ok(RawBytes(v.to_vec()))
+            }
+        }
+
+        deserializer.deserialize_bytes(BytesVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Captured;
+    use crate::{from_bytes, from_value, Value};
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Info {
+        length: i64,
+        name: String,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Torrent {
+        announce: String,
+        info: Captured<Info>,
+    }
+
+    const INPUT: &[u8] = b"d8:announce3:url4:infod6:lengthi03e4:name1:xee";
+
+    #[test]
+    fn test_captured() {
+        let expected = Torrent {
+            announce: "url".to_string(),
+            info: Captured {
+                value: Info {
+                    length: 3,
+                    name: "x".to_string(),
+                },
+                // as it was, leading zero and all
+                bytes: b"d6:lengthi03e4:name1:xe".to_vec(),
+            },
+        };
+        assert_eq!(Ok(&expected), from_bytes::<Torrent>(INPUT).as_ref());
+        #[cfg(feature = "std")]
+        assert_eq!(expected, crate::from_reader::<_, Torrent>(INPUT).unwrap());
+
+        let nested: Captured<Vec<Captured<i64>>> = from_bytes(b"li1ei-2ee").unwrap();
+        assert_eq!(b"li1ei-2ee", &nested.bytes[..]);
+        assert_eq!(b"i-2e", &nested.value[1].bytes[..]);
+
+        assert!(from_value::<Captured<i64>>(Value::Int(1)).is_err());
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive_raw() {
+        use crate::BencodeDeserialize;
+
+        #[derive(BencodeDeserialize, Debug, PartialEq)]
+        struct Torrent<'a> {
+            #[bencode(raw = "announce_bytes")]
+            announce: &'a str,
+            announce_bytes: Box<[u8]>,
+            #[bencode(raw)]
+            #[serde(rename = "info")]
+            metadata: Info,
+            metadata_raw: Vec<u8>,
+        }
+
+        let torrent: Torrent = from_bytes(INPUT).unwrap();
+        assert_eq!("url", torrent.announce);
+        assert_eq!(b"3:url", &torrent.announce_bytes[..]);
+        assert_eq!(3, torrent.metadata.length);
+        assert_eq!(b"d6:lengthi03e4:name1:xe", &torrent.metadata_raw[..]);
+
+        let err = from_bytes::<Torrent>(b"i1e").unwrap_err();
+        assert!(err.to_string().contains("struct Torrent"), "{}", err);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive_serde_attrs() {
+        use crate::BencodeDeserialize;
+        use std::borrow::Cow;
+
+        #[derive(BencodeDeserialize, Debug, PartialEq)]
+        #[serde(default, rename_all = "kebab-case")]
+        struct Defaulted {
+            #[serde(rename = "borrower")]
+            #[bencode(raw)]
+            name: Cow<'static, str>,
+            name_raw: Vec<u8>,
+            piece_length: i64,
+        }
+
+        impl Default for Defaulted {
+            fn default() -> Self {
+                Defaulted {
+                    name: "none".into(),
+                    name_raw: b"unused".to_vec(),
+                    piece_length: 7,
+                }
+            }
+        }
+
+        let v: Defaulted = from_bytes(b"d12:piece-lengthi3ee").unwrap();
+        assert_eq!(
+            Defaulted {
+                name: "none".into(),
+                name_raw: Vec::new(),
+                piece_length: 3,
+            },
+            v
+        );
+        // neither `'static` nor `borrower` means the field borrows
+        let v: Defaulted = from_bytes(b"d8:borrower1:xe").unwrap();
+        assert_eq!("x", v.name);
+        assert_eq!(b"1:x", &v.name_raw[..]);
+
+        fn seven() -> WithFn {
+            WithFn { a: 7, b: 8 }
+        }
+
+        #[derive(BencodeDeserialize, Debug, PartialEq)]
+        #[serde(default = "seven")]
+        struct WithFn {
+            a: i64,
+            b: i64,
+        }
+
+        assert_eq!(WithFn { a: 1, b: 8 }, from_bytes(b"d1:ai1ee").unwrap());
+        let err = from_bytes::<WithFn>(b"i1e").unwrap_err();
+        assert!(err.to_string().contains("struct WithFn"), "{}", err);
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn test_captured_buf() {
+        use bytes::Buf;
+
+        let buf = (&INPUT[..20]).chain(&INPUT[20..]);
+        let torrent: Torrent = crate::from_buf(buf).unwrap();
+        assert_eq!(b"d6:lengthi03e4:name1:xe", &torrent.info.bytes[..]);
+    }
+}
@@ -0,0 +1,182 @@
+use std::marker::PhantomData;
+
+use bytes::{Buf, BytesMut};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::de::from_bytes;
+use crate::error::Error;
+use crate::frame::{measure, Frame, Framer, DEFAULT_MAX_FRAME_LENGTH};
+use crate::ser::to_bytes_mut;
+use crate::value::Value;
+
+// Most that's reserved at once for a partial frame, so a huge claimed
+// length doesn't allocate up front.
+const MAX_RESERVE: usize = 64 * 1024;
+
+/// Frames complete values off a byte stream and decodes each one into `T`,
+/// and encodes anything serializable.
+pub struct BencodeCodec<T = Value> {
+    framer: Framer,
+    max_frame_length: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> BencodeCodec<T> {
+    pub fn new() -> Self {
+        BencodeCodec {
+            framer: Framer::new(),
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+            marker: PhantomData,
+        }
+    }
+
+    /// Limits how long one encoded value can be, 8 MiB by default. Past it,
+    /// decoding fails with `Error::FrameTooLong`.
+    pub fn set_max_frame_length(&mut self, len: usize) {
+        self.max_frame_length = len;
+    }
+
+    pub fn max_frame_length(&self) -> usize {
+        self.max_frame_length
+    }
+}
+
+impl<T> Default for BencodeCodec<T> {
+    fn default() -> Self {
+        BencodeCodec::new()
+    }
+}
+
+impl<T> Clone for BencodeCodec<T> {
+    fn clone(&self) -> Self {
+        BencodeCodec {
+            framer: self.framer.clone(),
+            max_frame_length: self.max_frame_length,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for BencodeCodec<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("BencodeCodec")
+            .field("max_frame_length", &self.max_frame_length)
+            .finish()
+    }
+}
+
+impl<T: DeserializeOwned> Decoder for BencodeCodec<T> {
+    type Item = T;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<T>, Error> {
+        let frame = match self.framer.advance_capped(src, self.max_frame_length) {
+            Err(Error::FrameTooLong) => {
+                // a value that arrived whole is skipped over
+                if let Ok(Some(len)) = measure(src) {
+                    src.advance(len);
+                }
+                return Err(Error::FrameTooLong);
+            }
+            frame => frame?,
+        };
+        match frame {
+            Frame::Complete(len) => {
+                let item = from_bytes(&src[..len]);
+                src.advance(len);
+                item.map(Some)
+            }
+            Frame::Incomplete(n) => {
+                src.reserve(n.min(MAX_RESERVE));
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl<T, I: Serialize> Encoder<I> for BencodeCodec<T> {
+    type Error = Error;
+
+    fn encode(&mut self, item: I, dst: &mut BytesMut) -> Result<(), Error> {
+        to_bytes_mut(&item, dst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+    use serde::{Deserialize, Serialize};
+    use tokio_util::codec::{Decoder, Encoder};
+
+    use super::BencodeCodec;
+    use crate::error::Error;
+    use crate::value::Value;
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Ping {
+        t: String,
+        y: String,
+    }
+
+    #[test]
+    fn test_decode_partial() {
+        let mut codec = BencodeCodec::<Ping>::new();
+        let mut buf = BytesMut::new();
+        for chunk in [&b"d1:t2:a"[..], b"a1:y1:q", b"ed1:t2:bb1:y1:re5:"] {
+            buf.extend_from_slice(chunk);
+            if let Some(ping) = codec.decode(&mut buf).unwrap() {
+                assert_eq!("aa", ping.t);
+            }
+        }
+        let ping = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!("bb", ping.t);
+        assert_eq!(None, codec.decode(&mut buf).unwrap());
+        assert_eq!(b"5:", &buf[..]);
+    }
+
+    #[test]
+    fn test_max_frame_length() {
+        let mut codec = BencodeCodec::<Value>::new();
+        assert_eq!(8 * 1024 * 1024, codec.max_frame_length());
+        codec.set_max_frame_length(8);
+
+        let mut buf = BytesMut::from(&b"l1:ae"[..]);
+        assert_eq!(Some(bencode!(["a"])), codec.decode(&mut buf).unwrap());
+
+        // a partial value that's already too long
+        let mut buf = BytesMut::from(&b"li1ei2ei3e"[..]);
+        assert_eq!(Err(Error::FrameTooLong), codec.decode(&mut buf));
+        // a byte string claiming more than the limit
+        let mut buf = BytesMut::from(&b"9:a"[..]);
+        assert_eq!(Err(Error::FrameTooLong), codec.decode(&mut buf));
+
+        // a value that arrives whole is skipped over
+        let mut codec = BencodeCodec::<Value>::new();
+        let mut buf = BytesMut::from(&b"li1ei"[..]);
+        assert_eq!(None, codec.decode(&mut buf).unwrap());
+        codec.set_max_frame_length(8);
+        buf.extend_from_slice(b"2ei3eei4e");
+        assert_eq!(Err(Error::FrameTooLong), codec.decode(&mut buf));
+        assert_eq!(Some(bencode!(4)), codec.decode(&mut buf).unwrap());
+    }
+
+    #[test]
+    fn test_encode() {
+        let mut codec = BencodeCodec::<Value>::default();
+        let mut buf = BytesMut::new();
+        let ping = Ping {
+            t: "aa".to_string(),
+            y: "q".to_string(),
+        };
+        codec.encode(&ping, &mut buf).unwrap();
+        codec.encode(bencode!([1]), &mut buf).unwrap();
+        assert_eq!(
+            Some(bencode!({ "t": "aa", "y": "q" })),
+            codec.decode(&mut buf).unwrap()
+        );
+        assert_eq!(Some(bencode!([1])), codec.decode(&mut buf).unwrap());
+        assert!(codec.decode(&mut BytesMut::from(&b"x"[..])).is_err());
+    }
+}
@@ -0,0 +1,284 @@
+//! `#[serde(with = ...)]` helpers for the compact peer encoding of BEP 23
+//! and BEP 7: an IPv4 address and port packed into 6 bytes, or an IPv6
+//! address and port into 18, all in network byte order. `v4` and `v6`
+//! handle a single address; `v4_list` and `v6_list` handle byte strings of
+//! several concatenated ones.
+
+use alloc::format;
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+use core::net::{Ipv4Addr, Ipv6Addr, SocketAddrV4, SocketAddrV6};
+use serde::de::{self, Deserializer, Visitor};
+use serde::Serializer;
+
+/// Something with a fixed-size compact encoding.
+pub(crate) trait Compact: Sized {
+    const LEN: usize;
+
+    fn write(&self, out: &mut Vec<u8>);
+
+    /// `bytes` is always exactly `LEN` long
+    fn read(bytes: &[u8]) -> Self;
+}
+
+impl Compact for SocketAddrV4 {
+    const LEN: usize = 6;
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend(self.ip().octets());
+        out.extend(self.port().to_be_bytes());
+    }
+
+    fn read(bytes: &[u8]) -> Self {
+        let ip: [u8; 4] = bytes[..4].try_into().unwrap();
+        SocketAddrV4::new(Ipv4Addr::from(ip), u16::from_be_bytes([bytes[4], bytes[5]]))
+    }
+}
+
+impl Compact for SocketAddrV6 {
+    const LEN: usize = 18;
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend(self.ip().octets());
+        out.extend(self.port().to_be_bytes());
+    }
+
+    fn read(bytes: &[u8]) -> Self {
+        let ip: [u8; 16] = bytes[..16].try_into().unwrap();
+        let port = u16::from_be_bytes([bytes[16], bytes[17]]);
+        SocketAddrV6::new(Ipv6Addr::from(ip), port, 0, 0)
+    }
+}
+
+// a node id followed by its address, as in the DHT's `nodes` (BEP 5)
+impl<A: Compact> Compact for ([u8; 20], A) {
+    const LEN: usize = 20 + A::LEN;
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend(self.0);
+        self.1.write(out);
+    }
+
+    fn read(bytes: &[u8]) -> Self {
+        (bytes[..20].try_into().unwrap(), A::read(&bytes[20..]))
+    }
+}
+
+pub(crate) fn encode_list<T: Compact>(items: &[T]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(items.len() * T::LEN);
+    for item in items {
+        item.write(&mut out);
+    }
+    out
+}
+
+/// Fails if `bytes` isn't a whole number of entries.
+pub(crate) fn decode_list<T: Compact, E: de::Error>(bytes: &[u8]) -> Result<Vec<T>, E> {
+    if !bytes.len().is_multiple_of(T::LEN) {
+        let expected = format!("a multiple of {} bytes", T::LEN);
+        return Err(E::invalid_length(bytes.len(), &expected.as_str()));
+    }
+    Ok(bytes.chunks_exact(T::LEN).map(T::read).collect())
+}
+
+fn serialize_one<T: Compact, S: Serializer>(item: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut out = Vec::with_capacity(T::LEN);
+    item.write(&mut out);
+    serializer.serialize_bytes(&out)
+}
+
+fn deserialize_one<'de, T: Compact, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+    deserializer.deserialize_bytes(CompactVisitor::<T>(PhantomData))
+}
+
+pub(crate) fn deserialize_list<'de, T: Compact, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<T>, D::Error> {
+    deserializer.deserialize_bytes(ListVisitor::<T>(PhantomData))
+}
+
+struct CompactVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Compact> Visitor<'de> for CompactVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a {}-byte compact address", T::LEN)
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<T, E>
+    where
+        E: de::Error,
+    {
+        if v.len() != T::LEN {
+            return Err(E::invalid_length(v.len(), &self));
+        }
+        Ok(T::read(v))
+    }
+
+    // compact strings that happen to be valid UTF-8
+    fn visit_str<E>(self, v: &str) -> Result<T, E>
+    where
+        E: de::Error,
+    {
+        self.visit_bytes(v.as_bytes())
+    }
+}
+
+struct ListVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Compact> Visitor<'de> for ListVisitor<T> {
+    type Value = Vec<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a byte string of {}-byte entries", T::LEN)
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Vec<T>, E>
+    where
+        E: de::Error,
+    {
+        decode_list(v)
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Vec<T>, E>
+    where
+        E: de::Error,
+    {
+        decode_list(v.as_bytes())
+    }
+}
+
+/// One `SocketAddrV4` as 6 bytes.
+pub mod v4 {
+    use core::net::SocketAddrV4;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(addr: &SocketAddrV4, serializer: S) -> Result<S::Ok, S::Error> {
+        super::serialize_one(addr, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<SocketAddrV4, D::Error> {
+        super::deserialize_one(deserializer)
+    }
+}
+
+/// One `SocketAddrV6` as 18 bytes.
+pub mod v6 {
+    use core::net::SocketAddrV6;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(addr: &SocketAddrV6, serializer: S) -> Result<S::Ok, S::Error> {
+        super::serialize_one(addr, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<SocketAddrV6, D::Error> {
+        super::deserialize_one(deserializer)
+    }
+}
+
+/// A `Vec<SocketAddrV4>` as concatenated 6-byte entries.
+pub mod v4_list {
+    use alloc::vec::Vec;
+    use core::net::SocketAddrV4;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        addrs: &[SocketAddrV4],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&super::encode_list(addrs))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<SocketAddrV4>, D::Error> {
+        super::deserialize_list(deserializer)
+    }
+}
+
+/// A `Vec<SocketAddrV6>` as concatenated 18-byte entries.
+pub mod v6_list {
+    use alloc::vec::Vec;
+    use core::net::SocketAddrV6;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        addrs: &[SocketAddrV6],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&super::encode_list(addrs))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<SocketAddrV6>, D::Error> {
+        super::deserialize_list(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_bytes, to_bytes};
+    use serde::{Deserialize, Serialize};
+    use std::net::{SocketAddrV4, SocketAddrV6};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Peer {
+        #[serde(with = "super::v4")]
+        ip4: SocketAddrV4,
+        #[serde(with = "super::v6")]
+        ip6: SocketAddrV6,
+    }
+
+    #[test]
+    fn test_single() {
+        let mut input = b"d3:ip46:\x7f\x00\x00\x01\x1a\xe13:ip618:".to_vec();
+        input.extend([0; 15]);
+        input.extend([1, 0, 80, b'e']);
+        let peer: Peer = from_bytes(&input).unwrap();
+        assert_eq!("127.0.0.1:6881", peer.ip4.to_string());
+        assert_eq!("[::1]:80", peer.ip6.to_string());
+        assert_eq!(input, to_bytes(&peer).unwrap());
+
+        let err = from_bytes::<Peer>(b"d3:ip45:abcdee").unwrap_err();
+        assert_eq!(
+            "invalid length 5, expected a 6-byte compact address",
+            err.inner().to_string()
+        );
+    }
+
+    #[test]
+    fn test_list() {
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Peers {
+            #[serde(with = "super::v4_list")]
+            added: Vec<SocketAddrV4>,
+            #[serde(with = "super::v6_list")]
+            added6: Vec<SocketAddrV6>,
+        }
+
+        let input = b"d5:added12:\x0a\x00\x00\x01\x00\x01\x0a\x00\x00\x02\x00\x026:added60:e";
+        let peers: Peers = from_bytes(input).unwrap();
+        assert_eq!(
+            vec![
+                "10.0.0.1:1".parse::<SocketAddrV4>().unwrap(),
+                "10.0.0.2:2".parse().unwrap(),
+            ],
+            peers.added
+        );
+        assert!(peers.added6.is_empty());
+        assert_eq!(input.to_vec(), to_bytes(&peers).unwrap());
+
+        let err = from_bytes::<Peers>(b"d5:added7:abcdefg6:added60:e").unwrap_err();
+        assert_eq!(
+            "invalid length 7, expected a multiple of 6 bytes",
+            err.inner().to_string()
+        );
+    }
+}
@@ -1,77 +1,484 @@
+use serde::de::value::{BorrowedBytesDeserializer, BytesDeserializer};
+#[cfg(feature = "std")]
+use serde::de::DeserializeOwned;
 use serde::de::{
-    self, Deserialize, DeserializeSeed, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor,
+    self, Deserialize, DeserializeSeed, EnumAccess, MapAccess, SeqAccess, Unexpected,
+    VariantAccess, Visitor,
 };
 use serde::forward_to_deserialize_any;
 
+use crate::capture::CAPTURE;
 use crate::error::{Error, Result};
-use std::ops::{AddAssign, MulAssign, Neg};
+use crate::frame::count_items;
+#[cfg(feature = "std")]
+use crate::read::IoRead;
+use crate::read::{Read, Reference, SliceRead, TrustedRead};
+use crate::validate::{check_structure, ViolationKind, Warning};
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::mem;
+use core::str;
+#[cfg(feature = "std")]
+use std::fs;
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::path::Path;
+
+pub struct Deserializer<R> {
+    read: R,
+    // byte strings that can't be borrowed from the input are read into this
+    scratch: Vec<u8>,
+    // spare buffers for dictionary keys that can't be borrowed either
+    keys: Vec<Vec<u8>>,
+    // only collected once asked for
+    warnings: Option<Vec<Warning>>,
+    // how many more lists and dictionaries can be opened
+    remaining_depth: usize,
+    progress: Option<Progress>,
+}
 
-pub struct Deserializer<'de> {
-    input: &'de [u8],
+struct Progress {
+    f: Box<dyn FnMut(usize, u64) + Send>,
+    interval: usize,
+    // the offset to report at next
+    next: usize,
+    values: u64,
 }
 
-impl<'de> Deserializer<'de> {
+/// How deeply lists and dictionaries can nest before deserializing fails,
+/// unless changed with `Deserializer::set_max_depth`.
+pub(crate) const DEFAULT_MAX_DEPTH: usize = 128;
+
+impl<'de, R: Read<'de>> Deserializer<R> {
+    pub fn new(read: R) -> Self {
+        Deserializer {
+            read,
+            scratch: Vec::new(),
+            keys: Vec::new(),
+            warnings: None,
+            remaining_depth: DEFAULT_MAX_DEPTH,
+            progress: None,
+        }
+    }
+
+    /// Limits how deeply lists and dictionaries can nest, 128 by default.
+    /// Parsing is recursive, so the limit is what keeps hostile input from
+    /// overflowing the stack; raise it only as far as the stack allows.
+    pub fn set_max_depth(&mut self, depth: usize) {
+        self.remaining_depth = depth;
+    }
+
+    // runs `f` one level deeper, with the opening `l` or `d` already read
+    pub(crate) fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.remaining_depth = match self.remaining_depth.checked_sub(1) {
+            Some(depth) => depth,
+            None => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(offset = self.offset(), "nesting depth limit exceeded");
+                return Err(Error::DepthLimitExceeded);
+            }
+        };
+        #[cfg(feature = "tracing")]
+        tracing::trace!(offset = self.offset().saturating_sub(1), "container");
+        self.value_seen();
+        let result = f(self);
+        self.remaining_depth += 1;
+        result
+    }
+
+    /// Starts over on new input, so one configured deserializer can be used
+    /// for many messages. The depth limit, progress callback and whether
+    /// warnings are collected carry over, as do the buffers for byte
+    /// strings and keys; warnings not yet taken are dropped, and progress
+    /// counts start again. Returns the old input.
+    pub fn reset(&mut self, read: R) -> R {
+        let old = mem::replace(&mut self.read, read);
+        self.restart();
+        old
+    }
+
+    fn restart(&mut self) {
+        if let Some(warnings) = &mut self.warnings {
+            warnings.clear();
+        }
+        if let Some(progress) = &mut self.progress {
+            progress.next = self.read.offset().saturating_add(progress.interval);
+            progress.values = 0;
+        }
+    }
+
+    /// Starts collecting warnings about input that's accepted but isn't
+    /// canonical: unsorted or repeated keys, leading zeros and negative zero.
+    pub fn collect_warnings(&mut self) {
+        self.warnings.get_or_insert_with(Vec::new);
+    }
+
+    /// Returns the warnings collected so far.
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        self.warnings
+            .as_mut()
+            .map(core::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Calls `f` with the number of bytes consumed and values read so far,
+    /// counting dictionary keys, each time another `interval` bytes have
+    /// been consumed, and once more from `end`. It's checked as each value
+    /// starts, so a long byte string is reported after it's been read.
+    pub fn on_progress<F>(&mut self, interval: usize, f: F)
+    where
+        F: FnMut(usize, u64) + Send + 'static,
+    {
+        self.progress = Some(Progress {
+            f: Box::new(f),
+            interval,
+            next: self.read.offset().saturating_add(interval),
+            values: 0,
+        });
+    }
+
+    fn value_seen(&mut self) {
+        if let Some(progress) = &mut self.progress {
+            progress.values += 1;
+            let offset = self.read.offset();
+            if offset >= progress.next {
+                progress.next = offset.saturating_add(progress.interval);
+                (progress.f)(offset, progress.values);
+            }
+        }
+    }
+
+    fn warn(&mut self, offset: usize, kind: ViolationKind) {
+        if let Some(warnings) = &mut self.warnings {
+            warnings.push(Warning { offset, kind });
+        }
+    }
+
+    /// Checks that the whole input has been consumed.
+    pub fn end(&mut self) -> Result<()> {
+        match self.read.peek()? {
+            Some(_) => Err(Error::TrailingCharacters),
+            None => {
+                if let Some(progress) = &mut self.progress {
+                    (progress.f)(self.read.offset(), progress.values);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    pub(crate) fn offset(&self) -> usize {
+        self.read.offset()
+    }
+
+    /// Attaches the current offset, and the byte there for syntax errors.
+    pub(crate) fn locate(&mut self, error: Error) -> Error {
+        if let Error::Located { .. } = error {
+            return error;
+        }
+        let found = match error.inner() {
+            Error::Message(_) | Error::Io(_) => None,
+            _ => self.read.peek().ok().flatten(),
+        };
+        Error::Located {
+            offset: self.read.offset(),
+            found,
+            error: Box::new(error),
+        }
+    }
+}
+
+impl<'de> Deserializer<SliceRead<'de>> {
     pub fn from_bytes(input: &'de [u8]) -> Self {
-        Deserializer { input }
+        Deserializer::new(SliceRead::new(input))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: io::Read> Deserializer<IoRead<R>> {
+    pub fn from_reader(reader: R) -> Self {
+        Deserializer::new(IoRead::new(reader))
+    }
+
+    /// Like `reset`, but keeps the read buffer too. Anything read ahead
+    /// from the old reader is dropped.
+    pub fn reset_reader(&mut self, reader: R) -> R {
+        let old = self.read.reset(reader);
+        self.restart();
+        old
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl<B: bytes::Buf> Deserializer<crate::read::BytesRead<B>> {
+    pub fn from_buf(buf: B) -> Self {
+        Deserializer::new(crate::read::BytesRead::new(buf))
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl<'a> Deserializer<SliceRead<'a>> {
+    pub fn from_mmap(mmap: &'a memmap2::Mmap) -> Self {
+        Deserializer::from_bytes(mmap)
+    }
+
+    /// Reads a file mapped with `MappedFile::open`; byte strings can be
+    /// borrowed from it for as long as it is kept.
+    pub fn from_file(file: &'a MappedFile) -> Self {
+        Deserializer::from_bytes(&file.mmap)
     }
 }
 
+fn from_read<'de, R, T>(read: R) -> Result<T>
+where
+    R: Read<'de>,
+    T: Deserialize<'de>,
+{
+    from_deserializer(&mut Deserializer::new(read))
+}
+
+fn from_deserializer<'de, R, T>(deserializer: &mut Deserializer<R>) -> Result<T>
+where
+    R: Read<'de>,
+    T: Deserialize<'de>,
+{
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!(
+        "bencode::parse",
+        ty = core::any::type_name::<T>(),
+        len = deserializer.read.lookahead().map(<[u8]>::len),
+    )
+    .entered();
+    let result = T::deserialize(&mut *deserializer)
+        .and_then(|t| deserializer.end().map(|()| t))
+        .map_err(|e| deserializer.locate(e));
+    #[cfg(feature = "tracing")]
+    match &result {
+        Ok(_) => tracing::debug!(bytes = deserializer.offset(), "parsed"),
+        Err(e) => {
+            tracing::debug!(offset = deserializer.offset(), error = %e.inner(), "parse failed")
+        }
+    }
+    result
+}
+
+/// Byte strings and strings can borrow from `b`: deserializing a type made
+/// only of borrowed fields, integers, options and nested such types makes no
+/// heap allocations, unless there's an error.
 pub fn from_bytes<'a, T>(b: &'a [u8]) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    from_read(SliceRead::new(b))
+}
+
+/// Input that has been checked to be exactly one value the deserializer can
+/// parse, so it can be deserialized repeatedly, as different types, with
+/// byte strings sliced out without bounds checks. It needn't be canonical.
+/// Integers are still range-checked and the value still has to match the
+/// type.
+#[derive(Clone, Copy, Debug)]
+pub struct Validated<'a> {
+    input: &'a [u8],
+}
+
+impl<'a> Validated<'a> {
+    pub fn new(input: &'a [u8]) -> Result<Validated<'a>> {
+        check_structure(input)?;
+        Ok(Validated { input })
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.input
+    }
+
+    pub fn deserialize<T>(&self) -> Result<T>
+    where
+        T: Deserialize<'a>,
+    {
+        // Safety: `new` checked the input
+        from_read(unsafe { TrustedRead::new(self.input) })
+    }
+}
+
+/// Like `from_bytes`, but also returns warnings about anything in the input
+/// that was accepted but isn't canonical.
+pub fn from_bytes_with_warnings<'a, T>(b: &'a [u8]) -> Result<(T, Vec<Warning>)>
 where
     T: Deserialize<'a>,
 {
     let mut deserializer = Deserializer::from_bytes(b);
-    let t = T::deserialize(&mut deserializer)?;
-    if deserializer.input.is_empty() {
-        Ok(t)
-    } else {
-        Err(Error::TrailingCharacters)
+    deserializer.collect_warnings();
+    let t = from_deserializer(&mut deserializer)?;
+    Ok((t, deserializer.take_warnings()))
+}
+
+#[cfg(feature = "std")]
+/// Deserializes from a reader. Byte strings are copied, so `T` can't borrow
+/// from the input.
+pub fn from_reader<R, T>(reader: R) -> Result<T>
+where
+    R: io::Read,
+    T: DeserializeOwned,
+{
+    from_read(IoRead::new(reader))
+}
+
+#[cfg(feature = "std")]
+/// Buffers kept between calls to `from_reader_with`, so decoding many
+/// messages from readers doesn't allocate them afresh each time.
+#[derive(Debug, Default)]
+pub struct ReadContext {
+    buf: Vec<u8>,
+    scratch: Vec<u8>,
+    keys: Vec<Vec<u8>>,
+}
+
+#[cfg(feature = "std")]
+impl ReadContext {
+    pub fn new() -> Self {
+        ReadContext::default()
+    }
+}
+
+#[cfg(feature = "std")]
+/// Like `from_reader`, but borrows its buffers from `cx` and hands them back
+/// afterwards. As with `from_reader`, the reader may be read past the end of
+/// the value.
+pub fn from_reader_with<R, T>(reader: R, cx: &mut ReadContext) -> Result<T>
+where
+    R: io::Read,
+    T: DeserializeOwned,
+{
+    let mut de = Deserializer::new(IoRead::with_buffer(reader, mem::take(&mut cx.buf)));
+    de.scratch = mem::take(&mut cx.scratch);
+    de.keys = mem::take(&mut cx.keys);
+    let result = from_deserializer(&mut de);
+    cx.scratch = de.scratch;
+    cx.keys = de.keys;
+    cx.buf = de.read.into_buffer();
+    result
+}
+
+#[cfg(feature = "std")]
+/// Reads and deserializes the file at `path`.
+pub fn from_path<T, P>(path: P) -> Result<T>
+where
+    T: DeserializeOwned,
+    P: AsRef<Path>,
+{
+    let contents = fs::read(path)?;
+    from_bytes(&contents)
+}
+
+/// Deserializes from a `bytes::Buf`, such as chained buffers from an HTTP
+/// body, without first joining it into one slice.
+#[cfg(feature = "bytes")]
+pub fn from_buf<B, T>(buf: B) -> Result<T>
+where
+    B: bytes::Buf,
+    T: DeserializeOwned,
+{
+    from_read(crate::read::BytesRead::new(buf))
+}
+
+/// Deserializes from a memory-mapped file. Like `from_bytes`, byte strings
+/// can be borrowed, so large fields such as `pieces` are never copied.
+#[cfg(feature = "mmap")]
+pub fn from_mmap<'a, T>(mmap: &'a memmap2::Mmap) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    from_bytes(mmap)
+}
+
+/// A file mapped into memory, which values can borrow their byte strings
+/// from for as long as it is kept.
+#[cfg(feature = "mmap")]
+pub struct MappedFile {
+    mmap: memmap2::Mmap,
+}
+
+#[cfg(feature = "mmap")]
+impl MappedFile {
+    /// Maps `file` into memory.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while it is mapped; see
+    /// `memmap2::Mmap::map`.
+    pub unsafe fn open(file: &std::fs::File) -> Result<MappedFile> {
+        Ok(MappedFile {
+            mmap: memmap2::Mmap::map(file)?,
+        })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.mmap
+    }
+
+    /// Deserializes the whole file, like `from_bytes`.
+    pub fn deserialize<'a, T>(&'a self) -> Result<T>
+    where
+        T: Deserialize<'a>,
+    {
+        from_bytes(&self.mmap)
     }
 }
 
 // basic parsing functions
-impl<'de> Deserializer<'de> {
-    fn peek_byte(&self) -> Result<u8> {
-        match self.input.iter().next() {
-            Some(x) => Ok(*x),
-            _ => Err(Error::Eof),
-        }
+impl<'de, R: Read<'de>> Deserializer<R> {
+    pub(crate) fn peek_byte(&mut self) -> Result<u8> {
+        self.read.peek()?.ok_or(Error::Eof)
     }
 
-    fn next_byte(&mut self) -> Result<u8> {
-        let b = self.peek_byte()?;
-        self.input = &self.input[1..];
-        Ok(b)
+    pub(crate) fn next_byte(&mut self) -> Result<u8> {
+        self.read.next()?.ok_or(Error::Eof)
+    }
+
+    // leaves anything else unread, so errors point at it
+    fn expect_byte(&mut self, expected: u8, error: Error) -> Result<()> {
+        if self.peek_byte()? != expected {
+            return Err(error);
+        }
+        self.next_byte()?;
+        Ok(())
     }
 
     fn parse_unsigned<T>(&mut self) -> Result<T>
     where
-        T: AddAssign<T> + MulAssign<T> + From<u8>,
+        T: TryFrom<u64>,
     {
-        let mut int = match self.next_byte()? {
-            b @ b'0'..=b'9' => T::from(b - b'0'),
-            _ => {
-                return Err(Error::ExpectedInteger);
-            }
-        };
-        loop {
-            match self.input.iter().next() {
-                Some(b @ b'0'..=b'9') => {
-                    self.input = &self.input[1..];
-                    int *= T::from(10);
-                    int += T::from(b - b'0');
-                }
-                _ => {
-                    return Ok(int);
-                }
-            }
+        let start = self.read.offset();
+        let digits = self.read.read_digits(&mut self.scratch)?;
+        if digits.is_empty() {
+            return Err(Error::ExpectedInteger);
         }
+        let leading_zero = digits.len() > 1 && digits[0] == b'0';
+        // only ASCII digits, so too many of them is the only way parsing
+        // can fail
+        let int = digits
+            .iter()
+            .try_fold(0u64, |n, d| {
+                n.checked_mul(10)?.checked_add(u64::from(d - b'0'))
+            })
+            .and_then(|n| T::try_from(n).ok())
+            .ok_or(Error::IntegerOutOfRange);
+        if leading_zero && self.warnings.is_some() {
+            self.warn(start, ViolationKind::LeadingZero);
+        }
+        int
     }
 
     fn parse_signed<T>(&mut self) -> Result<T>
     where
-        T: Neg<Output = T> + AddAssign<T> + MulAssign<T> + From<i8> + From<u8>,
+        T: TryFrom<i128>,
     {
+        // the `i`, for warnings
+        let start = self.read.offset().saturating_sub(1);
         let is_negative = match self.peek_byte()? {
             b'-' => {
                 self.next_byte()?;
@@ -80,38 +487,62 @@ impl<'de> Deserializer<'de> {
             _ => false,
         };
 
-        let mut num: T = self.parse_unsigned::<T>()?;
-        if is_negative {
-            num = -num;
-        }
-        return Ok(num);
+        // wide enough for any u64 or i64
+        let magnitude = i128::from(self.parse_unsigned::<u64>()?);
+        let num = match is_negative {
+            true => {
+                if magnitude == 0 {
+                    self.warn(start, ViolationKind::NegativeZero);
+                }
+                -magnitude
+            }
+            false => magnitude,
+        };
+        T::try_from(num).map_err(|_| Error::IntegerOutOfRange)
     }
 
-    fn parse_num<T>(&mut self) -> Result<T>
+    pub(crate) fn parse_num<T>(&mut self) -> Result<T>
     where
-        T: Neg<Output = T> + AddAssign<T> + MulAssign<T> + From<i8> + From<u8>,
+        T: TryFrom<i128>,
     {
-        if self.next_byte()? != b'i' {
-            return Err(Error::ExpectedI);
-        }
-
-        let n = self.parse_signed();
+        #[cfg(feature = "tracing")]
+        tracing::trace!(offset = self.offset(), "integer");
+        self.value_seen();
+        self.expect_byte(b'i', Error::ExpectedI)?;
+        let n = self.parse_signed()?;
+        self.expect_byte(b'e', Error::ExpectedE)?;
+        Ok(n)
+    }
 
-        match self.next_byte()? {
-            b'e' => n,
-            _ => Err(Error::ExpectedE),
-        }
+    // How many values are left in the current list or dictionary, if the
+    // input is in memory and they can be counted cheaply. Serializers such as
+    // serde_json's trust a size hint as exact, so the scan gives up at the
+    // first nested container rather than undercount.
+    fn lookahead_count(&self) -> Option<usize> {
+        const BUDGET: usize = 1 << 16;
+        count_items(self.read.lookahead()?, BUDGET)
     }
 
-    fn parse_byte_array(&mut self) -> Result<&'de [u8]> {
+    pub(crate) fn parse_byte_array(&mut self) -> Result<Reference<'de, '_, [u8]>> {
+        #[cfg(feature = "tracing")]
+        let start = self.offset();
+        self.value_seen();
         let length: usize = self.parse_unsigned()?;
-        if self.next_byte()? != b':' {
-            return Err(Error::ExpectedColon);
+        self.expect_byte(b':', Error::ExpectedColon)?;
+        #[cfg(feature = "tracing")]
+        match self.read.lookahead().map(<[u8]>::len) {
+            Some(available) if available < length => {
+                tracing::debug!(
+                    offset = start,
+                    len = length,
+                    available,
+                    "byte string runs past the input"
+                )
+            }
+            _ => tracing::trace!(offset = start, len = length, "byte string"),
         }
 
-        let s = &self.input[..length];
-        self.input = &self.input[length..];
-        Ok(s)
+        self.read.read_bytes(length, &mut self.scratch)
     }
 }
 
@@ -122,57 +553,74 @@ mod parser_tests {
 
     #[test]
     fn test_parse_num() {
-        let mut de = Deserializer { input: b"i123e" };
+        let mut de = Deserializer::from_bytes(b"i123e");
         let expected = 123i64;
-        assert_eq!(expected, de.parse_num().unwrap());
+        assert_eq!(expected, de.parse_num::<i64>().unwrap());
         assert_eq!(Err(Error::Eof), de.next_byte());
     }
 
     #[test]
     fn test_parse_num_negative() {
-        let mut de = Deserializer { input: b"i-123e" };
+        let mut de = Deserializer::from_bytes(b"i-123e");
         let expected = -123i64;
-        assert_eq!(expected, de.parse_num().unwrap());
+        assert_eq!(expected, de.parse_num::<i64>().unwrap());
         assert_eq!(Err(Error::Eof), de.next_byte());
     }
 
     #[test]
     fn test_parse_num_no_i() {
-        let mut de = Deserializer { input: b"123e" };
+        let mut de = Deserializer::from_bytes(b"123e");
         assert_eq!(Err(Error::ExpectedI), de.parse_num::<i32>());
     }
 
     #[test]
     fn test_parse_num_no_e() {
-        let mut de = Deserializer { input: b"i123F" };
+        let mut de = Deserializer::from_bytes(b"i123F");
         assert_eq!(Err(Error::ExpectedE), de.parse_num::<i32>());
     }
 
     #[test]
     fn test_parse_byte_array() {
-        let mut de = Deserializer { input: b"5:Hello" };
+        let mut de = Deserializer::from_bytes(b"5:Hello");
         let expected = b"Hello";
-        assert_eq!(expected, de.parse_byte_array().unwrap());
+        assert_eq!(expected, &*de.parse_byte_array().unwrap());
         assert_eq!(Err(Error::Eof), de.next_byte());
     }
 
     #[test]
     fn test_parse_signed() {
-        let mut de = Deserializer { input: b"-321" };
+        let mut de = Deserializer::from_bytes(b"-321");
         let expected = -321i32;
-        assert_eq!(expected, de.parse_signed().unwrap())
+        assert_eq!(expected, de.parse_signed::<i32>().unwrap())
     }
 
     #[test]
     fn test_parse_unsigned() {
-        let mut de = Deserializer { input: b"321" };
+        let mut de = Deserializer::from_bytes(b"321");
         let expected = 321u32;
-        assert_eq!(expected, de.parse_unsigned().unwrap())
+        assert_eq!(expected, de.parse_unsigned::<u32>().unwrap())
+    }
+
+    #[test]
+    fn test_parse_num_range() {
+        let mut de = Deserializer::from_bytes(b"i-9223372036854775808e");
+        assert_eq!(Ok(i64::MIN), de.parse_num::<i64>());
+        let mut de = Deserializer::from_bytes(b"i9223372036854775808e");
+        assert_eq!(Err(Error::IntegerOutOfRange), de.parse_num::<i64>());
+        let mut de = Deserializer::from_bytes(b"i99999999999999999999999e");
+        assert_eq!(Err(Error::IntegerOutOfRange), de.parse_num::<i64>());
+        let mut de = Deserializer::from_bytes(b"i300e");
+        assert_eq!(Err(Error::IntegerOutOfRange), de.parse_num::<i8>());
+        let mut de = Deserializer::from_bytes(b"99999999999999999999999:");
+        assert_eq!(
+            Err(Error::IntegerOutOfRange),
+            de.parse_byte_array().map(|_| ())
+        );
     }
 
     #[test]
     fn test_peek_byte() {
-        let de = Deserializer { input: b"Hello" };
+        let mut de = Deserializer::from_bytes(b"Hello");
         let expected = b'H';
 
         assert_eq!(expected, de.peek_byte().unwrap())
@@ -180,7 +628,7 @@ mod parser_tests {
 
     #[test]
     fn test_peek_byte_empty() {
-        let de = Deserializer { input: &[] };
+        let mut de = Deserializer::from_bytes(&[]);
         let expected = Err(Error::Eof);
 
         assert_eq!(expected, de.peek_byte())
@@ -188,10 +636,10 @@ mod parser_tests {
 
     #[test]
     fn test_next_byte() {
-        let mut de = Deserializer { input: b"Hello" };
+        let mut de = Deserializer::from_bytes(b"Hello");
 
         assert_eq!(b'H', de.next_byte().unwrap());
-        assert_eq!(b"ello", de.input);
+        assert_eq!(b"ello", de.read.remaining());
         assert_eq!(b'e', de.next_byte().unwrap());
         assert_eq!(b'l', de.next_byte().unwrap());
         assert_eq!(b'l', de.next_byte().unwrap());
@@ -200,98 +648,234 @@ mod parser_tests {
     }
 }
 
-impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+// each integer type is parsed and range-checked directly, so a value that
+// doesn't fit is reported against the type that was asked for
+macro_rules! deserialize_int {
+    ($($method:ident => $visit:ident: $ty:ty,)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                let n: i128 = self.parse_num()?;
+                match <$ty>::try_from(n) {
+                    Ok(n) => visitor.$visit(n),
+                    Err(_) => Err(match unexpected_int(n) {
+                        Some(unexp) => de::Error::invalid_value(unexp, &visitor),
+                        None => Error::IntegerOutOfRange,
+                    }),
+                }
+            }
+        )*
+    };
+}
+
+// `None` below `i64::MIN`, which serde has no way to describe
+fn unexpected_int(n: i128) -> Option<Unexpected<'static>> {
+    match (i64::try_from(n), u64::try_from(n)) {
+        (Ok(n), _) => Some(Unexpected::Signed(n)),
+        (_, Ok(n)) => Some(Unexpected::Unsigned(n)),
+        _ => None,
+    }
+}
+
+impl<'de, R: Read<'de>> de::Deserializer<'de> for &mut Deserializer<R> {
     type Error = Error;
 
+    // self-describing consumers (`serde_json::Value`, serde_transcode) see
+    // byte strings as strings when they're valid UTF-8, so dictionary keys
+    // become string keys; anything else is visited as bytes
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
         match self.peek_byte()? {
-            b'i' => self.deserialize_i64(visitor),
-            b'0'..=b'9' => self.deserialize_bytes(visitor),
+            b'i' => {
+                let n = self.parse_num::<i128>()?;
+                match (i64::try_from(n), u64::try_from(n)) {
+                    (Ok(n), _) => visitor.visit_i64(n),
+                    (_, Ok(n)) => visitor.visit_u64(n),
+                    _ => Err(Error::IntegerOutOfRange),
+                }
+            }
+            b'0'..=b'9' => self.deserialize_str(visitor),
             b'l' => self.deserialize_seq(visitor),
             b'd' => self.deserialize_map(visitor),
             _ => Err(Error::Syntax),
         }
     }
 
-    fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value>
+    deserialize_int! {
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_i128 => visit_i128: i128,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_u128 => visit_u128: u128,
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.parse_num()? {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            n => Err(de::Error::invalid_value(Unexpected::Signed(n), &visitor)),
+        }
+    }
+
+    // a value that is present is always `Some`; `None` is never written
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_i64(self.parse_num()?)
+        if name == CAPTURE {
+            let start = self.read.offset();
+            self.read.begin_capture();
+            let result = visitor.visit_seq(CaptureReader {
+                de: &mut *self,
+                start,
+                index: 0,
+            });
+            self.read.end_capture();
+            return result;
+        }
+        visitor.visit_newtype_struct(self)
     }
 
     fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_borrowed_bytes(self.parse_byte_array()?)
+        match self.parse_byte_array()? {
+            Reference::Borrowed(b) => visitor.visit_borrowed_bytes(b),
+            Reference::Copied(b) => visitor.visit_bytes(b),
+        }
     }
 
-    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        match self.next_byte()? {
-            b'l' => {
-                let value = visitor.visit_seq(SeqReader::new(self))?;
-                match self.next_byte()? {
-                    b'e' => Ok(value),
-                    _ => Err(Error::ExpectedListEnd),
-                }
-            }
-            _ => Err(Error::ExpectedList),
+        self.deserialize_bytes(visitor)
+    }
+
+    // byte strings that are valid UTF-8 are visited as strings, which lets
+    // `String` take them without a second copy
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if !self.peek_byte()?.is_ascii_digit() {
+            return self.deserialize_any(visitor);
         }
+        match self.parse_byte_array()? {
+            Reference::Borrowed(b) => match str::from_utf8(b) {
+                Ok(s) => visitor.visit_borrowed_str(s),
+                Err(_) => visitor.visit_borrowed_bytes(b),
+            },
+            Reference::Copied(b) => match str::from_utf8(b) {
+                Ok(s) => visitor.visit_str(s),
+                Err(_) => visitor.visit_bytes(b),
+            },
+        }
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.expect_byte(b'l', Error::ExpectedList)?;
+        let value = self.nested(|de| visitor.visit_seq(SeqReader::new(de)))?;
+        self.expect_byte(b'e', Error::ExpectedListEnd)?;
+        Ok(value)
     }
 
     fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        match self.next_byte()? {
+        self.expect_byte(b'd', Error::ExpectedDict)?;
+        let value = self.nested(|de| visitor.visit_map(MapReader::new(de)))?;
+        self.expect_byte(b'e', Error::ExpectedDictEnd)?;
+        Ok(value)
+    }
+
+    // unit variants are a byte string, the others a single-key dictionary,
+    // as the serializer writes them
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.peek_byte()? {
+            b'0'..=b'9' => visitor.visit_enum(EnumReader {
+                de: self,
+                unit: true,
+            }),
             b'd' => {
-                let value = visitor.visit_map(MapReader::new(self))?;
-                match self.next_byte()? {
-                    b'e' => Ok(value),
-                    _ => Err(Error::ExpectedDictEnd),
-                }
+                self.next_byte()?;
+                let value = self.nested(|de| {
+                    if !de.peek_byte()?.is_ascii_digit() {
+                        return Err(Error::KeyMustBeByteString);
+                    }
+                    visitor.visit_enum(EnumReader { de, unit: false })
+                })?;
+                self.expect_byte(b'e', Error::ExpectedDictEnd)?;
+                Ok(value)
+            }
+            b'i' => {
+                let n = self.parse_num::<i128>()?;
+                let unexp = unexpected_int(n).ok_or(Error::IntegerOutOfRange)?;
+                Err(de::Error::invalid_type(unexp, &"enum"))
             }
-            _ => Err(Error::ExpectedDict),
+            b'l' => Err(de::Error::invalid_type(Unexpected::Seq, &"enum")),
+            _ => Err(Error::Syntax),
         }
     }
 
-    // fn deserialize_enum<V>(
-    //     self,
-    //     _name: &'static str,
-    //     _variants: &'static [&'static str],
-    //     visitor: V,
-    // ) -> Result<V::Value>
-    // where
-    //     V: Visitor<'de>,
-    // {
-    //     visitor.visit_enum(EnumReader::new(self))
-    // }
-
     forward_to_deserialize_any! {
-        bool i8 i16 i32 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-        byte_buf option unit unit_struct newtype_struct tuple
-        tuple_struct struct identifier ignored_any enum
+        f32 f64 char
+        unit unit_struct tuple
+        tuple_struct struct identifier ignored_any
     }
 }
 
-struct SeqReader<'a, 'de: 'a> {
-    de: &'a mut Deserializer<'de>,
+struct SeqReader<'a, R> {
+    de: &'a mut Deserializer<R>,
+    index: usize,
 }
 
-impl<'a, 'de> SeqReader<'a, 'de> {
-    fn new(de: &'a mut Deserializer<'de>) -> Self {
-        SeqReader { de }
+impl<'a, R> SeqReader<'a, R> {
+    fn new(de: &'a mut Deserializer<R>) -> Self {
+        SeqReader { de, index: 0 }
     }
 }
 
-impl<'de, 'a> SeqAccess<'de> for SeqReader<'a, 'de> {
+impl<'de, 'a, R: Read<'de>> SeqAccess<'de> for SeqReader<'a, R> {
     type Error = Error;
 
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
@@ -302,32 +886,126 @@ impl<'de, 'a> SeqAccess<'de> for SeqReader<'a, 'de> {
             return Ok(None);
         }
 
-        Ok(Some(seed.deserialize(&mut *self.de)?))
+        let value = seed
+            .deserialize(&mut *self.de)
+            .map_err(|e| e.at_index(self.index))?;
+        self.index += 1;
+        Ok(Some(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.de.lookahead_count()
+    }
+}
+
+// hands a `Captured` the value and then the bytes it was read from
+struct CaptureReader<'a, R> {
+    de: &'a mut Deserializer<R>,
+    start: usize,
+    index: usize,
+}
+
+impl<'de, R: Read<'de>> SeqAccess<'de> for CaptureReader<'_, R> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        self.index += 1;
+        match self.index {
+            1 => seed.deserialize(&mut *self.de).map(Some),
+            2 => match self.de.read.captured(self.start) {
+                Reference::Borrowed(b) => seed.deserialize(BorrowedBytesDeserializer::new(b)),
+                Reference::Copied(b) => seed.deserialize(BytesDeserializer::new(b)),
+            }
+            .map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(2 - self.index.min(2))
     }
 }
 
-struct MapReader<'a, 'de: 'a> {
-    de: &'a mut Deserializer<'de>,
+struct MapReader<'a, 'de, R> {
+    de: &'a mut Deserializer<R>,
+    // the current key, kept for errors in its value and for warnings about
+    // the next key; only copied when it can't be borrowed from the input
+    key: Cow<'de, [u8]>,
+    first: bool,
 }
 
-impl<'a, 'de> MapReader<'a, 'de> {
-    fn new(de: &'a mut Deserializer<'de>) -> Self {
-        MapReader { de }
+impl<'a, 'de, R> MapReader<'a, 'de, R> {
+    fn new(de: &'a mut Deserializer<R>) -> Self {
+        MapReader {
+            de,
+            key: Cow::Borrowed(&[]),
+            first: true,
+        }
+    }
+}
+
+// hands a copied key's buffer back for the next dictionary
+impl<R> Drop for MapReader<'_, '_, R> {
+    fn drop(&mut self) {
+        if let Cow::Owned(buf) = mem::take(&mut self.key) {
+            self.de.keys.push(buf);
+        }
     }
 }
 
-impl<'a, 'de> MapAccess<'de> for MapReader<'a, 'de> {
+impl<'de, 'a, R: Read<'de>> MapAccess<'de> for MapReader<'a, 'de, R> {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
     where
         K: DeserializeSeed<'de>,
     {
-        if self.de.peek_byte()? == b'e' {
-            return Ok(None);
+        match self.de.peek_byte()? {
+            b'e' => return Ok(None),
+            b'0'..=b'9' => {}
+            _ => return Err(Error::KeyMustBeByteString),
         }
 
-        Ok(Some(seed.deserialize(&mut *self.de)?))
+        let offset = self.de.offset();
+        let check = !self.first && self.de.warnings.is_some();
+        self.first = false;
+        // a buffer for the key in case it's copied, taken now while the
+        // deserializer isn't borrowed
+        let mut spare = match self.key {
+            Cow::Borrowed(_) => self.de.keys.pop(),
+            Cow::Owned(_) => None,
+        };
+        let key = self.de.parse_byte_array()?;
+        let warning = match (*key).cmp(&*self.key) {
+            _ if !check => None,
+            Ordering::Less => Some(ViolationKind::UnsortedKey),
+            Ordering::Equal => Some(ViolationKind::DuplicateKey),
+            Ordering::Greater => None,
+        };
+        match key {
+            Reference::Borrowed(b) => self.key = Cow::Borrowed(b),
+            Reference::Copied(b) => match &mut self.key {
+                Cow::Owned(buf) => {
+                    buf.clear();
+                    buf.extend_from_slice(b);
+                }
+                borrowed => {
+                    let mut buf = spare.take().unwrap_or_default();
+                    buf.clear();
+                    buf.extend_from_slice(b);
+                    *borrowed = Cow::Owned(buf);
+                }
+            },
+        }
+        let key = seed.deserialize(KeyDeserializer { key })?;
+        self.de.keys.extend(spare);
+        if let Some(kind) = warning {
+            self.de.warn(offset, kind);
+        }
+        Ok(Some(key))
     }
 
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
@@ -335,90 +1013,153 @@ impl<'a, 'de> MapAccess<'de> for MapReader<'a, 'de> {
         V: DeserializeSeed<'de>,
     {
         seed.deserialize(&mut *self.de)
+            .map_err(|e| e.at_key(&self.key))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.de.lookahead_count().map(|n| n / 2)
     }
 }
 
-// struct EnumReader<'a, 'de: 'a> {
-//     de: &'a mut Deserializer<'de>,
-// }
-
-// impl<'a, 'de> EnumReader<'a, 'de> {
-//     fn new(de: &'a mut Deserializer<'de>) -> Self {
-//         EnumReader { de }
-//     }
-// }
-
-// impl<'a, 'de> EnumAccess<'de> for EnumReader<'a, 'de> {
-//     type Error = Error;
-//     type Variant = Self;
-
-//     fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
-//     where
-//         V: DeserializeSeed<'de>,
-//     {
-//         Ok((seed.deserialize(&mut *self.de)?, self))
-//     }
-// }
-
-// impl<'a, 'de> VariantAccess<'de> for EnumReader<'a, 'de> {
-//     type Error = Error;
-
-//     // I have no idea how this applies here
-//     fn unit_variant(self) -> Result<()> {
-//         Ok(())
-//     }
-
-//     fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
-//     where
-//         T: DeserializeSeed<'de>,
-//     {
-//         let value = seed.deserialize(&mut *self.de)?;
-//         match self.de.next_byte()? {
-//             b'e' => Ok(value),
-//             _ => Err(Error::ExpectedDictEnd),
-//         }
-//     }
-
-//     fn struct_variant<V>(
-//         self,
-//         _fields: &'static [&'static str],
-//         visitor: V,
-//     ) -> std::result::Result<V::Value, Self::Error>
-//     where
-//         V: Visitor<'de>,
-//     {
-//         let value = serde::de::Deserializer::deserialize_map(&mut *self.de, visitor)?;
-//         match self.de.next_byte()? {
-//             b'e' => Ok(value),
-//             _ => Err(Error::ExpectedDictEnd),
-//         }
-//     }
-
-//     fn tuple_variant<V>(self, _len: usize, visitor: V) -> std::result::Result<V::Value, Self::Error>
-//     where
-//         V: Visitor<'de>,
-//     {
-//         let value = serde::de::Deserializer::deserialize_seq(&mut *self.de, visitor)?;
-//         match self.de.next_byte()? {
-//             b'e' => Ok(value),
-//             _ => Err(Error::ExpectedListEnd),
-//         }
-//     }
-// }
+// Keys are read before they're deserialized, so the map reader can hold on
+// to them. Like other byte strings, they're visited as strings when they're
+// valid UTF-8.
+struct KeyDeserializer<'de, 's> {
+    key: Reference<'de, 's, [u8]>,
+}
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer<'de, '_> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.key {
+            Reference::Borrowed(b) => match str::from_utf8(b) {
+                Ok(s) => visitor.visit_borrowed_str(s),
+                Err(_) => visitor.visit_borrowed_bytes(b),
+            },
+            Reference::Copied(b) => match str::from_utf8(b) {
+                Ok(s) => visitor.visit_str(s),
+                Err(_) => visitor.visit_bytes(b),
+            },
+        }
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.key {
+            Reference::Borrowed(b) => visitor.visit_borrowed_bytes(b),
+            Reference::Copied(b) => visitor.visit_bytes(b),
+        }
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct identifier ignored_any enum
+    }
+}
+
+struct EnumReader<'a, R> {
+    de: &'a mut Deserializer<R>,
+    // a bare byte string, with no value after the name
+    unit: bool,
+}
+
+impl<'de, 'a, R: Read<'de>> EnumAccess<'de> for EnumReader<'a, R> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        Ok((seed.deserialize(&mut *self.de)?, self))
+    }
+}
+
+impl<'de, 'a, R: Read<'de>> VariantAccess<'de> for EnumReader<'a, R> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        match self.unit {
+            true => Ok(()),
+            false => Err(de::Error::invalid_type(Unexpected::Map, &"unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.unit {
+            true => Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"newtype variant",
+            )),
+            false => seed.deserialize(&mut *self.de),
+        }
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.unit {
+            true => Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"tuple variant",
+            )),
+            false => de::Deserializer::deserialize_seq(&mut *self.de, visitor),
+        }
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.unit {
+            true => Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"struct variant",
+            )),
+            false => de::Deserializer::deserialize_map(&mut *self.de, visitor),
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
 
     use std::collections::HashMap;
 
-    use super::from_bytes;
+    #[cfg(feature = "bytes")]
+    use super::from_buf;
+    use super::{from_bytes, from_bytes_with_warnings, Deserializer, Validated};
+    #[cfg(feature = "mmap")]
+    use super::{from_mmap, MappedFile};
+    #[cfg(feature = "std")]
+    use super::{from_path, from_reader, from_reader_with, ReadContext};
+    use crate::error::Error;
+    use crate::validate::ViolationKind;
     use serde::Deserialize;
 
     #[test]
     fn test_num() {
         assert_eq!(123i64, from_bytes::<i64>(b"i123e").unwrap());
         assert_eq!(-123i64, from_bytes::<i64>(b"i-123e").unwrap());
-        assert_eq!(-023i64, from_bytes::<i64>(b"i-23e").unwrap());
+        assert_eq!(-23i64, from_bytes::<i64>(b"i-23e").unwrap());
     }
 
     #[test]
@@ -437,16 +1178,13 @@ mod tests {
 
     #[test]
     fn test_hetero_list_to_tuple() {
+        #[allow(dead_code)]
         #[derive(Deserialize, PartialEq, Debug)]
         enum Value<'a> {
             Number(i64),
             ByteString(&'a [u8]),
         }
-        let expected: (i64, &[u8], i64) = (
-            10,
-            b"Hello",
-            69
-        );
+        let expected: (i64, &[u8], i64) = (10, b"Hello", 69);
         let b = b"li10e5:Helloi69ee";
         let v = from_bytes(b).unwrap();
         assert_eq!(expected, v);
@@ -457,7 +1195,7 @@ mod tests {
         #[derive(Deserialize, PartialEq, Debug)]
         struct Test<'a> {
             a: i64,
-            b: &'a [u8]
+            b: &'a [u8],
         }
 
         let expected = Test { a: 69, b: b"Hello" };
@@ -468,10 +1206,7 @@ mod tests {
 
     #[test]
     fn test_map_str_to_int() {
-        let expected = HashMap::from([
-            ("a", 69),
-            ("b", 20)
-        ]);
+        let expected = HashMap::from([("a", 69), ("b", 20)]);
         let b = b"d1:ai69e1:bi20ee";
         assert_eq!(expected, from_bytes(b).unwrap());
     }
@@ -498,23 +1233,488 @@ mod tests {
             info: Info<'a>,
         }
 
-        let expected = Parent{
+        let expected = Parent {
             announce: b"hello",
             info: Info {
                 length: 5,
                 name: b"john",
-            }
+            },
         };
         let b = b"d1:ai1e8:announce5:hello4:infod6:lengthi5e4:name4:johnee";
         assert_eq!(expected, from_bytes(b).unwrap());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_from_reader() {
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Test {
+            a: i64,
+            b: Vec<String>,
+        }
+
+        let expected = Test {
+            a: 69,
+            b: vec!["Hello".to_string()],
+        };
+        let b = &b"d1:ai69e1:bl5:Helloee"[..];
+        assert_eq!(expected, from_reader(b).unwrap());
+        assert!(from_reader::<_, Test>(&b"d1:ai69e1:bl5:Hell"[..]).is_err());
+        assert!(from_reader::<_, i64>(&b"i1ei2e"[..]).is_err());
+        assert!(from_reader::<_, String>(&b"2:\xff\xfe"[..]).is_err());
+        assert_eq!(
+            crate::Value::List(vec![crate::Value::from(vec![0xff])]),
+            from_reader(&b"l1:\xffe"[..]).unwrap()
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", ignore = "no filesystem")]
+    fn test_from_path() {
+        let v: crate::Value = from_path("debian.torrent").unwrap();
+        assert!(v["info"]["pieces"].as_bytes().is_some());
+        assert!(from_path::<crate::Value, _>("missing.torrent").is_err());
+        assert!(from_path::<crate::Value, _>("Cargo.toml").is_err());
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn test_from_buf() {
+        use bytes::Buf;
+
+        let body = (&b"d8:intervali18"[..]).chain(&b"00e5:peers0:e"[..]);
+        let v: crate::Value = from_buf(body).unwrap();
+        assert_eq!(Some(1800), v["interval"].as_int());
+        assert_eq!(Some(&b""[..]), v["peers"].as_bytes());
+        assert!(from_buf::<_, i64>((&b"i1"[..]).chain(&b"e "[..])).is_err());
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_from_mmap() {
+        #[derive(Deserialize)]
+        struct Torrent<'a> {
+            #[serde(borrow)]
+            info: Info<'a>,
+        }
+
+        #[derive(Deserialize)]
+        struct Info<'a> {
+            length: i64,
+            pieces: &'a [u8],
+        }
+
+        let file = std::fs::File::open("debian.torrent").unwrap();
+        let mmap = unsafe { memmap2::Mmap::map(&file).unwrap() };
+        let t: Torrent = from_mmap(&mmap).unwrap();
+        assert_eq!(0, t.info.pieces.len() % 20);
+        let range = mmap.as_ptr_range();
+        assert!(range.contains(&t.info.pieces.as_ptr()));
+
+        let mapped = unsafe { MappedFile::open(&file).unwrap() };
+        let v: crate::Value = mapped.deserialize().unwrap();
+        assert_eq!(Some(t.info.length), v["info"]["length"].as_int());
+        let t: Torrent = mapped.deserialize().unwrap();
+        let range = mapped.as_bytes().as_ptr_range();
+        assert!(range.contains(&t.info.pieces.as_ptr()));
+
+        let mut de = super::Deserializer::from_file(&mapped);
+        let t = Torrent::deserialize(&mut de).unwrap();
+        de.end().unwrap();
+        assert!(range.contains(&t.info.pieces.as_ptr()));
+    }
+
+    #[test]
+    fn test_warnings() {
+        let (v, warnings) =
+            from_bytes_with_warnings::<crate::Value>(b"d1:bi-0e1:ai007e1:ai1e02:xyi1ee").unwrap();
+        assert_eq!(Some(1), v["a"].as_int());
+        let warnings: Vec<_> = warnings.into_iter().map(|w| (w.offset, w.kind)).collect();
+        assert_eq!(
+            vec![
+                (4, ViolationKind::NegativeZero),
+                (8, ViolationKind::UnsortedKey),
+                (12, ViolationKind::LeadingZero),
+                (16, ViolationKind::DuplicateKey),
+                (22, ViolationKind::LeadingZero),
+            ],
+            warnings
+        );
+
+        let (_, warnings) = from_bytes_with_warnings::<crate::Value>(b"d1:ai0e1:bi10ee").unwrap();
+        assert!(warnings.is_empty());
 
+        let mut de = Deserializer::from_bytes(b"i-0e");
+        crate::Value::deserialize(&mut de).unwrap();
+        assert!(de.take_warnings().is_empty());
     }
 
+    #[test]
+    fn test_self_describing() {
+        let mut de = Deserializer::from_bytes(b"d4:listli1e2:abe4:name4:johne");
+        let json = serde_json::Value::deserialize(&mut de).unwrap();
+        de.end().unwrap();
+        assert_eq!(
+            r#"{"list":[1,"ab"],"name":"john"}"#,
+            serde_json::to_string(&json).unwrap()
+        );
+
+        // JSON has no bytes type, but a visitor that takes them still gets them
+        let raw: crate::Value = from_bytes(b"2:\xff\x00").unwrap();
+        assert_eq!(crate::Value::from(vec![0xff, 0]), raw);
+        assert!(from_bytes::<serde_json::Value>(b"2:\xff\x00").is_err());
+    }
+
+    #[test]
+    fn test_int_widths() {
+        assert_eq!(Ok(255u8), from_bytes(b"i255e"));
+        assert_eq!(Ok(-128i8), from_bytes(b"i-128e"));
+        assert_eq!(Ok(u64::MAX), from_bytes(b"i18446744073709551615e"));
+        assert_eq!(Ok(-1i128), from_bytes(b"i-1e"));
+        assert_eq!(
+            "invalid value: integer `300`, expected u8",
+            from_bytes::<u8>(b"i300e").unwrap_err().inner().to_string()
+        );
+        assert_eq!(
+            "invalid value: integer `-1`, expected u64",
+            from_bytes::<u64>(b"i-1e").unwrap_err().inner().to_string()
+        );
+        assert_eq!(
+            "invalid value: integer `18446744073709551615`, expected i64",
+            from_bytes::<i64>(b"i18446744073709551615e")
+                .unwrap_err()
+                .inner()
+                .to_string()
+        );
+        assert_eq!(
+            Err(crate::Error::IntegerOutOfRange),
+            from_bytes::<u64>(b"i18446744073709551616e")
+        );
+        // below `i64::MIN`, which used to wrap around
+        assert_eq!(
+            Ok(-18446744073709551615i128),
+            from_bytes(b"i-18446744073709551615e")
+        );
+        assert_eq!(
+            Err(crate::Error::IntegerOutOfRange),
+            from_bytes::<crate::Value>(b"i-18446744073709551615e")
+        );
+        assert_eq!(
+            Err(crate::Error::IntegerOutOfRange),
+            from_bytes::<i64>(b"i-9223372036854775809e")
+        );
+    }
+
+    #[test]
+    fn test_size_hint() {
+        use serde::de::{self, MapAccess, SeqAccess, Visitor};
+
+        struct Hint(Option<usize>);
+
+        impl<'de> Deserialize<'de> for Hint {
+            fn deserialize<D: de::Deserializer<'de>>(d: D) -> std::result::Result<Self, D::Error> {
+                struct HintVisitor;
+
+                impl<'de> Visitor<'de> for HintVisitor {
+                    type Value = Hint;
+
+                    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        f.write_str("a list or dictionary")
+                    }
+
+                    fn visit_seq<A: SeqAccess<'de>>(
+                        self,
+                        mut seq: A,
+                    ) -> std::result::Result<Hint, A::Error> {
+                        let hint = seq.size_hint();
+                        while seq.next_element::<de::IgnoredAny>()?.is_some() {}
+                        Ok(Hint(hint))
+                    }
+
+                    fn visit_map<A: MapAccess<'de>>(
+                        self,
+                        mut map: A,
+                    ) -> std::result::Result<Hint, A::Error> {
+                        let hint = map.size_hint();
+                        while map
+                            .next_entry::<de::IgnoredAny, de::IgnoredAny>()?
+                            .is_some()
+                        {}
+                        Ok(Hint(hint))
+                    }
+                }
+
+                d.deserialize_any(HintVisitor)
+            }
+        }
+
+        assert_eq!(Some(3), from_bytes::<Hint>(b"li1e1:ai-2ee").unwrap().0);
+        assert_eq!(None, from_bytes::<Hint>(b"li1eli2ee1:ae").unwrap().0);
+        assert_eq!(None, from_bytes::<Hint>(b"d1:ali1ee1:bi2ee").unwrap().0);
+        assert_eq!(Some(2), from_bytes::<Hint>(b"d1:ai1e1:b2:xye").unwrap().0);
+        assert_eq!(Some(0), from_bytes::<Hint>(b"le").unwrap().0);
+        #[cfg(feature = "std")]
+        assert_eq!(None, from_reader::<_, Hint>(&b"li1ee"[..]).unwrap().0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_from_reader_with() {
+        let mut cx = ReadContext::new();
+        for input in [&b"d1:ai1e1:bli2eee"[..], b"d2:aai3ee", b"le"] {
+            let v: crate::Value = from_reader_with(input, &mut cx).unwrap();
+            assert_eq!(crate::to_bytes(&v).unwrap(), input);
+        }
+        assert_eq!(
+            Err(Error::Eof),
+            from_reader_with::<_, crate::Value>(&b"d1:ai1e"[..], &mut cx)
+        );
+        let v: crate::Value = from_reader_with(&b"d1:ai1ee"[..], &mut cx).unwrap();
+        assert_eq!(Some(1), v.get("a").and_then(crate::Value::as_int));
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        fn nested(depth: usize) -> Vec<u8> {
+            let mut input = b"l".repeat(depth);
+            input.extend(b"e".repeat(depth));
+            input
+        }
+
+        assert!(from_bytes::<crate::Value>(&nested(128)).is_ok());
+        assert_eq!(
+            Err(Error::DepthLimitExceeded),
+            from_bytes::<crate::Value>(&nested(129))
+        );
+        // an error rather than a stack overflow, however it's read
+        let deep = b"d1:a".repeat(100_000);
+        assert_eq!(
+            Err(Error::DepthLimitExceeded),
+            from_bytes::<serde::de::IgnoredAny>(&deep).map(|_| ())
+        );
+        #[cfg(feature = "std")]
+        assert_eq!(
+            Err(Error::DepthLimitExceeded),
+            from_reader::<_, crate::Value>(&deep[..])
+        );
+
+        let input = nested(200);
+        let mut de = Deserializer::from_bytes(&input);
+        de.set_max_depth(200);
+        assert!(crate::Value::deserialize(&mut de).is_ok());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_on_progress() {
+        use std::sync::{Arc, Mutex};
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let input = [&b"l"[..], &b"i1e".repeat(100), b"3:abce"].concat();
+        let mut de = Deserializer::from_reader(&input[..]);
+        let sink = reports.clone();
+        de.on_progress(100, move |bytes, values| {
+            sink.lock().unwrap().push((bytes, values))
+        });
+        let v = crate::Value::deserialize(&mut de).unwrap();
+        de.end().unwrap();
+        assert_eq!(101, v.as_list().unwrap().len());
+        // checked as each value starts: the 34th integer at byte 100 and
+        // the 68th at byte 202, then at the end
+        assert_eq!(
+            vec![(100, 35), (202, 69), (307, 102)],
+            *reports.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_reset() {
+        use crate::read::SliceRead;
+
+        let mut de = Deserializer::from_bytes(b"i01e");
+        de.collect_warnings();
+        de.set_max_depth(1);
+        assert_eq!(Ok(1), i64::deserialize(&mut de));
+        assert_eq!(1, de.take_warnings().len());
+
+        let old = de.reset(SliceRead::new(b"li-0ee"));
+        assert_eq!(b"", old.remaining());
+        assert_eq!(Ok(vec![0]), Vec::<i64>::deserialize(&mut de));
+        assert_eq!(1, de.take_warnings().len());
+        // the limit carries over
+        de.reset(SliceRead::new(b"llee"));
+        assert_eq!(
+            Err(Error::DepthLimitExceeded),
+            crate::Value::deserialize(&mut de).map(|_| ())
+        );
+
+        #[cfg(feature = "std")]
+        {
+            let mut de = Deserializer::from_reader(&b"i1ei2e"[..]);
+            assert_eq!(Ok(1), i64::deserialize(&mut de));
+            // `i2e` was read ahead and goes with the old reader
+            de.reset_reader(&b"3:abc"[..]);
+            assert_eq!(Ok("abc".to_string()), String::deserialize(&mut de));
+            assert_eq!(Ok(()), de.end());
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_tracing() {
+        use std::fmt::{Debug, Write};
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        // each span or event as `name field=value ...`
+        #[derive(Clone, Default)]
+        struct Collect(Arc<Mutex<Vec<String>>>);
+
+        struct Line(String);
+
+        impl Visit for Line {
+            fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+                write!(self.0, " {}={:?}", field, value).unwrap();
+            }
+        }
+
+        impl Subscriber for Collect {
+            fn enabled(&self, _: &Metadata) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &Attributes) -> Id {
+                let mut line = Line(span.metadata().name().to_string());
+                span.record(&mut line);
+                self.0.lock().unwrap().push(line.0);
+                Id::from_u64(1)
+            }
+
+            fn record(&self, _: &Id, _: &Record) {}
+
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+            fn event(&self, event: &Event) {
+                let mut line = Line(event.metadata().level().to_string());
+                event.record(&mut line);
+                self.0.lock().unwrap().push(line.0);
+            }
+
+            fn enter(&self, _: &Id) {}
+
+            fn exit(&self, _: &Id) {}
+        }
+
+        let collect = Collect::default();
+        tracing::subscriber::with_default(collect.clone(), || {
+            from_bytes::<(i64, &str)>(b"li1e2:abe").unwrap();
+            from_bytes::<crate::Value>(&b"l".repeat(200)).unwrap_err();
+            from_bytes::<&str>(b"9:ab").unwrap_err();
+        });
+        let lines = collect.0.lock().unwrap();
+        assert_eq!(
+            [
+                "bencode::parse ty=\"(i64, &str)\" len=9",
+                "TRACE message=container offset=0",
+                "TRACE message=integer offset=1",
+                "TRACE message=byte string offset=4 len=2",
+                "DEBUG message=parsed bytes=9",
+            ],
+            lines[..5]
+        );
+        assert_eq!(
+            "WARN message=nesting depth limit exceeded offset=129",
+            lines[lines.len() - 5]
+        );
+        assert_eq!(
+            [
+                "DEBUG message=byte string runs past the input offset=0 len=9 available=2",
+                "DEBUG message=parse failed offset=2 error=unexpected end of input",
+            ],
+            lines[lines.len() - 2..]
+        );
+    }
+
+    #[test]
+    fn test_validated() {
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Info<'a> {
+            length: u32,
+            name: &'a str,
+        }
+
+        let input = b"d6:lengthi5e4:name4:johne";
+        let v = Validated::new(input).unwrap();
+        assert_eq!(
+            Info {
+                length: 5,
+                name: "john"
+            },
+            v.deserialize::<Info>().unwrap()
+        );
+        let value: crate::Value = v.deserialize().unwrap();
+        assert_eq!(
+            Some("john"),
+            value.get("name").and_then(crate::Value::as_str)
+        );
+        assert!(v.deserialize::<Vec<i64>>().is_err());
+        assert!(Validated::new(b"d6:lengthi-1ee")
+            .unwrap()
+            .deserialize::<Info>()
+            .is_err());
+
+        assert_eq!(Err(Error::Eof), Validated::new(b"d4:name4:joh").map(|_| ()));
+        assert_eq!(
+            Err(Error::TrailingCharacters),
+            Validated::new(b"i1ei2e").map(|_| ())
+        );
+        assert_eq!(Err(Error::Syntax), Validated::new(b"x").map(|_| ()));
+        for (input, error) in [
+            (&b"ie"[..], Error::ExpectedInteger),
+            (b"i--1e", Error::ExpectedInteger),
+            (b"di1ei2ee", Error::KeyMustBeByteString),
+            (b"d1:ae", Error::Syntax),
+            (b"4:ab", Error::Eof),
+        ] {
+            assert_eq!(Err(error), Validated::new(input).map(|_| ()));
+        }
+        // non-canonical is fine, as it is for `from_bytes`
+        let v = Validated::new(b"d1:bi01e1:ai-0ee").unwrap();
+        assert_eq!(
+            1,
+            v.deserialize::<crate::Value>().unwrap()["b"]
+                .as_int()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_option_and_bool() {
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Test {
+            a: Option<String>,
+            b: Option<i64>,
+            c: bool,
+        }
+
+        assert_eq!(
+            Test {
+                a: Some("x".to_string()),
+                b: None,
+                c: true,
+            },
+            from_bytes(b"d1:a1:x1:ci1ee").unwrap()
+        );
+        assert!(from_bytes::<bool>(b"i2e").is_err());
+    }
 
     #[test]
     fn test_torrent() {
         #[derive(Deserialize, PartialEq, Debug)]
-        struct Torrent<'a>{
+        struct Torrent<'a> {
             announce: String,
             comment: String,
             #[serde(rename = "created by")]
@@ -524,7 +1724,7 @@ mod tests {
             #[serde(borrow)]
             info: Info<'a>,
             #[serde(rename = "url-list")]
-            url_list: Vec<String>
+            url_list: Vec<String>,
         }
 
         #[derive(Deserialize, PartialEq, Debug)]
@@ -536,11 +1736,115 @@ mod tests {
             pieces: &'a [u8],
         }
 
-        let contents = std::fs::read("debian.torrent").unwrap();
+        let contents = include_bytes!("../debian.torrent").to_vec();
         let b = contents.as_slice();
         let v: Torrent = from_bytes(b).unwrap();
         println!("{:?}", v);
         // assert_eq!(expected, from_bytes(b).unwrap());
+    }
+}
+
+// Counts allocations made by the current thread, so tests running alongside
+// don't disturb each other.
+#[cfg(test)]
+mod alloc_tests {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    use super::from_bytes;
+    #[cfg(feature = "std")]
+    use super::{from_reader_with, ReadContext};
+    use serde::Deserialize;
+
+    struct Counting;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for Counting {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: Counting = Counting;
+
+    fn allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+        let before = ALLOCATIONS.with(Cell::get);
+        let t = f();
+        (t, ALLOCATIONS.with(Cell::get) - before)
+    }
+
+    #[test]
+    fn test_borrowed_struct_does_not_allocate() {
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Message<'a> {
+            #[serde(borrow)]
+            a: Args<'a>,
+            q: &'a str,
+            t: &'a [u8],
+            v: Option<&'a str>,
+            y: &'a str,
+        }
+
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Args<'a> {
+            id: &'a [u8],
+            port: u16,
+            target: (&'a [u8], i64),
+        }
+
+        let input = b"d1:ad2:id3:abc7:ignoredld1:xi1eee4:porti6881e6:targetl2:\xff\x00i-1eee\
+                      1:q9:find_node1:t2:aa1:y1:qe";
+        let (message, n) = allocations(|| from_bytes::<Message>(input));
+        let expected = Message {
+            a: Args {
+                id: b"abc",
+                port: 6881,
+                target: (b"\xff\x00", -1),
+            },
+            q: "find_node",
+            t: b"aa",
+            v: None,
+            y: "q",
+        };
+        assert_eq!(expected, message.unwrap());
+        assert_eq!(0, n);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_read_context_reuses_buffers() {
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Message {
+            a: Args,
+            y: i64,
+        }
+
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Args {
+            port: u16,
+            token: serde::de::IgnoredAny,
+        }
 
+        let input = b"d1:ad4:porti6881e5:token8:aoeusnthe1:yi1ee";
+        let mut cx = ReadContext::new();
+        let first: Message = from_reader_with(&input[..], &mut cx).unwrap();
+        let (second, n) = allocations(|| from_reader_with::<_, Message>(&input[..], &mut cx));
+        assert_eq!(first, second.unwrap());
+        assert_eq!(0, n);
+    }
+
+    #[test]
+    fn test_counting() {
+        let (_, n) = allocations(|| vec![1u8]);
+        assert_eq!(1, n);
     }
 }
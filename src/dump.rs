@@ -0,0 +1,255 @@
+use core::fmt::Write;
+
+use crate::error::Result;
+use crate::events::{parse_events, Handler};
+use crate::tokens::{Token, Tokens};
+use crate::value::display::write_bytes;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// Renders raw bencode as indented text in the style of `Value`'s `{:#}`,
+/// keeping dictionary keys in input order, duplicates included.
+pub fn to_debug_string(input: &[u8]) -> Result<String> {
+    let mut dumper = Dumper::default();
+    parse_events(input, &mut dumper)?;
+    Ok(dumper.out)
+}
+
+const HEXDUMP_WIDTH: usize = 16;
+
+/// Renders a hexdump of `input`, annotating each line with the tokens that
+/// start on it:
+///
+/// ```text
+/// 00000000  64 34 3a 69 6e 66 6f 64 34 3a 6e 61 6d 65 31 3a  |d4:infod4:name1:|  dict at 0x0, key "info" at 0x1, dict at 0x7, key "name" at 0x8, bytes (1) at 0xe
+/// 00000010  78 65 65                                         |xee|  end at 0x11, end at 0x12
+/// ```
+///
+/// Malformed input is dumped in full, with the error noted where parsing
+/// stopped.
+pub fn annotated_hexdump(input: &[u8]) -> String {
+    let mut notes: Vec<(usize, String)> = Vec::new();
+    let mut stack: Vec<Open> = Vec::new();
+    let mut tokens = Tokens::new(input);
+    while let Some(next) = tokens.next() {
+        let (token, span) = match next {
+            Ok(next) => next,
+            Err(e) => {
+                notes.push((tokens.offset(), format!("error: {}", e)));
+                break;
+            }
+        };
+        let is_key = stack.last() == Some(&Open::Key);
+        let note = match token {
+            Token::Int(i) => format!("int {}", i),
+            Token::Bytes(b) if is_key => {
+                let mut note = String::from("key ");
+                let _ = write_bytes(&mut note, b);
+                note
+            }
+            Token::Bytes(b) => format!("bytes ({})", b.len()),
+            Token::ListStart => "list".to_string(),
+            Token::DictStart => "dict".to_string(),
+            Token::End => "end".to_string(),
+        };
+        notes.push((span.start, note));
+
+        match token {
+            Token::ListStart => stack.push(Open::List),
+            Token::DictStart => stack.push(Open::Key),
+            _ if is_key => *stack.last_mut().unwrap() = Open::Value,
+            _ => {
+                if token == Token::End {
+                    stack.pop();
+                }
+                // a finished value is followed by the next key
+                if let Some(open @ Open::Value) = stack.last_mut() {
+                    *open = Open::Key;
+                }
+            }
+        }
+    }
+
+    let mut out = String::new();
+    let mut notes = notes.into_iter().peekable();
+    for (i, line) in input.chunks(HEXDUMP_WIDTH).enumerate() {
+        let start = i * HEXDUMP_WIDTH;
+        let _ = write!(out, "{:08x}  ", start);
+        for b in line {
+            let _ = write!(out, "{:02x} ", b);
+        }
+        for _ in line.len()..HEXDUMP_WIDTH {
+            out.push_str("   ");
+        }
+        out.push_str(" |");
+        for b in line {
+            out.push(if b.is_ascii_graphic() || *b == b' ' {
+                *b as char
+            } else {
+                '.'
+            });
+        }
+        out.push('|');
+        let mut first = true;
+        while let Some((offset, note)) = notes.next_if(|(offset, _)| *offset < start + line.len()) {
+            out.push_str(if first { "  " } else { ", " });
+            first = false;
+            let _ = write!(out, "{} at {:#x}", note, offset);
+        }
+        out.push('\n');
+    }
+    // an error at the very end of the input
+    for (offset, note) in notes {
+        let _ = writeln!(out, "{:8}  {} at {:#x}", "", note, offset);
+    }
+    out
+}
+
+// an open container, and for dictionaries, what comes next
+#[derive(PartialEq)]
+enum Open {
+    List,
+    Key,
+    Value,
+}
+
+#[derive(Default)]
+struct Dumper {
+    out: String,
+    // open containers: whether each is a dictionary, and how many entries
+    // it has so far
+    stack: Vec<(bool, usize)>,
+}
+
+impl Dumper {
+    // starts a new line for the next entry of the innermost container
+    fn entry(&mut self) {
+        let depth = self.stack.len();
+        if let Some((_, len)) = self.stack.last_mut() {
+            if *len > 0 {
+                self.out.push(',');
+            }
+            *len += 1;
+            self.out.push('\n');
+            for _ in 0..depth {
+                self.out.push_str("  ");
+            }
+        }
+    }
+
+    // list elements are entries; dictionary values follow their key
+    fn value(&mut self) {
+        if let Some((false, _)) = self.stack.last() {
+            self.entry();
+        }
+    }
+
+    fn open(&mut self, dict: bool) -> Result<()> {
+        self.value();
+        self.out.push(if dict { '{' } else { '[' });
+        self.stack.push((dict, 0));
+        Ok(())
+    }
+
+    fn close(&mut self, close: char) -> Result<()> {
+        if let Some((_, len)) = self.stack.pop() {
+            if len > 0 {
+                self.out.push('\n');
+                for _ in 0..self.stack.len() {
+                    self.out.push_str("  ");
+                }
+            }
+        }
+        self.out.push(close);
+        Ok(())
+    }
+}
+
+// writing to a String can't fail
+impl Handler for Dumper {
+    fn on_int(&mut self, value: i64) -> Result<()> {
+        self.value();
+        let _ = write!(self.out, "{}", value);
+        Ok(())
+    }
+
+    fn on_bytes(&mut self, value: &[u8]) -> Result<()> {
+        self.value();
+        let _ = write_bytes(&mut self.out, value);
+        Ok(())
+    }
+
+    fn on_list_start(&mut self) -> Result<()> {
+        self.open(false)
+    }
+
+    fn on_list_end(&mut self) -> Result<()> {
+        self.close(']')
+    }
+
+    fn on_dict_start(&mut self) -> Result<()> {
+        self.open(true)
+    }
+
+    fn on_dict_key(&mut self, key: &[u8]) -> Result<()> {
+        self.entry();
+        let _ = write_bytes(&mut self.out, key);
+        self.out.push_str(": ");
+        Ok(())
+    }
+
+    fn on_dict_end(&mut self) -> Result<()> {
+        self.close('}')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{annotated_hexdump, to_debug_string};
+    use crate::value::Value;
+
+    #[test]
+    fn test_to_debug_string() {
+        let input = b"d1:bli1e0:lee1:a20:\x00\x01\x02\x03\x04\x05\x06\x07\x08\x09\x0a\x0b\x0c\x0d\x0e\x0f\x10\x11\x12\x131:ai-2ee";
+        assert_eq!(
+            "{\n  \"b\": [\n    1,\n    \"\",\n    []\n  ],\n  \
+             \"a\": <000102030405060708090a0b0c0d0e0f10111213>,\n  \
+             \"a\": -2\n}",
+            to_debug_string(input).unwrap()
+        );
+        assert_eq!("{}", to_debug_string(b"de").unwrap());
+        assert_eq!("\"spam\"", to_debug_string(b"4:spam").unwrap());
+        assert!(to_debug_string(b"l").is_err());
+    }
+
+    #[test]
+    fn test_to_debug_string_matches_value() {
+        let input = include_bytes!("../debian.torrent").to_vec();
+        let value: Value = crate::from_bytes(&input).unwrap();
+        assert_eq!(format!("{:#}", value), to_debug_string(&input).unwrap());
+    }
+
+    #[test]
+    fn test_annotated_hexdump() {
+        let dump = annotated_hexdump(b"d4:infod4:name1:xee");
+        assert_eq!(
+            vec![
+                "00000000  64 34 3a 69 6e 66 6f 64 34 3a 6e 61 6d 65 31 3a  |d4:infod4:name1:|  \
+                 dict at 0x0, key \"info\" at 0x1, dict at 0x7, key \"name\" at 0x8, \
+                 bytes (1) at 0xe",
+                "00000010  78 65 65                                         |xee|  \
+                 end at 0x11, end at 0x12",
+            ],
+            dump.lines().collect::<Vec<_>>()
+        );
+
+        let dump = annotated_hexdump(b"li1e1:ai2");
+        assert_eq!(
+            "00000000  6c 69 31 65 31 3a 61 69 32                       |li1e1:ai2|  \
+             list at 0x0, int 1 at 0x1, bytes (1) at 0x4\n         \
+             \x20error: unexpected end of input at 0x9\n",
+            dump
+        );
+    }
+}
@@ -0,0 +1,236 @@
+use crate::error::{Error, Result};
+use crate::io;
+use alloc::format;
+use alloc::vec::Vec;
+
+/// A push-style writer for building bencode by hand, for data that doesn't
+/// exist as a `Serialize` type.
+///
+/// Dictionary keys must be written in sorted order, every `begin_*` must
+/// be closed with `end` before `finish`, and there is exactly one value at
+/// the top level.
+pub struct Encoder<W> {
+    output: W,
+    stack: Vec<Frame>,
+    // the top-level value has been started
+    root: bool,
+}
+
+enum Frame {
+    List,
+    Dict {
+        last_key: Option<Vec<u8>>,
+        // a key has been written and its value has not
+        pending: bool,
+    },
+}
+
+impl<W: io::Write> Encoder<W> {
+    pub fn new(output: W) -> Self {
+        Encoder {
+            output,
+            stack: Vec::new(),
+            root: false,
+        }
+    }
+
+    pub fn begin_dict(&mut self) -> Result<&mut Self> {
+        self.value_start()?;
+        self.stack.push(Frame::Dict {
+            last_key: None,
+            pending: false,
+        });
+        self.write(b"d")
+    }
+
+    pub fn begin_list(&mut self) -> Result<&mut Self> {
+        self.value_start()?;
+        self.stack.push(Frame::List);
+        self.write(b"l")
+    }
+
+    pub fn key<K: AsRef<[u8]>>(&mut self, key: K) -> Result<&mut Self> {
+        let key = key.as_ref();
+        match self.stack.last_mut() {
+            Some(Frame::Dict { last_key, pending }) => {
+                if *pending {
+                    return Err(Error::Encoder("expected a value, found a key"));
+                }
+                if let Some(last) = last_key {
+                    if last.as_slice() >= key {
+                        return Err(Error::NonLexicographical);
+                    }
+                    last.clear();
+                    last.extend_from_slice(key);
+                } else {
+                    *last_key = Some(key.to_vec());
+                }
+                *pending = true;
+            }
+            _ => return Err(Error::Encoder("key written outside of a dictionary")),
+        }
+        self.write_byte_array(key)
+    }
+
+    pub fn int(&mut self, v: i64) -> Result<&mut Self> {
+        self.value_start()?;
+        self.write(format!("i{}e", v).as_bytes())
+    }
+
+    pub fn bytes<B: AsRef<[u8]>>(&mut self, v: B) -> Result<&mut Self> {
+        self.value_start()?;
+        self.write_byte_array(v.as_ref())
+    }
+
+    pub fn end(&mut self) -> Result<&mut Self> {
+        // the frame stays open when closing it fails
+        match self.stack.last() {
+            Some(Frame::Dict { pending: true, .. }) => {
+                Err(Error::Encoder("dictionary key is missing its value"))
+            }
+            Some(_) => {
+                self.stack.pop();
+                self.write(b"e")
+            }
+            None => Err(Error::Encoder("end without an open list or dictionary")),
+        }
+    }
+
+    /// Checks that a value was written and every list and dictionary was
+    /// closed, and returns the underlying writer.
+    pub fn finish(self) -> Result<W> {
+        if !self.root {
+            Err(Error::Encoder("nothing was written"))
+        } else if !self.stack.is_empty() {
+            Err(Error::Encoder("unclosed list or dictionary"))
+        } else {
+            Ok(self.output)
+        }
+    }
+
+    fn value_start(&mut self) -> Result<()> {
+        match self.stack.last_mut() {
+            Some(Frame::Dict { pending, .. }) => {
+                if !*pending {
+                    return Err(Error::Encoder("expected a dictionary key"));
+                }
+                *pending = false;
+                Ok(())
+            }
+            Some(Frame::List) => Ok(()),
+            None if self.root => Err(Error::Encoder("more than one top-level value")),
+            None => {
+                self.root = true;
+                Ok(())
+            }
+        }
+    }
+
+    fn write(&mut self, b: &[u8]) -> Result<&mut Self> {
+        self.output.write_all(b)?;
+        Ok(self)
+    }
+
+    fn write_byte_array(&mut self, b: &[u8]) -> Result<&mut Self> {
+        self.write(format!("{}:", b.len()).as_bytes())?;
+        self.write(b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Encoder;
+    use crate::error::Error;
+
+    #[test]
+    fn test_nested() {
+        let mut enc = Encoder::new(Vec::new());
+        enc.begin_dict()
+            .unwrap()
+            .key("interval")
+            .unwrap()
+            .int(1800)
+            .unwrap()
+            .key("peers")
+            .unwrap()
+            .begin_list()
+            .unwrap();
+        for ip in ["a", "b"] {
+            enc.begin_dict()
+                .unwrap()
+                .key("ip")
+                .unwrap()
+                .bytes(ip)
+                .unwrap()
+                .end()
+                .unwrap();
+        }
+        enc.end().unwrap().end().unwrap();
+
+        let expected = b"d8:intervali1800e5:peersld2:ip1:aed2:ip1:beee";
+        assert_eq!(expected.to_vec(), enc.finish().unwrap());
+    }
+
+    #[test]
+    fn test_unsorted_keys() {
+        let mut enc = Encoder::new(Vec::new());
+        enc.begin_dict().unwrap().key("b").unwrap().int(1).unwrap();
+        assert_eq!(Err(Error::NonLexicographical), enc.key("a").map(|_| ()));
+        assert_eq!(Err(Error::NonLexicographical), enc.key("b").map(|_| ()));
+    }
+
+    #[test]
+    fn test_missing_key() {
+        let mut enc = Encoder::new(Vec::new());
+        enc.begin_dict().unwrap();
+        assert!(enc.int(1).is_err());
+    }
+
+    #[test]
+    fn test_unbalanced() {
+        let mut enc = Encoder::new(Vec::new());
+        assert!(enc.end().is_err());
+
+        let mut enc = Encoder::new(Vec::new());
+        enc.begin_list().unwrap();
+        assert!(enc.finish().is_err());
+
+        let mut enc = Encoder::new(Vec::new());
+        enc.begin_dict().unwrap().key("a").unwrap();
+        assert!(enc.end().is_err());
+
+        // a failed `end` leaves the dictionary open
+        let mut enc = Encoder::new(Vec::new());
+        enc.begin_list()
+            .unwrap()
+            .begin_dict()
+            .unwrap()
+            .key("a")
+            .unwrap();
+        assert!(enc.end().is_err());
+        enc.int(1).unwrap().end().unwrap().end().unwrap();
+        assert_eq!(b"ld1:ai1eee".to_vec(), enc.finish().unwrap());
+    }
+
+    #[test]
+    fn test_root_values() {
+        let mut enc = Encoder::new(Vec::new());
+        enc.int(1).unwrap();
+        assert_eq!(
+            Err(Error::Encoder("more than one top-level value")),
+            enc.int(2).map(|_| ())
+        );
+        assert!(enc.begin_list().is_err());
+        assert_eq!(b"i1e".to_vec(), enc.finish().unwrap());
+
+        let mut enc = Encoder::new(Vec::new());
+        enc.begin_list().unwrap().end().unwrap();
+        assert!(enc.bytes("x").is_err());
+
+        let enc = Encoder::new(Vec::new());
+        assert_eq!(
+            Err(Error::Encoder("nothing was written")),
+            enc.finish().map(|_| ())
+        );
+    }
+}
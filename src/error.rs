@@ -1,12 +1,20 @@
-use std::fmt::{self, Display};
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::fmt::{self, Display};
 
-use serde::de;
+use serde::ser::SerializeStruct;
+use serde::{de, ser, Serialize, Serializer};
 
-pub type Result<T> = std::result::Result<T, Error>;
+use crate::io;
 
-#[derive(Debug, PartialEq)]
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     Message(String),
+    Io(io::Error),
 
     // integer
     NegativeZero,
@@ -29,9 +37,34 @@ pub enum Error {
     ExpectedList,
     ExpectedListEnd,
 
+    // serialization
+    Unsupported(&'static str),
+    KeyMustBeByteString,
+    Encoder(&'static str),
+    NonCanonical(&'static str),
+    DuplicateKey(String),
+    IntegerOutOfRange,
+
     TrailingCharacters,
     Eof,
     Syntax,
+    DepthLimitExceeded,
+    FrameTooLong,
+
+    /// Another error, with the keys and indexes leading to the value it
+    /// was found in, such as `info.files[3].length`.
+    Path {
+        path: String,
+        error: Box<Error>,
+    },
+
+    /// Another error, with where in the input it was found.
+    Located {
+        offset: usize,
+        /// The byte at `offset`, for syntax errors.
+        found: Option<u8>,
+        error: Box<Error>,
+    },
 }
 
 impl de::Error for Error {
@@ -43,22 +76,465 @@ impl de::Error for Error {
     }
 }
 
-impl std::error::Error for Error {}
+impl ser::Error for Error {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: Display,
+    {
+        Error::Message(msg.to_string())
+    }
+}
+
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Path { error, .. } | Error::Located { error, .. } => error.source(),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+// `io::Error` isn't comparable, so I/O errors are equal when their kinds are.
+// Locations aren't compared, so `from_bytes(..) == Err(Error::Eof)` holds.
+impl PartialEq for Error {
+    fn eq(&self, other: &Error) -> bool {
+        use Error::*;
+        match (self.inner(), other.inner()) {
+            (Message(a), Message(b)) => a == b,
+            (Io(a), Io(b)) => a.kind() == b.kind(),
+            (Unsupported(a), Unsupported(b)) => a == b,
+            (Encoder(a), Encoder(b)) => a == b,
+            (NonCanonical(a), NonCanonical(b)) => a == b,
+            (DuplicateKey(a), DuplicateKey(b)) => a == b,
+            // the remaining variants carry no data
+            (a, b) => core::mem::discriminant(a) == core::mem::discriminant(b),
+        }
+    }
+}
+
+impl Error {
+    /// Displays the error followed by a hex dump of the line of `input`
+    /// around the error's offset, with a caret under the failing byte:
+    ///
+    /// ```text
+    /// expected `e` to end an integer, found `x` at byte 3
+    ///   00000000  69 31 32 78                                      |i12x|
+    ///                      ^^
+    /// ```
+    ///
+    /// `input` must be what was deserialized. Errors without an offset
+    /// display as usual.
+    pub fn with_input<'a>(&'a self, input: &'a [u8]) -> Snippet<'a> {
+        Snippet { error: self, input }
+    }
+
+    /// A short, stable name for the kind of error, such as `"expected_e"`,
+    /// for logging and metrics. Locations and paths don't change it.
+    pub fn code(&self) -> &'static str {
+        match self.inner() {
+            Error::Message(_) => "message",
+            Error::Io(_) => "io",
+            Error::NegativeZero => "negative_zero",
+            Error::NonASCII => "non_ascii",
+            Error::ExpectedInteger => "expected_integer",
+            Error::ExpectedI => "expected_i",
+            Error::ExpectedE => "expected_e",
+            Error::ZeroLength => "zero_length",
+            Error::NegativeLength => "negative_length",
+            Error::ExpectedColon => "expected_colon",
+            Error::NonLexicographical => "non_lexicographical",
+            Error::ExpectedDict => "expected_dict",
+            Error::ExpectedDictEnd => "expected_dict_end",
+            Error::ExpectedList => "expected_list",
+            Error::ExpectedListEnd => "expected_list_end",
+            Error::Unsupported(_) => "unsupported",
+            Error::KeyMustBeByteString => "key_must_be_byte_string",
+            Error::Encoder(_) => "encoder",
+            Error::NonCanonical(_) => "non_canonical",
+            Error::DuplicateKey(_) => "duplicate_key",
+            Error::IntegerOutOfRange => "integer_out_of_range",
+            Error::TrailingCharacters => "trailing_characters",
+            Error::Eof => "eof",
+            Error::Syntax => "syntax",
+            Error::DepthLimitExceeded => "depth_limit_exceeded",
+            Error::FrameTooLong => "frame_too_long",
+            // `inner` never returns these
+            Error::Path { .. } | Error::Located { .. } => unreachable!(),
+        }
+    }
+
+    /// The byte offset into the input where the error was found, if known.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Error::Located { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    /// The path to the value the error was found in, if known.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Error::Path { path, .. } => Some(path),
+            Error::Located { error, .. } => error.path(),
+            _ => None,
+        }
+    }
+
+    /// The error without its location or path.
+    pub fn inner(&self) -> &Error {
+        match self {
+            Error::Path { error, .. } | Error::Located { error, .. } => error.inner(),
+            _ => self,
+        }
+    }
+
+    pub(crate) fn at_index(self, index: usize) -> Error {
+        self.prepend(format!("[{}]", index))
+    }
+
+    pub(crate) fn at_key(self, key: &[u8]) -> Error {
+        self.prepend(String::from_utf8_lossy(key).into_owned())
+    }
+
+    fn prepend(self, mut segment: String) -> Error {
+        match self {
+            Error::Path { path, error } => {
+                if !path.starts_with('[') {
+                    segment.push('.');
+                }
+                segment.push_str(&path);
+                Error::Path {
+                    path: segment,
+                    error,
+                }
+            }
+            error => Error::Path {
+                path: segment,
+                error: Box::new(error),
+            },
+        }
+    }
+}
 
 impl Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Error::Message(msg) => f.write_str(msg),
-            Error::NegativeZero => f.write_str("disallowed negative zero"),
-            Error::NonASCII => f.write_str("disallowed non-ascii character"),
-            Error::ExpectedInteger => f.write_str("expected an integer"),
-            Error::ZeroLength => f.write_str("disallowed zero-length byte string"),
-            Error::NegativeLength => f.write_str("disallowed negative length bytes string"),
-            Error::NonLexicographical => f.write_str("keys not lexicographically sorted"),
-            Error::TrailingCharacters => f.write_str("unexpected trailing characters"),
-            Error::Eof => f.write_str("End of fuck"),
-            Error::ExpectedColon => f.write_str("Expected a colon between length and string"),
-            _ => f.write_str("shit"),
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Path { path, error } => write!(f, "{}: {}", path, error),
+            Error::Located {
+                offset,
+                found,
+                error,
+            } => {
+                write!(f, "{}", error)?;
+                if let Some(b) = found {
+                    write!(f, ", found {}", DisplayByte(*b))?;
+                }
+                write!(f, " at byte {}", offset)
+            }
+            Error::NegativeZero => f.write_str("integer is negative zero (`i-0e`)"),
+            Error::NonASCII => f.write_str("expected an ASCII character"),
+            Error::ExpectedInteger => f.write_str("expected a digit"),
+            Error::ExpectedI => f.write_str("expected `i` to start an integer"),
+            Error::ExpectedE => f.write_str("expected `e` to end an integer"),
+            Error::ZeroLength => f.write_str("byte string has zero length"),
+            Error::NegativeLength => f.write_str("byte string has a negative length"),
+            Error::ExpectedColon => f.write_str("expected `:` after a byte string length"),
+            Error::NonLexicographical => f.write_str("dictionary keys are not in sorted order"),
+            Error::ExpectedDict => f.write_str("expected `d` to start a dictionary"),
+            Error::ExpectedDictEnd => f.write_str("expected `e` to end a dictionary"),
+            Error::ExpectedList => f.write_str("expected `l` to start a list"),
+            Error::ExpectedListEnd => f.write_str("expected `e` to end a list"),
+            Error::Unsupported(what) => write!(f, "cannot serialize {} as bencode", what),
+            Error::KeyMustBeByteString => f.write_str("dictionary keys must be byte strings"),
+            Error::Encoder(msg) => f.write_str(msg),
+            Error::NonCanonical(what) => write!(f, "{} has no canonical encoding", what),
+            Error::DuplicateKey(key) => write!(f, "duplicate dictionary key {:?}", key),
+            Error::IntegerOutOfRange => f.write_str("integer out of range"),
+            Error::TrailingCharacters => f.write_str("unexpected data after the end of the value"),
+            Error::Eof => f.write_str("unexpected end of input"),
+            Error::Syntax => f.write_str("expected a value: `i`, `l`, `d` or a byte string length"),
+            Error::DepthLimitExceeded => {
+                f.write_str("lists and dictionaries are nested too deeply")
+            }
+            Error::FrameTooLong => f.write_str("value is longer than the maximum frame length"),
+        }
+    }
+}
+
+/// Serializes as a struct with the error's `code`, its `message` without
+/// location or path, and its `offset` and `path` when they're known.
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Error", 4)?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("message", &self.inner().to_string())?;
+        s.serialize_field("offset", &self.offset())?;
+        s.serialize_field("path", &self.path())?;
+        s.end()
+    }
+}
+
+// Errors don't hold on to their input, so attach it to render the label:
+// `miette::Report::new(err).with_source_code(input.to_vec())`.
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for Error {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new(format!("serde_bencode::{}", Error::code(self))))
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        let offset = self.offset()?;
+        let len = match self {
+            Error::Located { found: Some(_), .. } => 1,
+            _ => 0,
+        };
+        let label = match self.path() {
+            Some(path) => format!("in {}", path),
+            None => "here".to_string(),
+        };
+        Some(Box::new(core::iter::once(miette::LabeledSpan::new(
+            Some(label),
+            offset,
+            len,
+        ))))
+    }
+}
+
+/// An error displayed with an excerpt of its input; see `Error::with_input`.
+pub struct Snippet<'a> {
+    error: &'a Error,
+    input: &'a [u8],
+}
+
+const SNIPPET_WIDTH: usize = 16;
+
+impl Display for Snippet<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)?;
+        let offset = match self.error.offset() {
+            Some(offset) if offset <= self.input.len() => offset,
+            _ => return Ok(()),
+        };
+        // an error at the very end points just past the last line
+        let start = match offset {
+            0 => 0,
+            _ if offset == self.input.len() => (offset - 1) / SNIPPET_WIDTH * SNIPPET_WIDTH,
+            _ => offset / SNIPPET_WIDTH * SNIPPET_WIDTH,
+        };
+        let line = &self.input[start..self.input.len().min(start + SNIPPET_WIDTH)];
+
+        write!(f, "\n  {:08x}  ", start)?;
+        for b in line {
+            write!(f, "{:02x} ", b)?;
+        }
+        for _ in line.len()..SNIPPET_WIDTH {
+            f.write_str("   ")?;
+        }
+        f.write_str(" |")?;
+        for b in line {
+            let c = if b.is_ascii_graphic() || *b == b' ' {
+                *b as char
+            } else {
+                '.'
+            };
+            write!(f, "{}", c)?;
+        }
+        f.write_str("|")?;
+        write!(f, "\n  {:w$}^^", "", w = 10 + (offset - start) * 3)
+    }
+}
+
+// a byte as it'd appear in the input: `x` when printable, `0x00` otherwise
+struct DisplayByte(u8);
+
+impl Display for DisplayByte {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_ascii_graphic() {
+            write!(f, "`{}`", self.0 as char)
+        } else {
+            write!(f, "0x{:02x}", self.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Error;
+    #[cfg(feature = "std")]
+    use std::error::Error as _;
+    #[cfg(feature = "std")]
+    use std::io;
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_io_error() {
+        struct Broken;
+        impl io::Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::ConnectionReset, "reset"))
+            }
+        }
+
+        let err = crate::from_reader::<_, i64>(Broken).unwrap_err();
+        assert!(matches!(err.inner(), Error::Io(e) if e.kind() == io::ErrorKind::ConnectionReset));
+        assert_eq!("reset", err.source().unwrap().to_string());
+        assert!(Error::Eof.source().is_none());
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!("unexpected end of input", Error::Eof.to_string());
+        assert_eq!(
+            "expected `e` to end an integer",
+            Error::ExpectedE.to_string()
+        );
+        assert_eq!(
+            "expected `:` after a byte string length",
+            Error::ExpectedColon.to_string()
+        );
+        assert_eq!(
+            "expected a value: `i`, `l`, `d` or a byte string length",
+            Error::Syntax.to_string()
+        );
+        assert_eq!(
+            "dictionary keys are not in sorted order",
+            Error::NonLexicographical.to_string()
+        );
+    }
+
+    #[test]
+    fn test_located() {
+        let err = crate::from_bytes::<i64>(b"i12x").unwrap_err();
+        assert_eq!(Some(3), err.offset());
+        assert_eq!(&Error::ExpectedE, err.inner());
+        assert_eq!(Error::ExpectedE, err);
+        assert_eq!(
+            "expected `e` to end an integer, found `x` at byte 3",
+            err.to_string()
+        );
+
+        let err = crate::from_bytes::<Vec<i64>>(b"li1e").unwrap_err();
+        assert_eq!("unexpected end of input at byte 4", err.to_string());
+        let err = crate::from_bytes::<i64>(b"i1e\x00").unwrap_err();
+        assert_eq!(
+            "unexpected data after the end of the value, found 0x00 at byte 3",
+            err.to_string()
+        );
+        let err = crate::from_bytes::<String>(b"i1e").unwrap_err();
+        assert_eq!(
+            "invalid type: integer `1`, expected a string at byte 3",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_path() {
+        #[derive(Debug, serde::Deserialize)]
+        #[allow(dead_code)]
+        struct File {
+            length: i64,
+        }
+
+        #[derive(Debug, serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Info {
+            files: Vec<File>,
+        }
+
+        #[derive(Debug, serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Torrent {
+            info: Info,
         }
+
+        let err = crate::from_bytes::<Torrent>(b"d4:infod5:filesld6:lengthi1eed6:length1:xeeee")
+            .unwrap_err();
+        assert_eq!(Some("info.files[1].length"), err.path());
+        assert_eq!(
+            "info.files[1].length: expected `i` to start an integer, found `1` at byte 38",
+            err.to_string()
+        );
+
+        let err = crate::from_bytes::<Vec<Vec<i64>>>(b"lli1eeli2ei3xee").unwrap_err();
+        assert_eq!(Some("[1][1]"), err.path());
+        assert_eq!(Error::ExpectedE, err);
+    }
+
+    #[test]
+    fn test_snippet() {
+        let input = b"i12x";
+        let err = crate::from_bytes::<i64>(input).unwrap_err();
+        let snippet = err.with_input(input).to_string();
+        let lines: Vec<_> = snippet.lines().collect();
+        assert_eq!(
+            vec![
+                "expected `e` to end an integer, found `x` at byte 3",
+                &format!("  00000000  69 31 32 78 {:36} |i12x|", ""),
+                "                     ^^",
+            ],
+            lines
+        );
+
+        let input = b"d8:announce3:url4:infod6:lengthi1e4:name";
+        let err = crate::from_bytes::<crate::Value>(input).unwrap_err();
+        let snippet = err.with_input(input).to_string();
+        let lines: Vec<_> = snippet.lines().collect();
+        assert_eq!("info.name: unexpected end of input at byte 40", lines[0]);
+        assert_eq!(
+            format!("  00000020  31 65 34 3a 6e 61 6d 65 {:24} |1e4:name|", ""),
+            lines[1]
+        );
+        // just past the last byte
+        assert_eq!(Some(12 + 8 * 3), lines[2].find('^'));
+
+        assert_eq!(
+            "unexpected end of input",
+            Error::Eof.with_input(b"").to_string()
+        );
+    }
+
+    #[cfg(feature = "miette")]
+    #[test]
+    fn test_diagnostic() {
+        use miette::Diagnostic;
+
+        let err = crate::from_bytes::<crate::Value>(b"d1:ai1xe").unwrap_err();
+        let labels: Vec<_> = err.labels().unwrap().collect();
+        assert_eq!(1, labels.len());
+        assert_eq!(6, labels[0].offset());
+        assert_eq!(1, labels[0].len());
+        assert_eq!(Some("in a"), labels[0].label());
+        assert!(Error::Eof.labels().is_none());
+    }
+
+    #[test]
+    fn test_code() {
+        assert_eq!("eof", Error::Eof.code());
+        let err = crate::from_bytes::<Vec<i64>>(b"li1x").unwrap_err();
+        assert_eq!("expected_e", err.code());
+
+        let json = serde_json::to_value(&err).unwrap();
+        assert_eq!(
+            serde_json::json!({
+                "code": "expected_e",
+                "message": "expected `e` to end an integer",
+                "offset": 3,
+                "path": "[0]",
+            }),
+            json
+        );
+        assert_eq!(
+            &b"d4:code3:eof7:message23:unexpected end of inpute"[..],
+            &crate::to_bytes(&Error::Eof).unwrap()[..]
+        );
     }
 }
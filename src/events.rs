@@ -0,0 +1,195 @@
+use crate::de::Deserializer;
+use crate::error::{Error, Result};
+use crate::read::Read;
+
+/// Receives parse events in input order. Every method does nothing by
+/// default; returning an error stops the parse.
+pub trait Handler {
+    fn on_int(&mut self, _value: i64) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_bytes(&mut self, _value: &[u8]) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_list_start(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_list_end(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_dict_start(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called before each dictionary value, which follows as its own events.
+    fn on_dict_key(&mut self, _key: &[u8]) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_dict_end(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<H: Handler + ?Sized> Handler for &mut H {
+    fn on_int(&mut self, value: i64) -> Result<()> {
+        (**self).on_int(value)
+    }
+
+    fn on_bytes(&mut self, value: &[u8]) -> Result<()> {
+        (**self).on_bytes(value)
+    }
+
+    fn on_list_start(&mut self) -> Result<()> {
+        (**self).on_list_start()
+    }
+
+    fn on_list_end(&mut self) -> Result<()> {
+        (**self).on_list_end()
+    }
+
+    fn on_dict_start(&mut self) -> Result<()> {
+        (**self).on_dict_start()
+    }
+
+    fn on_dict_key(&mut self, key: &[u8]) -> Result<()> {
+        (**self).on_dict_key(key)
+    }
+
+    fn on_dict_end(&mut self) -> Result<()> {
+        (**self).on_dict_end()
+    }
+}
+
+impl<'de, R: Read<'de>> Deserializer<R> {
+    /// Parses one value, passing each part of it to `handler` instead of
+    /// building anything. Byte strings are borrowed from slice input.
+    pub fn parse_events<H: Handler>(&mut self, handler: &mut H) -> Result<()> {
+        match self.peek_byte()? {
+            b'i' => handler.on_int(self.parse_num()?),
+            b'0'..=b'9' => handler.on_bytes(&self.parse_byte_array()?),
+            b'l' => {
+                self.next_byte()?;
+                handler.on_list_start()?;
+                self.nested(|de| {
+                    while de.peek_byte()? != b'e' {
+                        de.parse_events(handler)?;
+                    }
+                    Ok(())
+                })?;
+                self.next_byte()?;
+                handler.on_list_end()
+            }
+            b'd' => {
+                self.next_byte()?;
+                handler.on_dict_start()?;
+                self.nested(|de| loop {
+                    match de.peek_byte()? {
+                        b'e' => return Ok(()),
+                        b'0'..=b'9' => handler.on_dict_key(&de.parse_byte_array()?)?,
+                        _ => return Err(Error::KeyMustBeByteString),
+                    }
+                    de.parse_events(handler)?;
+                })?;
+                self.next_byte()?;
+                handler.on_dict_end()
+            }
+            _ => Err(Error::Syntax),
+        }
+    }
+}
+
+/// Parses the single value in `input`, passing each part of it to `handler`.
+pub fn parse_events<H: Handler>(input: &[u8], mut handler: H) -> Result<()> {
+    let mut de = Deserializer::from_bytes(input);
+    de.parse_events(&mut handler)
+        .and_then(|()| de.end())
+        .map_err(|e| de.locate(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_events, Handler};
+    use crate::error::{Error, Result};
+
+    #[derive(Default)]
+    struct Totals {
+        ints: i64,
+        bytes: usize,
+        depth: usize,
+        max_depth: usize,
+        keys: Vec<Vec<u8>>,
+    }
+
+    impl Handler for Totals {
+        fn on_int(&mut self, value: i64) -> Result<()> {
+            self.ints += value;
+            Ok(())
+        }
+
+        fn on_bytes(&mut self, value: &[u8]) -> Result<()> {
+            self.bytes += value.len();
+            Ok(())
+        }
+
+        fn on_list_start(&mut self) -> Result<()> {
+            self.depth += 1;
+            self.max_depth = self.max_depth.max(self.depth);
+            Ok(())
+        }
+
+        fn on_list_end(&mut self) -> Result<()> {
+            self.depth -= 1;
+            Ok(())
+        }
+
+        fn on_dict_key(&mut self, key: &[u8]) -> Result<()> {
+            self.keys.push(key.to_vec());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_parse_events() {
+        let mut totals = Totals::default();
+        parse_events(b"d1:ali1ei-3el2:xyee1:b3:abce", &mut totals).unwrap();
+        assert_eq!(-2, totals.ints);
+        assert_eq!(5, totals.bytes);
+        assert_eq!(2, totals.max_depth);
+        assert_eq!(0, totals.depth);
+        assert_eq!(vec![b"a".to_vec(), b"b".to_vec()], totals.keys);
+    }
+
+    #[test]
+    fn test_parse_events_errors() {
+        struct Stop;
+        impl Handler for Stop {
+            fn on_bytes(&mut self, _: &[u8]) -> Result<()> {
+                Err(Error::Message("stop".into()))
+            }
+        }
+
+        let mut totals = Totals::default();
+        assert_eq!(Err(Error::Eof), parse_events(b"li1e", &mut totals));
+        assert_eq!(
+            Err(Error::KeyMustBeByteString),
+            parse_events(b"di1ei2ee", &mut totals)
+        );
+        assert_eq!(
+            Err(Error::TrailingCharacters),
+            parse_events(b"i1ei2e", &mut totals)
+        );
+        assert_eq!(
+            Err(Error::Message("stop".into())),
+            parse_events(b"li1e1:ae", Stop)
+        );
+        assert_eq!(
+            Err(Error::DepthLimitExceeded),
+            parse_events(&b"l".repeat(100_000), Totals::default())
+        );
+    }
+}
@@ -0,0 +1,269 @@
+use crate::error::{Error, Result};
+
+// the same default as `tokio_util`'s `LengthDelimitedCodec`
+pub(crate) const DEFAULT_MAX_FRAME_LENGTH: usize = 8 * 1024 * 1024;
+
+/// How far a buffer gets towards holding one complete value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Frame {
+    /// The value is the first this many bytes.
+    Complete(usize),
+    /// At least this many more bytes are needed. Reading exactly that many
+    /// never reads past the end of the value.
+    Incomplete(usize),
+}
+
+/// Finds where the first value in a growing buffer ends without decoding it,
+/// picking up where the last call left off, even part way through a token.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Framer {
+    // start of the first token not yet scanned
+    pos: usize,
+    // containers opened and not yet closed
+    depth: usize,
+    // bytes of the token at `pos` already scanned
+    scanned: usize,
+    // length of the byte string at `pos`, once its prefix has been read
+    string_len: Option<usize>,
+}
+
+impl Framer {
+    pub(crate) fn new() -> Self {
+        Framer::default()
+    }
+
+    /// Scans `buf`, which must start with the bytes given to earlier calls.
+    pub(crate) fn advance(&mut self, buf: &[u8]) -> Result<Frame> {
+        loop {
+            let rest = &buf[self.pos..];
+            let first = match rest.first() {
+                Some(b) => *b,
+                None => return Ok(Frame::Incomplete(1)),
+            };
+            let len = match first {
+                b'i' => {
+                    let mut i = self.scanned.max(1);
+                    loop {
+                        match rest.get(i) {
+                            Some(b'e') => break i + 1,
+                            Some(b'-' | b'0'..=b'9') => i += 1,
+                            Some(_) => return Err(Error::ExpectedE),
+                            None => {
+                                self.scanned = i;
+                                return Ok(Frame::Incomplete(1));
+                            }
+                        }
+                    }
+                }
+                b'0'..=b'9' => {
+                    let total = match self.string_len {
+                        Some(total) => total,
+                        None => {
+                            let mut colon = self.scanned;
+                            loop {
+                                match rest.get(colon) {
+                                    Some(b':') => break,
+                                    Some(b'0'..=b'9') => colon += 1,
+                                    Some(_) => return Err(Error::ExpectedColon),
+                                    None => {
+                                        self.scanned = colon;
+                                        return Ok(Frame::Incomplete(1));
+                                    }
+                                }
+                            }
+                            let len: usize = core::str::from_utf8(&rest[..colon])
+                                .ok()
+                                .and_then(|s| s.parse().ok())
+                                .ok_or(Error::Syntax)?;
+                            // a claimed length this large can never be complete
+                            let total = len.saturating_add(colon + 1);
+                            self.string_len = Some(total);
+                            total
+                        }
+                    };
+                    if rest.len() < total {
+                        return Ok(Frame::Incomplete(total - rest.len()));
+                    }
+                    total
+                }
+                b'l' | b'd' => {
+                    self.depth += 1;
+                    1
+                }
+                b'e' if self.depth > 0 => {
+                    self.depth -= 1;
+                    1
+                }
+                _ => return Err(Error::Syntax),
+            };
+            self.pos += len;
+            self.scanned = 0;
+            self.string_len = None;
+            if self.depth == 0 {
+                let end = self.pos;
+                *self = Framer::new();
+                return Ok(Frame::Complete(end));
+            }
+        }
+    }
+
+    /// Like `advance`, but fails with `Error::FrameTooLong` as soon as the
+    /// value is known to be longer than `max`: once it's complete, or once
+    /// `buf` plus the bytes still needed goes past it, which catches a byte
+    /// string claiming more than the limit before any of it arrives. This
+    /// keeps a peer from making the buffer grow without bound. On any error
+    /// the framer starts over, as there's no telling where the next value
+    /// starts.
+    pub(crate) fn advance_capped(&mut self, buf: &[u8], max: usize) -> Result<Frame> {
+        let frame = match self.advance(buf) {
+            Ok(Frame::Complete(len)) if len > max => Err(Error::FrameTooLong),
+            Ok(Frame::Incomplete(n)) if buf.len().saturating_add(n) > max => {
+                Err(Error::FrameTooLong)
+            }
+            frame => frame,
+        };
+        if frame.is_err() {
+            *self = Framer::new();
+        }
+        frame
+    }
+
+    /// How far into the buffer has been scanned, which is where the next
+    /// call starts.
+    #[cfg(test)]
+    fn scanned(&self) -> usize {
+        self.pos + self.scanned
+    }
+}
+
+/// Returns the length of the first complete value in `input` without decoding
+/// it, or `None` if the input ends before the value does.
+pub fn measure(input: &[u8]) -> Result<Option<usize>> {
+    match Framer::new().advance(input)? {
+        Frame::Complete(len) => Ok(Some(len)),
+        Frame::Incomplete(_) => Ok(None),
+    }
+}
+
+/// Counts the integers and byte strings from the start of `input` up to the
+/// `e` closing the container they're in, scanning no more than `budget`
+/// bytes. Nested containers aren't skipped over, so every byte is scanned at
+/// most once on top of parsing it. Returns `None` unless the closing `e` is
+/// reached, since consumers may take the count as exact: a nested list or
+/// dictionary, anything malformed or running out of budget all give up.
+pub(crate) fn count_items(input: &[u8], budget: usize) -> Option<usize> {
+    let window = &input[..input.len().min(budget)];
+    let mut pos = 0;
+    let mut count = 0;
+    while let Some(b'i' | b'0'..=b'9') = window.get(pos) {
+        pos += measure(&window[pos..]).ok()??;
+        count += 1;
+    }
+    match window.get(pos) {
+        Some(b'e') => Some(count),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{count_items, measure, Frame, Framer};
+    use crate::error::Error;
+
+    #[test]
+    fn test_frame_complete() {
+        assert_eq!(Ok(Frame::Complete(4)), Framer::new().advance(b"i42ei1e"));
+        assert_eq!(Ok(Frame::Complete(5)), Framer::new().advance(b"3:abcxyz"));
+        assert_eq!(
+            Ok(Frame::Complete(14)),
+            Framer::new().advance(b"d1:ali1e2:bceee")
+        );
+    }
+
+    #[test]
+    fn test_frame_incremental() {
+        let input = b"d4:spaml1:ai-1eee";
+        let mut framer = Framer::new();
+        let mut len = 0;
+        loop {
+            match framer.advance(&input[..len]).unwrap() {
+                Frame::Incomplete(n) => {
+                    assert!(len + n <= input.len());
+                    len += n;
+                }
+                Frame::Complete(n) => {
+                    assert_eq!(input.len(), n);
+                    break;
+                }
+            }
+        }
+        assert_eq!(Ok(Frame::Incomplete(3)), Framer::new().advance(b"5:ab"));
+    }
+
+    #[test]
+    fn test_frame_resumes_in_token() {
+        let mut framer = Framer::new();
+        assert_eq!(Ok(Frame::Incomplete(1)), framer.advance(b"li123"));
+        assert_eq!(5, framer.scanned());
+        assert_eq!(Ok(Frame::Incomplete(1)), framer.advance(b"li12345"));
+        assert_eq!(7, framer.scanned());
+        assert_eq!(Ok(Frame::Incomplete(1)), framer.advance(b"li12345e12"));
+        assert_eq!(10, framer.scanned());
+        assert_eq!(
+            Ok(Frame::Incomplete(2)),
+            framer.advance(b"li12345e12:abcdefghij")
+        );
+        assert_eq!(
+            Ok(Frame::Complete(24)),
+            framer.advance(b"li12345e12:abcdefghijkle")
+        );
+
+        let mut framer = Framer::new();
+        assert_eq!(Ok(Frame::Incomplete(1)), framer.advance(b"i12"));
+        assert_eq!(Err(Error::ExpectedE), framer.advance(b"i12x"));
+        let mut framer = Framer::new();
+        assert_eq!(Ok(Frame::Incomplete(1)), framer.advance(b"12"));
+        assert_eq!(Err(Error::ExpectedColon), framer.advance(b"12x"));
+    }
+
+    #[test]
+    fn test_frame_errors() {
+        assert_eq!(Err(Error::Syntax), Framer::new().advance(b"x"));
+        assert_eq!(Err(Error::Syntax), Framer::new().advance(b"e"));
+        assert_eq!(Err(Error::ExpectedColon), Framer::new().advance(b"3x"));
+        assert_eq!(Err(Error::ExpectedE), Framer::new().advance(b"i1x"));
+    }
+
+    #[test]
+    fn test_measure() {
+        assert_eq!(Ok(Some(10)), measure(b"l3:abci1eeli2ee"));
+        assert_eq!(Ok(None), measure(b"l3:abci1e"));
+        assert_eq!(Ok(None), measure(b""));
+        assert_eq!(Err(Error::Syntax), measure(b"x"));
+        // a length that overflows once the prefix is added
+        assert_eq!(Ok(None), measure(b"18446744073709551615:"));
+    }
+
+    #[test]
+    fn test_frame_capped() {
+        let mut framer = Framer::new();
+        assert_eq!(Ok(Frame::Complete(5)), framer.advance_capped(b"l1:ae", 5));
+        assert_eq!(Err(Error::FrameTooLong), framer.advance_capped(b"l1:ae", 4));
+        // a byte string claiming more than the limit fails up front
+        assert_eq!(Err(Error::FrameTooLong), framer.advance_capped(b"9:a", 8));
+        assert_eq!(Ok(Frame::Incomplete(1)), framer.advance_capped(b"l1:a", 9));
+        // the framer starts over after an error
+        assert_eq!(Err(Error::Syntax), framer.advance_capped(b"l1:ax", 9));
+        assert_eq!(Ok(Frame::Complete(3)), framer.advance_capped(b"i1e", 9));
+    }
+
+    #[test]
+    fn test_count_items() {
+        assert_eq!(Some(3), count_items(b"i1e1:ai-2eexyz", 100));
+        assert_eq!(Some(0), count_items(b"e", 100));
+        assert_eq!(None, count_items(b"i1eli2ee1:ae", 100));
+        assert_eq!(None, count_items(b"i1e1:ae", 5));
+        assert_eq!(None, count_items(b"i1ex", 100));
+        assert_eq!(None, count_items(b"i1e", 100));
+    }
+}
@@ -0,0 +1,76 @@
+use serde::de::Error as _;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, Result};
+use crate::raw::get_raw;
+
+/// Returns the BitTorrent v1 infohash of a torrent: the SHA-1 of its `info`
+/// dictionary exactly as it is encoded. Hashing a re-serialized `info`
+/// gives the wrong answer whenever the original isn't canonical.
+pub fn info_hash_v1(torrent: &[u8]) -> Result<[u8; 20]> {
+    Ok(Sha1::digest(info(torrent)?).into())
+}
+
+/// Returns the BitTorrent v2 infohash of a BEP 52 torrent: the SHA-256 of
+/// its `info` dictionary exactly as it is encoded.
+pub fn info_hash_v2(torrent: &[u8]) -> Result<[u8; 32]> {
+    Ok(Sha256::digest(info(torrent)?).into())
+}
+
+/// Returns the v2 infohash truncated to 20 bytes, the form used in places
+/// sized for v1 hashes such as the peer wire handshake and the DHT.
+pub fn info_hash_v2_truncated(torrent: &[u8]) -> Result<[u8; 20]> {
+    let hash = info_hash_v2(torrent)?;
+    let mut truncated = [0; 20];
+    truncated.copy_from_slice(&hash[..20]);
+    Ok(truncated)
+}
+
+fn info(torrent: &[u8]) -> Result<&[u8]> {
+    match get_raw(torrent, &["info"])? {
+        Some(info) if info.first() == Some(&b'd') => Ok(info),
+        Some(_) => Err(Error::ExpectedDict),
+        None => Err(Error::missing_field("info")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{info_hash_v1, info_hash_v2, info_hash_v2_truncated};
+    use crate::error::Error;
+
+    #[test]
+    fn test_info_hash_v1() {
+        let torrent = include_bytes!("../debian.torrent").to_vec();
+        assert_eq!(
+            "d55be2cd263efa84aeb9495333a4fabc428a4250",
+            hex(&info_hash_v1(&torrent).unwrap())
+        );
+
+        // keys out of order are hashed as they are
+        assert_eq!(
+            "28e6bb72ba5d7919ac19cdf1042326bd9939a064",
+            hex(&info_hash_v1(b"d4:infod1:bi1e1:ai2eee").unwrap())
+        );
+
+        assert_eq!(Err(Error::ExpectedDict), info_hash_v1(b"d4:infoi1ee"));
+        assert!(info_hash_v1(b"de").is_err());
+    }
+
+    #[test]
+    fn test_info_hash_v2() {
+        let torrent = b"d4:infod1:bi1e1:ai2eee";
+        let hash = info_hash_v2(torrent).unwrap();
+        assert_eq!(
+            "29d3f3dfd7843c305c6d643be0c6dfeb2ac7a918f4886a0437081453a70a50cf",
+            hex(&hash)
+        );
+        assert_eq!(hash[..20], info_hash_v2_truncated(torrent).unwrap());
+        assert_eq!(Err(Error::ExpectedDict), info_hash_v2(b"d4:infoi1ee"));
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
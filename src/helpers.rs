@@ -0,0 +1,248 @@
+//! `#[serde(with = ...)]` helpers for byte strings that hold text-encoded
+//! binary, like the hex info-hashes some tracker extensions use, and for
+//! text that isn't always valid UTF-8.
+//!
+//! `hex`, `base32` and `base64` decode into anything that is
+//! `TryFrom<Vec<u8>>`, so a hash can go straight into a `[u8; 20]`, and
+//! encode anything that is `AsRef<[u8]>`.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+use serde::de::{self, Deserializer, Unexpected, Visitor};
+
+fn deserialize_encoded<'de, T, D>(
+    deserializer: D,
+    expecting: &'static str,
+    decode: fn(&str) -> Option<Vec<u8>>,
+) -> Result<T, D::Error>
+where
+    T: TryFrom<Vec<u8>>,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_str(EncodedVisitor {
+        expecting,
+        decode,
+        marker: PhantomData,
+    })
+}
+
+struct EncodedVisitor<T> {
+    expecting: &'static str,
+    decode: fn(&str) -> Option<Vec<u8>>,
+    marker: PhantomData<T>,
+}
+
+impl<T: TryFrom<Vec<u8>>> Visitor<'_> for EncodedVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(self.expecting)
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<T, E>
+    where
+        E: de::Error,
+    {
+        let bytes = (self.decode)(v).ok_or_else(|| E::invalid_value(Unexpected::Str(v), &self))?;
+        let len = bytes.len();
+        T::try_from(bytes).map_err(|_| E::invalid_length(len, &"a byte string of the right length"))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<T, E>
+    where
+        E: de::Error,
+    {
+        match core::str::from_utf8(v) {
+            Ok(s) => self.visit_str(s),
+            Err(_) => Err(E::invalid_value(Unexpected::Bytes(v), &self)),
+        }
+    }
+}
+
+/// Bytes as a hex string, written in lowercase and read in either case.
+pub mod hex {
+    use alloc::vec::Vec;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<T, S>(bytes: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: AsRef<[u8]>,
+        S: Serializer,
+    {
+        serializer.serialize_str(&::hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: TryFrom<Vec<u8>>,
+        D: Deserializer<'de>,
+    {
+        super::deserialize_encoded(deserializer, "a hex string", |s| ::hex::decode(s).ok())
+    }
+}
+
+/// Bytes as RFC 4648 base32, as in magnet links: written in uppercase
+/// without padding, and read in either case, padded or not.
+pub mod base32 {
+    use alloc::vec::Vec;
+    use base32::Alphabet;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<T, S>(bytes: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: AsRef<[u8]>,
+        S: Serializer,
+    {
+        let encoded = ::base32::encode(Alphabet::Rfc4648 { padding: false }, bytes.as_ref());
+        serializer.serialize_str(&encoded)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: TryFrom<Vec<u8>>,
+        D: Deserializer<'de>,
+    {
+        super::deserialize_encoded(deserializer, "a base32 string", |s| {
+            ::base32::decode(Alphabet::Rfc4648 { padding: true }, &s.to_ascii_uppercase())
+        })
+    }
+}
+
+/// Bytes as standard, padded base64.
+pub mod base64 {
+    use alloc::vec::Vec;
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<T, S>(bytes: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: AsRef<[u8]>,
+        S: Serializer,
+    {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: TryFrom<Vec<u8>>,
+        D: Deserializer<'de>,
+    {
+        super::deserialize_encoded(deserializer, "a base64 string", |s| STANDARD.decode(s).ok())
+    }
+}
+
+/// A `String` read from any byte string, with invalid UTF-8 replaced by
+/// U+FFFD, for names from clients that don't always encode them properly.
+pub mod lossy_utf8 {
+    use alloc::string::String;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(s: &str, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+        deserializer.deserialize_bytes(super::LossyVisitor)
+    }
+}
+
+struct LossyVisitor;
+
+impl Visitor<'_> for LossyVisitor {
+    type Value = String;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a byte string")
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<String, E>
+    where
+        E: de::Error,
+    {
+        Ok(String::from_utf8_lossy(v).into_owned())
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<String, E>
+    where
+        E: de::Error,
+    {
+        Ok(String::from_utf8(v)
+            .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned()))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<String, E>
+    where
+        E: de::Error,
+    {
+        Ok(v.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_bytes, to_bytes};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Hashes {
+        #[serde(with = "super::hex")]
+        hex: [u8; 4],
+        #[serde(with = "super::base32")]
+        base32: Vec<u8>,
+        #[serde(with = "super::base64")]
+        base64: Box<[u8]>,
+    }
+
+    #[test]
+    fn test_encodings() {
+        let input = b"d6:base328:MFRGGZDF6:base648:AAEC/w==3:hex8:deadbeefe";
+        let hashes: Hashes = from_bytes(input).unwrap();
+        assert_eq!(
+            Hashes {
+                hex: [0xde, 0xad, 0xbe, 0xef],
+                base32: b"abcde".to_vec(),
+                base64: vec![0, 1, 2, 0xff].into(),
+            },
+            hashes
+        );
+        assert_eq!(input.to_vec(), to_bytes(&hashes).unwrap());
+
+        // either case, and base32 with padding
+        let input = b"d6:base3216:mfrggzdfmy======6:base640:3:hex8:DEADBEEFe";
+        let hashes: Hashes = from_bytes(input).unwrap();
+        assert_eq!(b"abcdef", &hashes.base32[..]);
+        assert_eq!([0xde, 0xad, 0xbe, 0xef], hashes.hex);
+
+        let err = from_bytes::<Hashes>(b"d6:base320:6:base640:3:hex6:deadbee").unwrap_err();
+        assert_eq!(
+            "invalid length 3, expected a byte string of the right length",
+            err.inner().to_string()
+        );
+        let err = from_bytes::<Hashes>(b"d6:base320:6:base640:3:hex8:xxxxxxxxe").unwrap_err();
+        assert_eq!(
+            "invalid value: string \"xxxxxxxx\", expected a hex string",
+            err.inner().to_string()
+        );
+    }
+
+    #[test]
+    fn test_lossy_utf8() {
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Named {
+            #[serde(with = "super::lossy_utf8")]
+            name: String,
+        }
+
+        let named: Named = from_bytes(b"d4:name4:a\xffbce").unwrap();
+        assert_eq!("a\u{fffd}bc", named.name);
+        assert_eq!(
+            b"d4:name6:a\xef\xbf\xbdbce".to_vec(),
+            to_bytes(&named).unwrap()
+        );
+        let named: Named = crate::from_reader(&b"d4:name3:abce"[..]).unwrap();
+        assert_eq!("abc", named.name);
+    }
+}
@@ -0,0 +1,150 @@
+use core::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+
+use crate::de::from_bytes;
+use crate::error::Result;
+use crate::frame::{Frame, Framer, DEFAULT_MAX_FRAME_LENGTH};
+use crate::value::Value;
+use alloc::vec::Vec;
+
+/// The result of [`IncrementalParser::parse`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Parsed<T> {
+    Complete(T),
+    /// At least this many more bytes must be fed before a value completes.
+    Incomplete(usize),
+}
+
+/// A parser that is fed input as it arrives, for non-blocking loops that
+/// receive bencode in fragments. Scanning resumes where it stopped, so
+/// fragments aren't rescanned.
+pub struct IncrementalParser<T = Value> {
+    buf: Vec<u8>,
+    framer: Framer,
+    max_frame_length: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> IncrementalParser<T> {
+    pub fn new() -> Self {
+        IncrementalParser {
+            buf: Vec::new(),
+            framer: Framer::new(),
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+            marker: PhantomData,
+        }
+    }
+
+    /// Limits how long one encoded value can be, 8 MiB by default. Past it,
+    /// parsing fails with `Error::FrameTooLong`.
+    pub fn set_max_frame_length(&mut self, len: usize) {
+        self.max_frame_length = len;
+    }
+
+    pub fn max_frame_length(&self) -> usize {
+        self.max_frame_length
+    }
+
+    /// Appends more input.
+    pub fn feed(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Decodes the next value once all of it has been fed. Its bytes are
+    /// then dropped, and anything fed after it is kept for the next call.
+    ///
+    /// When the input can't be framed, or is too long, there's no telling
+    /// where the next value starts, so all buffered input is dropped before
+    /// the error is returned. A value that frames but doesn't deserialize
+    /// into `T` only drops its own bytes.
+    pub fn parse(&mut self) -> Result<Parsed<T>> {
+        match self.framer.advance_capped(&self.buf, self.max_frame_length) {
+            Ok(Frame::Complete(len)) => {
+                let value = from_bytes(&self.buf[..len]);
+                self.buf.drain(..len);
+                value.map(Parsed::Complete)
+            }
+            Ok(Frame::Incomplete(n)) => Ok(Parsed::Incomplete(n)),
+            Err(e) => {
+                self.clear();
+                Err(e)
+            }
+        }
+    }
+
+    /// Input that has been fed but not yet parsed.
+    pub fn buffered(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Drops all buffered input and starts over.
+    pub fn clear(&mut self) {
+        self.buf.clear();
+        self.framer = Framer::new();
+    }
+}
+
+impl<T: DeserializeOwned> Default for IncrementalParser<T> {
+    fn default() -> Self {
+        IncrementalParser::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IncrementalParser, Parsed};
+    use crate::error::Error;
+    use crate::value::Value;
+
+    #[test]
+    fn test_incremental() {
+        let mut parser = IncrementalParser::<Value>::new();
+        assert_eq!(Ok(Parsed::Incomplete(1)), parser.parse());
+        parser.feed(b"d1:a");
+        assert_eq!(Ok(Parsed::Incomplete(1)), parser.parse());
+        parser.feed(b"10:01234");
+        assert_eq!(Ok(Parsed::Incomplete(5)), parser.parse());
+        parser.feed(b"56789");
+        assert_eq!(Ok(Parsed::Incomplete(1)), parser.parse());
+        parser.feed(b"ei4");
+        assert_eq!(
+            Ok(Parsed::Complete(bencode!({ "a": "0123456789" }))),
+            parser.parse()
+        );
+        assert_eq!(b"i4", parser.buffered());
+        parser.feed(b"2e");
+        assert_eq!(Ok(Parsed::Complete(bencode!(42))), parser.parse());
+        assert!(parser.buffered().is_empty());
+    }
+
+    #[test]
+    fn test_incremental_error() {
+        let mut parser = IncrementalParser::<i64>::default();
+        parser.feed(b"i1x");
+        assert_eq!(Err(Error::ExpectedE), parser.parse());
+        // the bad input is gone, so the next value parses
+        assert!(parser.buffered().is_empty());
+        parser.feed(b"i1e");
+        assert_eq!(Ok(Parsed::Complete(1)), parser.parse());
+        // a value of the wrong type only drops itself
+        parser.feed(b"1:ai2e");
+        assert!(parser.parse().is_err());
+        assert_eq!(Ok(Parsed::Complete(2)), parser.parse());
+    }
+
+    #[test]
+    fn test_incremental_max_frame_length() {
+        let mut parser = IncrementalParser::<Value>::new();
+        parser.set_max_frame_length(8);
+        parser.feed(b"l1:ae");
+        assert_eq!(Ok(Parsed::Complete(bencode!(["a"]))), parser.parse());
+        parser.feed(b"li1ei2e");
+        assert_eq!(Ok(Parsed::Incomplete(1)), parser.parse());
+        parser.feed(b"i3ee");
+        assert_eq!(Err(Error::FrameTooLong), parser.parse());
+        assert!(parser.buffered().is_empty());
+        parser.feed(b"9:a");
+        assert_eq!(Err(Error::FrameTooLong), parser.parse());
+    }
+}
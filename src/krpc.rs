@@ -0,0 +1,531 @@
+//! Messages of the DHT's KRPC protocol (BEP 5).
+//!
+//! Every message is a dictionary with a transaction id `t` and a type `y`:
+//! `q` for a query, whose method is named by `q` and arguments given in
+//! `a`; `r` for a response, in `r`; or `e` for an error, in `e`.
+
+use serde::de::Error as _;
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::net::{SocketAddr, SocketAddrV4};
+
+use crate::Value;
+
+pub type NodeId = [u8; 20];
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Message {
+    /// Chosen by the querying node and echoed back in the reply
+    #[serde(rename = "t", with = "serde_bytes")]
+    pub transaction_id: Vec<u8>,
+    /// The client version, if the sender includes it
+    #[serde(
+        rename = "v",
+        default,
+        with = "serde_bytes",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub version: Option<Vec<u8>>,
+    #[serde(flatten)]
+    pub kind: Kind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "RawKind", into = "RawKind")]
+pub enum Kind {
+    Query(Query),
+    Response(Response),
+    Error(Error),
+}
+
+// `y` names the kind, and the query's method and arguments or the response
+// or error sit beside it
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "y")]
+enum RawKind {
+    #[serde(rename = "q")]
+    Query(Query),
+    #[serde(rename = "r")]
+    Response { r: Response },
+    #[serde(rename = "e")]
+    Error { e: Error },
+}
+
+impl From<RawKind> for Kind {
+    fn from(raw: RawKind) -> Kind {
+        match raw {
+            RawKind::Query(query) => Kind::Query(query),
+            RawKind::Response { r } => Kind::Response(r),
+            RawKind::Error { e } => Kind::Error(e),
+        }
+    }
+}
+
+impl From<Kind> for RawKind {
+    fn from(kind: Kind) -> RawKind {
+        match kind {
+            Kind::Query(query) => RawKind::Query(query),
+            Kind::Response(r) => RawKind::Response { r },
+            Kind::Error(e) => RawKind::Error { e },
+        }
+    }
+}
+
+/// A query: the method is written in `q`, and its arguments in `a`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(remote = "Self", tag = "q", content = "a", rename_all = "snake_case")]
+pub enum Query {
+    Ping {
+        #[serde(with = "serde_bytes")]
+        id: NodeId,
+    },
+    FindNode {
+        #[serde(with = "serde_bytes")]
+        id: NodeId,
+        #[serde(with = "serde_bytes")]
+        target: NodeId,
+    },
+    GetPeers {
+        #[serde(with = "serde_bytes")]
+        id: NodeId,
+        #[serde(with = "serde_bytes")]
+        info_hash: [u8; 20],
+    },
+    AnnouncePeer {
+        #[serde(with = "serde_bytes")]
+        id: NodeId,
+        #[serde(with = "serde_bytes")]
+        info_hash: [u8; 20],
+        port: u16,
+        /// The token from an earlier `get_peers` response
+        #[serde(with = "serde_bytes")]
+        token: Vec<u8>,
+        /// Use the port the query came from instead of `port`
+        #[serde(default, skip_serializing_if = "is_false", with = "int_bool")]
+        implied_port: bool,
+    },
+    /// A method this module doesn't know, so a node can still reply, with
+    /// a `METHOD_UNKNOWN` error if nothing else.
+    #[serde(skip)]
+    Other { method: String, args: Value },
+}
+
+fn is_false(b: &bool) -> bool {
+    !b
+}
+
+const METHODS: [&str; 4] = ["ping", "find_node", "get_peers", "announce_peer"];
+
+impl Serialize for Query {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Query::Other { method, args } => {
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry("a", args)?;
+                map.serialize_entry("q", method)?;
+                map.end()
+            }
+            _ => Query::serialize(self, serializer),
+        }
+    }
+}
+
+// Known methods go through the derived impl, so bad arguments are still
+// reported as such; only an unknown method becomes `Other`.
+impl<'de> Deserialize<'de> for Query {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Query, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        let method = match value.get("q") {
+            Some(Value::Bytes(q)) => String::from_utf8_lossy(q).into_owned(),
+            Some(_) => return Err(D::Error::custom("`q` must be a byte string")),
+            None => return Err(D::Error::missing_field("q")),
+        };
+        if METHODS.contains(&method.as_str()) {
+            return Query::deserialize(value).map_err(D::Error::custom);
+        }
+        let args = value
+            .get("a")
+            .cloned()
+            .ok_or_else(|| D::Error::missing_field("a"))?;
+        Ok(Query::Other { method, args })
+    }
+}
+
+impl Query {
+    /// The id of the querying node; `None` for an `Other` query without a
+    /// 20-byte `id` argument.
+    pub fn id(&self) -> Option<&NodeId> {
+        match self {
+            Query::Ping { id }
+            | Query::FindNode { id, .. }
+            | Query::GetPeers { id, .. }
+            | Query::AnnouncePeer { id, .. } => Some(id),
+            Query::Other { args, .. } => args
+                .get("id")
+                .and_then(Value::as_bytes)
+                .and_then(|id| id.try_into().ok()),
+        }
+    }
+
+    /// The method name, as written in `q`.
+    pub fn method(&self) -> &str {
+        match self {
+            Query::Ping { .. } => "ping",
+            Query::FindNode { .. } => "find_node",
+            Query::GetPeers { .. } => "get_peers",
+            Query::AnnouncePeer { .. } => "announce_peer",
+            Query::Other { method, .. } => method,
+        }
+    }
+}
+
+/// A response to any query. Responses don't say which query they answer,
+/// so this has the fields of all of them; which are set depends on the
+/// query.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Response {
+    #[serde(with = "serde_bytes")]
+    pub id: NodeId,
+    /// IPv4 nodes, from `find_node` and `get_peers`
+    #[serde(default, with = "nodes", skip_serializing_if = "Vec::is_empty")]
+    pub nodes: Vec<NodeInfo>,
+    /// IPv6 nodes (BEP 32)
+    #[serde(default, with = "nodes6", skip_serializing_if = "Vec::is_empty")]
+    pub nodes6: Vec<NodeInfo>,
+    /// From `get_peers`, to be passed to `announce_peer`
+    #[serde(default, with = "serde_bytes", skip_serializing_if = "Option::is_none")]
+    pub token: Option<Vec<u8>>,
+    /// Peers for the torrent, from `get_peers`
+    #[serde(default, with = "values", skip_serializing_if = "Option::is_none")]
+    pub values: Option<Vec<SocketAddrV4>>,
+}
+
+/// A DHT node and where to reach it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeInfo {
+    pub id: NodeId,
+    pub addr: SocketAddr,
+}
+
+/// Reads `nodes` into a `Vec<NodeInfo>` and writes it back: 26 bytes per
+/// node, the id then the compact IPv4 address. Writing an IPv6 node is an
+/// error.
+pub mod nodes {
+    use serde::ser::Error as _;
+    use serde::{Deserializer, Serializer};
+    use std::net::{SocketAddr, SocketAddrV4};
+
+    use super::{NodeId, NodeInfo};
+    use crate::compact::{deserialize_list, encode_list};
+
+    pub fn serialize<S>(nodes: &[NodeInfo], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let nodes = nodes
+            .iter()
+            .map(|node| match node.addr {
+                SocketAddr::V4(addr) => Ok((node.id, addr)),
+                SocketAddr::V6(_) => Err(S::Error::custom("`nodes` only holds IPv4 nodes")),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        serializer.serialize_bytes(&encode_list(&nodes))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<NodeInfo>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let nodes = deserialize_list::<(NodeId, SocketAddrV4), D>(deserializer)?;
+        Ok(nodes
+            .into_iter()
+            .map(|(id, addr)| NodeInfo {
+                id,
+                addr: addr.into(),
+            })
+            .collect())
+    }
+}
+
+/// Like `nodes`, for `nodes6`: 38 bytes per node, with IPv6 addresses.
+pub mod nodes6 {
+    use serde::ser::Error as _;
+    use serde::{Deserializer, Serializer};
+    use std::net::{SocketAddr, SocketAddrV6};
+
+    use super::{NodeId, NodeInfo};
+    use crate::compact::{deserialize_list, encode_list};
+
+    pub fn serialize<S>(nodes: &[NodeInfo], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let nodes = nodes
+            .iter()
+            .map(|node| match node.addr {
+                SocketAddr::V6(addr) => Ok((node.id, addr)),
+                SocketAddr::V4(_) => Err(S::Error::custom("`nodes6` only holds IPv6 nodes")),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        serializer.serialize_bytes(&encode_list(&nodes))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<NodeInfo>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let nodes = deserialize_list::<(NodeId, SocketAddrV6), D>(deserializer)?;
+        Ok(nodes
+            .into_iter()
+            .map(|(id, addr)| NodeInfo {
+                id,
+                addr: addr.into(),
+            })
+            .collect())
+    }
+}
+
+/// An error reply, written as a list of the code and the message.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "(i64, String)", into = "(i64, String)")]
+pub struct Error {
+    pub code: i64,
+    pub message: String,
+}
+
+impl Error {
+    pub const GENERIC: i64 = 201;
+    pub const SERVER: i64 = 202;
+    pub const PROTOCOL: i64 = 203;
+    pub const METHOD_UNKNOWN: i64 = 204;
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<(i64, String)> for Error {
+    fn from((code, message): (i64, String)) -> Error {
+        Error { code, message }
+    }
+}
+
+impl From<Error> for (i64, String) {
+    fn from(error: Error) -> (i64, String) {
+        (error.code, error.message)
+    }
+}
+
+// `values` is a list of compact peers, each its own byte string
+mod values {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::net::SocketAddrV4;
+
+    #[derive(Serialize, Deserialize)]
+    struct Peer(#[serde(with = "crate::compact::v4")] SocketAddrV4);
+
+    pub fn serialize<S>(
+        values: &Option<Vec<SocketAddrV4>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        values
+            .as_ref()
+            .map(|values| values.iter().map(|&v| Peer(v)).collect::<Vec<_>>())
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Vec<SocketAddrV4>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let values = Option::<Vec<Peer>>::deserialize(deserializer)?;
+        Ok(values.map(|values| values.into_iter().map(|p| p.0).collect()))
+    }
+}
+
+// `implied_port` is written as `i1e`; the arguments are buffered before
+// they reach the bencode deserializer, so its own int-as-bool doesn't apply
+mod int_bool {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(b: &bool, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(*b as i64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+        Ok(i64::deserialize(deserializer)? != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, Kind, Message, NodeInfo, Query, Response};
+    use crate::{from_bytes, to_bytes};
+
+    fn round_trip(input: &[u8]) -> Message {
+        let message: Message = from_bytes(input).unwrap();
+        assert_eq!(input.to_vec(), to_bytes(&message).unwrap());
+        message
+    }
+
+    // the examples from BEP 5
+    #[test]
+    fn test_queries() {
+        let message = round_trip(b"d1:ad2:id20:abcdefghij0123456789e1:q4:ping1:t2:aa1:y1:qe");
+        assert_eq!(b"aa", &message.transaction_id[..]);
+        assert_eq!(
+            Kind::Query(Query::Ping {
+                id: *b"abcdefghij0123456789"
+            }),
+            message.kind
+        );
+
+        let message = round_trip(
+            b"d1:ad2:id20:abcdefghij01234567896:target20:mnopqrstuvwxyz123456e\
+            1:q9:find_node1:t2:aa1:y1:qe",
+        );
+        assert!(matches!(message.kind, Kind::Query(Query::FindNode { .. })));
+
+        let message = round_trip(
+            b"d1:ad2:id20:abcdefghij01234567899:info_hash20:mnopqrstuvwxyz123456e\
+            1:q9:get_peers1:t2:aa1:y1:qe",
+        );
+        assert!(matches!(message.kind, Kind::Query(Query::GetPeers { .. })));
+
+        let message = round_trip(
+            b"d1:ad2:id20:abcdefghij012345678912:implied_porti1e\
+            9:info_hash20:mnopqrstuvwxyz1234564:porti6881e5:token8:aoeusnthe\
+            1:q13:announce_peer1:t2:aa1:y1:qe",
+        );
+        match message.kind {
+            Kind::Query(Query::AnnouncePeer {
+                port,
+                token,
+                implied_port,
+                ..
+            }) => {
+                assert_eq!(6881, port);
+                assert_eq!(b"aoeusnth", &token[..]);
+                assert!(implied_port);
+            }
+            kind => panic!("{:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_response() {
+        let message = round_trip(
+            b"d1:rd2:id20:abcdefghij01234567895:token8:aoeusnth6:valuesl6:axje.u6:idhtnmee\
+            1:t2:aa1:y1:re",
+        );
+        let Kind::Response(Response { token, values, .. }) = message.kind else {
+            panic!("{:?}", message.kind);
+        };
+        assert_eq!(Some(b"aoeusnth".to_vec()), token);
+        assert_eq!(
+            vec!["97.120.106.101:11893", "105.100.104.116:28269"],
+            values
+                .unwrap()
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_nodes() {
+        let mut input = b"d1:rd2:id20:abcdefghij01234567895:nodes52:".to_vec();
+        input.extend(b"mnopqrstuvwxyz123456\x0a\x00\x00\x01\x1a\xe1");
+        input.extend(b"zyxwvutsrqponm654321\x0a\x00\x00\x02\x1a\xe2");
+        input.extend(b"6:nodes638:0123456789abcdefghij");
+        input.extend([0; 15]);
+        input.extend(b"\x01\x00\x50e1:t2:aa1:y1:re");
+        let message = round_trip(&input);
+        let Kind::Response(response) = message.kind else {
+            panic!("{:?}", message.kind);
+        };
+        assert_eq!(
+            vec![
+                NodeInfo {
+                    id: *b"mnopqrstuvwxyz123456",
+                    addr: "10.0.0.1:6881".parse().unwrap(),
+                },
+                NodeInfo {
+                    id: *b"zyxwvutsrqponm654321",
+                    addr: "10.0.0.2:6882".parse().unwrap(),
+                },
+            ],
+            response.nodes
+        );
+        assert_eq!("[::1]:80", response.nodes6[0].addr.to_string());
+
+        let mut wrong = response.clone();
+        wrong.nodes.push(response.nodes6[0]);
+        assert!(to_bytes(&wrong).is_err());
+
+        let err = from_bytes::<Response>(b"d2:id20:abcdefghij01234567895:nodes3:abce").unwrap_err();
+        assert_eq!(
+            "invalid length 3, expected a multiple of 26 bytes",
+            err.inner().to_string()
+        );
+    }
+
+    #[test]
+    fn test_error() {
+        let message = round_trip(b"d1:eli201e23:A Generic Error Ocurrede1:t2:aa1:y1:ee");
+        assert_eq!(
+            Kind::Error(Error {
+                code: Error::GENERIC,
+                message: "A Generic Error Ocurred".to_string(),
+            }),
+            message.kind
+        );
+    }
+
+    #[test]
+    fn test_unknown_method() {
+        let message = round_trip(b"d1:ad2:id20:abcdefghij01234567891:xi1ee1:q3:foo1:t2:aa1:y1:qe");
+        let Kind::Query(query) = &message.kind else {
+            panic!("{:?}", message.kind);
+        };
+        assert_eq!("foo", query.method());
+        assert_eq!(Some(b"abcdefghij0123456789"), query.id());
+        assert_eq!(
+            Kind::Query(Query::Other {
+                method: "foo".to_string(),
+                args: bencode!({ "id": "abcdefghij0123456789", "x": 1 }),
+            }),
+            message.kind
+        );
+        // enough to reply to
+        let reply = Message {
+            transaction_id: message.transaction_id,
+            version: None,
+            kind: Kind::Error(Error {
+                code: Error::METHOD_UNKNOWN,
+                message: "Method Unknown".to_string(),
+            }),
+        };
+        assert_eq!(
+            b"d1:eli204e14:Method Unknowne1:t2:aa1:y1:ee".to_vec(),
+            to_bytes(&reply).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        let err =
+            from_bytes::<Message>(b"d1:ad2:id20:abcdefghij0123456789e1:q9:find_node1:t0:1:y1:qe")
+                .unwrap_err();
+        assert_eq!("missing field `target`", err.inner().to_string());
+        assert!(from_bytes::<Message>(b"d1:t0:1:y1:xe").is_err());
+    }
+}
@@ -0,0 +1,516 @@
+use core::fmt::{self, Display, Write};
+use serde::ser::{self, Serialize};
+
+use crate::error::{Error, Result};
+use crate::ser::{map_key, BoolPolicy, Canonical, FloatPolicy, NonePolicy, SerializerOptions};
+
+/// Returns the exact number of bytes `to_bytes` would produce for `value`,
+/// without producing them.
+///
+/// Dictionary keys aren't kept, so a dictionary with the same key twice is
+/// measured as written rather than failing with `Error::DuplicateKey` the
+/// way `to_bytes` does. Nothing is allocated, except to encode and convert
+/// keys under `KeyPolicy::Custom`.
+pub fn serialized_len<T>(value: &T) -> Result<usize>
+where
+    T: ?Sized + Serialize,
+{
+    serialized_len_with_options(value, SerializerOptions::default())
+}
+
+pub fn serialized_len_with_options<T>(value: &T, options: SerializerOptions) -> Result<usize>
+where
+    T: ?Sized + Serialize,
+{
+    options.check()?;
+    encoded_len(value, &options)
+}
+
+pub(crate) fn encoded_len<T>(value: &T, options: &SerializerOptions) -> Result<usize>
+where
+    T: ?Sized + Serialize,
+{
+    value.serialize(&LenSerializer { options })
+}
+
+// Each method returns the encoded length of the value. Zero means the value
+// was skipped, since every real bencode value is at least two bytes.
+struct LenSerializer<'a> {
+    options: &'a SerializerOptions,
+}
+
+struct Counter(usize);
+
+impl Write for Counter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+fn display_len<T: Display>(v: T) -> usize {
+    let mut counter = Counter(0);
+    // Counter never fails
+    let _ = write!(counter, "{}", v);
+    counter.0
+}
+
+pub(crate) fn int_len<T: Display>(v: T) -> usize {
+    display_len(v) + 2
+}
+
+pub(crate) fn byte_array_len(len: usize) -> usize {
+    display_len(len) + 1 + len
+}
+
+impl LenSerializer<'_> {
+    fn none_len(&self) -> Result<usize> {
+        match self.options.none_policy {
+            NonePolicy::Skip => Ok(0),
+            NonePolicy::Error => Err(Error::Unsupported("none")),
+        }
+    }
+}
+
+impl<'a> ser::Serializer for &LenSerializer<'a> {
+    type Ok = usize;
+    type Error = Error;
+
+    type SerializeSeq = SeqLen<'a>;
+    type SerializeTuple = SeqLen<'a>;
+    type SerializeTupleStruct = SeqLen<'a>;
+    type SerializeTupleVariant = SeqLen<'a>;
+    type SerializeMap = MapLen<'a>;
+    type SerializeStruct = MapLen<'a>;
+    type SerializeStructVariant = MapLen<'a>;
+
+    fn serialize_bool(self, _v: bool) -> Result<usize> {
+        match self.options.bool_policy {
+            BoolPolicy::Int => Ok(3),
+            BoolPolicy::Error => Err(Error::Unsupported("bool")),
+        }
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<usize> {
+        Ok(int_len(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<usize> {
+        Ok(int_len(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<usize> {
+        Ok(int_len(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<usize> {
+        Ok(int_len(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<usize> {
+        Ok(int_len(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<usize> {
+        Ok(int_len(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<usize> {
+        Ok(int_len(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<usize> {
+        Ok(int_len(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<usize> {
+        Ok(int_len(v))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<usize> {
+        Ok(int_len(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<usize> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<usize> {
+        if self.options.canonical == Canonical::Strict {
+            return Err(Error::NonCanonical("float"));
+        }
+        match self.options.float_policy {
+            FloatPolicy::Error => Err(Error::Unsupported("float")),
+            FloatPolicy::String => Ok(byte_array_len(display_len(v))),
+        }
+    }
+
+    fn serialize_char(self, v: char) -> Result<usize> {
+        Ok(byte_array_len(v.len_utf8()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<usize> {
+        Ok(byte_array_len(v.len()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<usize> {
+        Ok(byte_array_len(v.len()))
+    }
+
+    fn serialize_none(self) -> Result<usize> {
+        self.none_len()
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<usize>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<usize> {
+        self.none_len()
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<usize> {
+        self.none_len()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<usize> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<usize>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<usize>
+    where
+        T: ?Sized + Serialize,
+    {
+        match value.serialize(self)? {
+            0 => Ok(0),
+            len => Ok(2 + byte_array_len(variant.len()) + len),
+        }
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Ok(SeqLen {
+            ser: LenSerializer {
+                options: self.options,
+            },
+            len: 2,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        let mut seq = self.serialize_seq(Some(len))?;
+        seq.len += 2 + byte_array_len(variant.len());
+        Ok(seq)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Ok(MapLen {
+            ser: LenSerializer {
+                options: self.options,
+            },
+            len: 2,
+            key: 0,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        let mut map = self.serialize_map(Some(len))?;
+        map.len += 2 + byte_array_len(variant.len());
+        Ok(map)
+    }
+}
+
+pub(crate) struct SeqLen<'a> {
+    ser: LenSerializer<'a>,
+    len: usize,
+}
+
+impl SeqLen<'_> {
+    fn add<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        match value.serialize(&self.ser)? {
+            0 => Err(Error::Unsupported("none in a list")),
+            len => {
+                self.len += len;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl ser::SerializeSeq for SeqLen<'_> {
+    type Ok = usize;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.add(value)
+    }
+
+    fn end(self) -> Result<usize> {
+        Ok(self.len)
+    }
+}
+
+impl ser::SerializeTuple for SeqLen<'_> {
+    type Ok = usize;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.add(value)
+    }
+
+    fn end(self) -> Result<usize> {
+        Ok(self.len)
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqLen<'_> {
+    type Ok = usize;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.add(value)
+    }
+
+    fn end(self) -> Result<usize> {
+        Ok(self.len)
+    }
+}
+
+impl ser::SerializeTupleVariant for SeqLen<'_> {
+    type Ok = usize;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.add(value)
+    }
+
+    fn end(self) -> Result<usize> {
+        Ok(self.len)
+    }
+}
+
+pub(crate) struct MapLen<'a> {
+    ser: LenSerializer<'a>,
+    len: usize,
+    // encoded length of the key waiting for its value
+    key: usize,
+}
+
+impl MapLen<'_> {
+    fn add<T>(&mut self, key: usize, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let value = value.serialize(&self.ser)?;
+        if value != 0 {
+            self.len += key + value;
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for MapLen<'_> {
+    type Ok = usize;
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.key = map_key(key, self.ser.options, |k| Ok(byte_array_len(k.len())))?;
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.add(self.key, value)
+    }
+
+    fn end(self) -> Result<usize> {
+        Ok(self.len)
+    }
+}
+
+impl ser::SerializeStruct for MapLen<'_> {
+    type Ok = usize;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.add(byte_array_len(key.len()), value)
+    }
+
+    fn end(self) -> Result<usize> {
+        Ok(self.len)
+    }
+}
+
+impl ser::SerializeStructVariant for MapLen<'_> {
+    type Ok = usize;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.add(byte_array_len(key.len()), value)
+    }
+
+    fn end(self) -> Result<usize> {
+        Ok(self.len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{serialized_len, serialized_len_with_options};
+    use crate::ser::{to_bytes, to_bytes_with_options, FloatPolicy, KeyPolicy, SerializerOptions};
+    use serde::Serialize;
+
+    fn assert_len<T: ?Sized + Serialize>(value: &T) {
+        assert_eq!(
+            to_bytes(value).unwrap().len(),
+            serialized_len(value).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_scalars() {
+        assert_len(&0i64);
+        assert_len(&-123i32);
+        assert_len(&u64::MAX);
+        assert_len(&true);
+        assert_len("");
+        assert_len("hello");
+        assert_len(&'é');
+    }
+
+    #[test]
+    fn test_nested() {
+        #[derive(Serialize)]
+        enum Enum {
+            Unit,
+            Newtype(i32),
+            Tuple(i32, i32),
+            Struct { a: i32 },
+            Skipped(Option<i32>),
+        }
+
+        #[derive(Serialize)]
+        struct Test {
+            b: Vec<Enum>,
+            a: Option<i64>,
+            skipped: Enum,
+            map: HashMap<String, (i64, String)>,
+        }
+
+        let v = Test {
+            b: vec![
+                Enum::Unit,
+                Enum::Newtype(-1),
+                Enum::Tuple(1, 10),
+                Enum::Struct { a: 1 },
+                Enum::Skipped(Some(2)),
+            ],
+            a: None,
+            skipped: Enum::Skipped(None),
+            map: HashMap::from([("key".to_string(), (1, "value".to_string()))]),
+        };
+        assert_len(&v);
+
+        // nothing to drop a skipped element with
+        let list = vec![Enum::Unit, Enum::Skipped(None)];
+        assert!(to_bytes(&list).is_err());
+        assert!(serialized_len(&list).is_err());
+    }
+
+    #[test]
+    fn test_key_policy() {
+        let options = SerializerOptions::new().key_policy(KeyPolicy::Decimal);
+        let v = HashMap::from([(1u32, "a"), (200u32, "b")]);
+        assert_eq!(
+            to_bytes_with_options(&v, options.clone()).unwrap().len(),
+            serialized_len_with_options(&v, options).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_options() {
+        let options = SerializerOptions::new().float_policy(FloatPolicy::String);
+        let v = [1.5f64, 0.1];
+        assert_eq!(
+            to_bytes_with_options(&v, options.clone()).unwrap().len(),
+            serialized_len_with_options(&v, options).unwrap()
+        );
+        assert!(serialized_len(&1.5f64).is_err());
+    }
+}
@@ -1,23 +1,121 @@
+// tests use std for the harness, but std-only APIs stay behind the feature
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[macro_use]
+mod macros;
+
+#[cfg(feature = "tokio")]
+mod async_io;
+mod capture;
+#[cfg(feature = "codec")]
+mod codec;
+pub mod compact;
 mod de;
+mod dump;
+mod encoder;
 mod error;
+mod events;
+mod frame;
+#[cfg(feature = "hash")]
+mod hash;
+#[cfg(feature = "helpers")]
+pub mod helpers;
+mod incremental;
+pub mod io;
+#[cfg(feature = "krpc")]
+pub mod krpc;
+mod len;
+mod normalize;
+mod raw;
+mod read;
+mod schema;
+mod ser;
+mod stats;
+#[cfg(any(feature = "chrono", feature = "time"))]
+pub mod timestamp;
+mod tokens;
+#[cfg(feature = "torrent")]
+pub mod torrent;
+mod validate;
+mod value;
+
+pub use crate::capture::Captured;
+pub use crate::de::{from_bytes, from_bytes_with_warnings, Deserializer, Validated};
+pub use crate::dump::{annotated_hexdump, to_debug_string};
+pub use crate::encoder::Encoder;
+pub use crate::error::{Error, Result, Snippet};
+pub use crate::events::{parse_events, Handler};
+pub use crate::frame::measure;
+pub use crate::incremental::{IncrementalParser, Parsed};
+pub use crate::len::{serialized_len, serialized_len_with_options};
+pub use crate::normalize::{normalize, normalize_with, DuplicatePolicy};
+pub use crate::raw::{get_raw, select, select_values};
+pub use crate::read::{Read, SliceRead};
+pub use crate::schema::{
+    validate_against, DictSchema, Schema, SchemaViolation, SchemaViolationKind,
+};
+pub use crate::ser::{
+    to_bytes, to_bytes_in, to_bytes_with_options, to_writer, BoolPolicy, Canonical, FloatPolicy,
+    KeyConverter, KeyPolicy, NonePolicy, Serializer, SerializerOptions,
+};
+pub use crate::stats::{stats, Stats};
+pub use crate::tokens::{Token, Tokens};
+pub use crate::validate::{is_canonical, validate, Violation, ViolationKind, Warning};
+pub use crate::value::{
+    diff, from_value, to_value, ByteString, Difference, Entry, Index, Map, MergeStrategy, Value,
+    ValueRef,
+};
+
+#[cfg(feature = "std")]
+pub use crate::de::{from_path, from_reader, from_reader_with, ReadContext};
+#[cfg(feature = "std")]
+pub use crate::read::IoRead;
+
+#[cfg(feature = "bytes")]
+pub use crate::de::from_buf;
+#[cfg(feature = "bytes")]
+pub use crate::read::BytesRead;
+#[cfg(feature = "bytes")]
+pub use crate::ser::to_bytes_mut;
+
+#[cfg(feature = "mmap")]
+pub use crate::de::{from_mmap, MappedFile};
+
+#[cfg(feature = "hash")]
+pub use crate::hash::{info_hash_v1, info_hash_v2, info_hash_v2_truncated};
+
+#[cfg(feature = "json")]
+pub use crate::value::JsonBytes;
+
+#[cfg(feature = "arena")]
+pub use crate::value::BumpValue;
+
+#[cfg(feature = "small_bytes")]
+pub use crate::value::SmallBytes;
 
-// pub use crate::de::{from_str, Deserializer};
-// pub use crate::error::{Error, Result};
+#[cfg(feature = "tokio")]
+pub use crate::async_io::{
+    from_async_reader, from_async_reader_with, to_async_writer, to_async_writer_with_options,
+    AsyncStreamDeserializer,
+};
 
-// Here’s a small programming problem:
-// write a function that takes a string of words separated by spaces and
-// returns the first word it finds in that string.
-// If the function doesn’t find a space in the string, the whole
-// string must be one word, so the entire string should be returned.
+#[cfg(feature = "codec")]
+pub use crate::codec::BencodeCodec;
 
-// Let’s work through how we’d write the signature of this function
-// without using slices, to understand the problem that slices will solve:
+#[cfg(feature = "derive")]
+pub use serde_bencode_derive::BencodeDeserialize;
 
-// fn first_word(s: &String) -> ?
+// lets the derive's `::serde_bencode` paths resolve in this crate's tests
+#[cfg(all(test, feature = "derive"))]
+extern crate self as serde_bencode;
 
-fn first_word(s: &String) -> &str {
-    match s.find(' ') {
-        Some(i) => &s[..i],
-        _ => &s[..],
-    }
+// Not public API; used by `bencode!`, which can't name `std` or `alloc`
+// from a crate that may not link them, and by the derive, which can't
+// assume `serde` is a dependency.
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::vec;
+    pub use serde;
 }
@@ -0,0 +1,134 @@
+/// Builds a [`Value`](crate::Value) from a JSON-like literal.
+///
+/// ```
+/// # use serde_bencode::bencode;
+/// let id = b"abcdefghij0123456789".to_vec();
+/// let query = bencode!({ "t": "aa", "y": "q", "q": "ping", "a": { "id": id } });
+/// assert_eq!(Some("ping"), query["q"].as_str());
+/// ```
+///
+/// Keys are anything that is `AsRef<[u8]>`; a key that is more than a single
+/// token must be wrapped in parentheses. Other values go through
+/// `Value::from`.
+#[macro_export]
+macro_rules! bencode {
+    ($($bencode:tt)+) => {
+        $crate::bencode_internal!($($bencode)+)
+    };
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! bencode_internal {
+    // list elements, accumulated as expressions
+
+    (@list [$($elems:expr),*]) => {
+        $crate::__private::vec![$($elems),*]
+    };
+
+    (@list [$($elems:expr),*] [$($list:tt)*] $(, $($rest:tt)*)?) => {
+        $crate::bencode_internal!(
+            @list [$($elems,)* $crate::bencode_internal!([$($list)*])] $($($rest)*)?
+        )
+    };
+
+    (@list [$($elems:expr),*] {$($dict:tt)*} $(, $($rest:tt)*)?) => {
+        $crate::bencode_internal!(
+            @list [$($elems,)* $crate::bencode_internal!({$($dict)*})] $($($rest)*)?
+        )
+    };
+
+    (@list [$($elems:expr),*] $next:expr $(, $($rest:tt)*)?) => {
+        $crate::bencode_internal!(
+            @list [$($elems,)* $crate::bencode_internal!($next)] $($($rest)*)?
+        )
+    };
+
+    // dictionary entries, inserted one at a time
+
+    (@dict $dict:ident) => {};
+
+    (@dict $dict:ident $key:tt : [$($list:tt)*] $(, $($rest:tt)*)?) => {
+        $dict.insert(
+            $crate::bencode_internal!(@key $key),
+            $crate::bencode_internal!([$($list)*]),
+        );
+        $crate::bencode_internal!(@dict $dict $($($rest)*)?);
+    };
+
+    (@dict $dict:ident $key:tt : {$($inner:tt)*} $(, $($rest:tt)*)?) => {
+        $dict.insert(
+            $crate::bencode_internal!(@key $key),
+            $crate::bencode_internal!({$($inner)*}),
+        );
+        $crate::bencode_internal!(@dict $dict $($($rest)*)?);
+    };
+
+    (@dict $dict:ident $key:tt : $value:expr $(, $($rest:tt)*)?) => {
+        $dict.insert(
+            $crate::bencode_internal!(@key $key),
+            $crate::bencode_internal!($value),
+        );
+        $crate::bencode_internal!(@dict $dict $($($rest)*)?);
+    };
+
+    (@key $key:expr) => {
+        $crate::ByteString::from(::core::convert::AsRef::<[u8]>::as_ref(&$key))
+    };
+
+    // values
+
+    ([]) => {
+        $crate::Value::List($crate::__private::vec![])
+    };
+
+    ([ $($tt:tt)+ ]) => {
+        $crate::Value::List($crate::bencode_internal!(@list [] $($tt)+))
+    };
+
+    ({}) => {
+        $crate::Value::Dict($crate::Map::new())
+    };
+
+    ({ $($tt:tt)+ }) => {{
+        let mut dict = $crate::Map::new();
+        $crate::bencode_internal!(@dict dict $($tt)+);
+        $crate::Value::Dict(dict)
+    }};
+
+    ($other:expr) => {
+        $crate::Value::from($other)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::de::from_bytes;
+    use crate::value::Value;
+
+    #[test]
+    fn test_bencode_macro() {
+        let id = b"abcdefghij0123456789".to_vec();
+        let query = bencode!({ "t": "aa", "y": "q", "q": "ping", "a": { "id": id } });
+        let expected: Value =
+            from_bytes(b"d1:ad2:id20:abcdefghij0123456789e1:q4:ping1:t2:aa1:y1:qe").unwrap();
+        assert_eq!(expected, query);
+    }
+
+    #[test]
+    fn test_bencode_macro_nesting() {
+        let key = "k";
+        let n = 2;
+        let v = bencode!({
+            "list": [1, -n, "two", [], {}, [3, [4]], { "a": 5 },],
+            (key.to_owned() + "ey"): n * 3,
+            b"raw": &b"\x00\xff"[..],
+        });
+        let expected: Value =
+            from_bytes(b"d3:keyi6e4:listli1ei-2e3:twoledeli3eli4eeed1:ai5eee3:raw2:\x00\xffe")
+                .unwrap();
+        assert_eq!(expected, v);
+        assert_eq!(Value::Int(7), bencode!(7));
+        assert_eq!(Value::List(vec![]), bencode!([]));
+    }
+}
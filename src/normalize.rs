@@ -0,0 +1,144 @@
+use crate::error::{Error, Result};
+use crate::events::{parse_events, Handler};
+use crate::ser::to_bytes;
+use crate::value::{byte_string, Map, Value};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Which value to keep when a dictionary has the same key more than once.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    First,
+    #[default]
+    Last,
+    Error,
+}
+
+/// Re-encodes `input` canonically: keys sorted, integers and lengths
+/// without leading zeros, and the last of any duplicate keys kept.
+pub fn normalize(input: &[u8]) -> Result<Vec<u8>> {
+    normalize_with(input, DuplicatePolicy::default())
+}
+
+/// Like `normalize`, with `duplicates` deciding which of any duplicate keys
+/// to keep, or failing with `Error::DuplicateKey`.
+pub fn normalize_with(input: &[u8], duplicates: DuplicatePolicy) -> Result<Vec<u8>> {
+    to_bytes(&parse_with(input, duplicates)?)
+}
+
+pub(crate) fn parse_with(input: &[u8], duplicates: DuplicatePolicy) -> Result<Value> {
+    let mut builder = Builder {
+        duplicates,
+        stack: Vec::new(),
+        root: None,
+    };
+    parse_events(input, &mut builder)?;
+    // parse_events only succeeds once a whole value has been seen
+    Ok(builder.root.unwrap())
+}
+
+struct Builder {
+    duplicates: DuplicatePolicy,
+    // open containers, each with the key its next value goes under
+    stack: Vec<(Value, Vec<u8>)>,
+    root: Option<Value>,
+}
+
+impl Builder {
+    fn add(&mut self, value: Value) -> Result<()> {
+        match self.stack.last_mut() {
+            None => self.root = Some(value),
+            Some((Value::List(list), _)) => list.push(value),
+            Some((Value::Dict(dict), key)) => {
+                let key = core::mem::take(key);
+                match (dict.contains_key(&key[..]), self.duplicates) {
+                    (true, DuplicatePolicy::First) => {}
+                    (true, DuplicatePolicy::Error) => {
+                        return Err(Error::DuplicateKey(
+                            String::from_utf8_lossy(&key).into_owned(),
+                        ))
+                    }
+                    _ => {
+                        dict.insert(byte_string(key), value);
+                    }
+                }
+            }
+            Some(_) => unreachable!(),
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        // the parser balances starts and ends
+        let (value, _) = self.stack.pop().unwrap();
+        self.add(value)
+    }
+}
+
+impl Handler for Builder {
+    fn on_int(&mut self, value: i64) -> Result<()> {
+        self.add(Value::Int(value))
+    }
+
+    fn on_bytes(&mut self, value: &[u8]) -> Result<()> {
+        self.add(Value::Bytes(value.into()))
+    }
+
+    fn on_list_start(&mut self) -> Result<()> {
+        self.stack.push((Value::List(Vec::new()), Vec::new()));
+        Ok(())
+    }
+
+    fn on_list_end(&mut self) -> Result<()> {
+        self.close()
+    }
+
+    fn on_dict_start(&mut self) -> Result<()> {
+        self.stack.push((Value::Dict(Map::new()), Vec::new()));
+        Ok(())
+    }
+
+    fn on_dict_key(&mut self, key: &[u8]) -> Result<()> {
+        if let Some((_, pending)) = self.stack.last_mut() {
+            pending.clear();
+            pending.extend_from_slice(key);
+        }
+        Ok(())
+    }
+
+    fn on_dict_end(&mut self) -> Result<()> {
+        self.close()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize, normalize_with, DuplicatePolicy};
+    use crate::error::Error;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            Ok(b"d1:ai1e1:bli2e2:xyee".to_vec()),
+            normalize(b"d1:bli002e02:xye1:ai1ee")
+        );
+        let torrent = include_bytes!("../debian.torrent").to_vec();
+        assert_eq!(Ok(torrent.clone()), normalize(&torrent));
+        assert!(normalize(b"d1:ai1e").is_err());
+        assert!(normalize(b"i1ei2e").is_err());
+    }
+
+    #[test]
+    fn test_normalize_duplicates() {
+        let input = b"d1:ai1e1:bi2e1:ai3ee";
+        assert_eq!(Ok(b"d1:ai3e1:bi2ee".to_vec()), normalize(input));
+        assert_eq!(
+            Ok(b"d1:ai1e1:bi2ee".to_vec()),
+            normalize_with(input, DuplicatePolicy::First)
+        );
+        assert_eq!(
+            Err(Error::DuplicateKey("a".to_string())),
+            normalize_with(input, DuplicatePolicy::Error)
+        );
+    }
+}
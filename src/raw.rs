@@ -0,0 +1,248 @@
+use crate::de::from_bytes;
+use crate::error::{Error, Result};
+use crate::frame::measure;
+use crate::value::Value;
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+
+// the end of the value starting at `pos`
+fn skip(input: &[u8], pos: usize) -> Result<usize> {
+    match measure(&input[pos..])? {
+        Some(len) => Ok(pos + len),
+        None => Err(Error::Eof),
+    }
+}
+
+// the contents of the byte string starting at `pos`, and where it ends
+fn byte_string(input: &[u8], pos: usize) -> Result<(&[u8], usize)> {
+    let end = skip(input, pos)?;
+    let colon = input[pos..end]
+        .iter()
+        .position(|b| *b == b':')
+        .ok_or(Error::KeyMustBeByteString)?;
+    Ok((&input[pos + colon + 1..end], end))
+}
+
+// where the child of the container at `pos` named by `segment` starts
+fn child(input: &[u8], pos: usize, segment: &str) -> Result<Option<usize>> {
+    match input.get(pos) {
+        Some(b'd') => {
+            let mut pos = pos + 1;
+            loop {
+                match input.get(pos) {
+                    Some(b'e') => return Ok(None),
+                    Some(b'0'..=b'9') => {}
+                    Some(_) => return Err(Error::KeyMustBeByteString),
+                    None => return Err(Error::Eof),
+                }
+                let (key, value) = byte_string(input, pos)?;
+                if key == segment.as_bytes() {
+                    return Ok(Some(value));
+                }
+                pos = skip(input, value)?;
+            }
+        }
+        Some(b'l') => {
+            let index: usize = match segment.parse() {
+                Ok(index) => index,
+                Err(_) => return Ok(None),
+            };
+            let mut pos = pos + 1;
+            for _ in 0..index {
+                match input.get(pos) {
+                    Some(b'e') => return Ok(None),
+                    Some(_) => pos = skip(input, pos)?,
+                    None => return Err(Error::Eof),
+                }
+            }
+            match input.get(pos) {
+                Some(b'e') => Ok(None),
+                Some(_) => Ok(Some(pos)),
+                None => Err(Error::Eof),
+            }
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Returns the encoded bytes of the value at `path`, where each segment is a
+/// dictionary key or a list index, or `None` if there's nothing there.
+/// Values that aren't on the path are skipped without being decoded, so
+/// errors in them aren't reported.
+///
+/// ```
+/// let torrent = b"d8:announce3:url4:infod6:lengthi1eee";
+/// let info = serde_bencode::get_raw(torrent, &["info"]).unwrap();
+/// assert_eq!(Some(&b"d6:lengthi1ee"[..]), info);
+/// ```
+pub fn get_raw<'a>(input: &'a [u8], path: &[&str]) -> Result<Option<&'a [u8]>> {
+    let mut pos = 0;
+    for segment in path {
+        pos = match child(input, pos, segment)? {
+            Some(pos) => pos,
+            None => return Ok(None),
+        };
+    }
+    let end = skip(input, pos)?;
+    Ok(Some(&input[pos..end]))
+}
+
+// where each child of the container at `pos` starts
+fn children(input: &[u8], pos: usize, out: &mut Vec<usize>) -> Result<()> {
+    let is_dict = match input.get(pos) {
+        Some(b'd') => true,
+        Some(b'l') => false,
+        _ => return Ok(()),
+    };
+    let mut pos = pos + 1;
+    loop {
+        match input.get(pos) {
+            Some(b'e') => return Ok(()),
+            Some(_) if is_dict => pos = byte_string(input, pos)?.1,
+            Some(_) => {}
+            None => return Err(Error::Eof),
+        }
+        out.push(pos);
+        pos = skip(input, pos)?;
+    }
+}
+
+enum Step<'q> {
+    Key(&'q str),
+    Index(usize),
+    Any,
+}
+
+// the same syntax as `Value::get_path`, plus `*` and `[*]`
+fn parse_query(query: &str) -> Result<Vec<Step<'_>>> {
+    let invalid = || Error::Message(format!("invalid query {:?}", query));
+    let mut steps = Vec::new();
+    for segment in query.split('.').filter(|s| !s.is_empty()) {
+        let (key, positions) = match segment.split_once('[') {
+            Some((key, rest)) => (key, Some(rest.strip_suffix(']').ok_or_else(invalid)?)),
+            None => (segment, None),
+        };
+        match key {
+            "" => {}
+            "*" => steps.push(Step::Any),
+            key => steps.push(Step::Key(key)),
+        }
+        for position in positions.into_iter().flat_map(|p| p.split("][")) {
+            steps.push(match position {
+                "*" => Step::Any,
+                n => Step::Index(n.parse().map_err(|_| invalid())?),
+            });
+        }
+    }
+    Ok(steps)
+}
+
+/// Returns the encoded bytes of every value matching `query`, in input
+/// order. Queries are dotted paths like `Value::get_path` takes, where `*`
+/// or `[*]` matches every value in a dictionary or list:
+///
+/// ```
+/// let torrent = b"d4:infod5:filesld6:lengthi1eed6:lengthi2eeee";
+/// let lengths = serde_bencode::select(torrent, "info.files[*].length").unwrap();
+/// assert_eq!(vec![&b"i1e"[..], &b"i2e"[..]], lengths);
+/// ```
+///
+/// Like `get_raw`, anything off the matching paths is skipped unchecked.
+pub fn select<'a>(input: &'a [u8], query: &str) -> Result<Vec<&'a [u8]>> {
+    let mut matches = vec![0];
+    let mut next = Vec::new();
+    for step in parse_query(query)? {
+        for &pos in &matches {
+            match step {
+                Step::Key(key) => next.extend(child(input, pos, key)?),
+                Step::Index(i) if input.get(pos) == Some(&b'l') => {
+                    next.extend(child(input, pos, &i.to_string())?)
+                }
+                Step::Index(_) => {}
+                Step::Any => children(input, pos, &mut next)?,
+            }
+        }
+        core::mem::swap(&mut matches, &mut next);
+        next.clear();
+    }
+    matches
+        .into_iter()
+        .map(|pos| Ok(&input[pos..skip(input, pos)?]))
+        .collect()
+}
+
+/// Like `select`, with each match decoded.
+pub fn select_values(input: &[u8], query: &str) -> Result<Vec<Value>> {
+    select(input, query)?.into_iter().map(from_bytes).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_raw, select, select_values};
+    use crate::error::Error;
+    use crate::value::Value;
+
+    #[test]
+    fn test_get_raw() {
+        let input = b"d4:infod5:filesld6:lengthi1eed6:lengthi22eee4:name1:xe3:zzz0:e";
+        assert_eq!(
+            Ok(Some(&b"i22e"[..])),
+            get_raw(input, &["info", "files", "1", "length"])
+        );
+        assert_eq!(Ok(Some(&b"1:x"[..])), get_raw(input, &["info", "name"]));
+        assert_eq!(Ok(Some(&input[..])), get_raw(input, &[]));
+        assert_eq!(Ok(None), get_raw(input, &["info", "files", "2"]));
+        assert_eq!(Ok(None), get_raw(input, &["info", "files", "x"]));
+        assert_eq!(Ok(None), get_raw(input, &["info", "name", "x"]));
+        assert_eq!(Ok(None), get_raw(input, &["missing"]));
+    }
+
+    #[test]
+    fn test_get_raw_torrent() {
+        let torrent = include_bytes!("../debian.torrent").to_vec();
+        let info = get_raw(&torrent, &["info"]).unwrap().unwrap();
+        let value: crate::Value = crate::from_bytes(&torrent).unwrap();
+        assert_eq!(crate::to_bytes(&value["info"]).unwrap(), info);
+    }
+
+    #[test]
+    fn test_get_raw_errors() {
+        assert_eq!(Err(Error::Eof), get_raw(b"d1:ai1e", &["b"]));
+        assert_eq!(Err(Error::Eof), get_raw(b"li1e", &["1"]));
+        assert_eq!(
+            Err(Error::KeyMustBeByteString),
+            get_raw(b"di1ei2ee", &["a"])
+        );
+    }
+
+    #[test]
+    fn test_select() {
+        let input = b"d4:infod5:filesld6:lengthi1e4:pathl1:aeed6:lengthi22e4:pathl1:b1:ceeee\
+                      4:listli1ei2eee";
+        assert_eq!(
+            Ok(vec![&b"i1e"[..], &b"i22e"[..]]),
+            select(input, "info.files[*].length")
+        );
+        assert_eq!(
+            Ok(vec![&b"1:a"[..], &b"1:b"[..], &b"1:c"[..]]),
+            select(input, "info.files.*.path[*]")
+        );
+        assert_eq!(
+            Ok(vec![&b"1:c"[..]]),
+            select(input, "info.files[1].path[1]")
+        );
+        assert_eq!(Ok(vec![&b"i2e"[..]]), select(input, "list.1"));
+        assert_eq!(Ok(vec![]), select(input, "info.files[2]"));
+        assert_eq!(Ok(vec![&b"i1e"[..]]), select(input, "*[0]"));
+        assert_eq!(2, select(input, "*").unwrap().len());
+        assert!(select(input, "info.files[x]").is_err());
+        assert!(select(input, "info.files[0").is_err());
+
+        assert_eq!(
+            Ok(vec![Value::Int(1), Value::Int(22)]),
+            select_values(input, "info.files[*].length")
+        );
+    }
+}
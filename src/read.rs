@@ -0,0 +1,527 @@
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use core::mem;
+use core::ops::Deref;
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::error::{Error, Result};
+
+/// A source of input for the `Deserializer`: a slice it can borrow from, or
+/// a reader it copies out of. The trait is sealed.
+pub trait Read<'de>: private::Sealed {
+    #[doc(hidden)]
+    fn peek(&mut self) -> Result<Option<u8>>;
+
+    #[doc(hidden)]
+    fn next(&mut self) -> Result<Option<u8>>;
+
+    /// Reads the next `len` bytes, borrowed from the input when it can be
+    /// and copied into `scratch` otherwise.
+    #[doc(hidden)]
+    fn read_bytes<'s>(
+        &'s mut self,
+        len: usize,
+        scratch: &'s mut Vec<u8>,
+    ) -> Result<Reference<'de, 's, [u8]>>;
+
+    /// How many bytes have been consumed so far.
+    #[doc(hidden)]
+    fn offset(&self) -> usize;
+
+    /// The unread input, when it is all in memory.
+    #[doc(hidden)]
+    fn lookahead(&self) -> Option<&[u8]> {
+        None
+    }
+
+    /// Reads the run of ASCII digits at the current position, which may be
+    /// empty.
+    #[doc(hidden)]
+    fn read_digits<'s>(&'s mut self, scratch: &'s mut Vec<u8>) -> Result<Reference<'de, 's, [u8]>> {
+        scratch.clear();
+        while let Some(b @ b'0'..=b'9') = self.peek()? {
+            scratch.push(b);
+            self.next()?;
+        }
+        Ok(Reference::Copied(scratch))
+    }
+
+    /// Starts keeping what's consumed, for `captured`. Captures nest, and
+    /// each must be closed with `end_capture`.
+    #[doc(hidden)]
+    fn begin_capture(&mut self) {}
+
+    #[doc(hidden)]
+    fn end_capture(&mut self) {}
+
+    /// The input consumed since offset `start`, which must be no earlier
+    /// than the innermost open `begin_capture`.
+    #[doc(hidden)]
+    fn captured(&self, start: usize) -> Reference<'de, '_, [u8]>;
+}
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for super::SliceRead<'_> {}
+    impl Sealed for super::TrustedRead<'_> {}
+    #[cfg(feature = "std")]
+    impl<R: std::io::Read> Sealed for super::IoRead<R> {}
+    #[cfg(feature = "bytes")]
+    impl<B: bytes::Buf> Sealed for super::BytesRead<B> {}
+}
+
+/// Bytes that either live as long as the input (`'b`) or only until the
+/// next read (`'c`).
+pub enum Reference<'b, 'c, T: ?Sized> {
+    Borrowed(&'b T),
+    Copied(&'c T),
+}
+
+impl<T: ?Sized> Deref for Reference<'_, '_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match self {
+            Reference::Borrowed(t) => t,
+            Reference::Copied(t) => t,
+        }
+    }
+}
+
+/// Input from a byte slice; byte strings are borrowed from it.
+pub struct SliceRead<'a> {
+    slice: &'a [u8],
+    index: usize,
+}
+
+impl<'a> SliceRead<'a> {
+    pub fn new(slice: &'a [u8]) -> Self {
+        SliceRead { slice, index: 0 }
+    }
+
+    pub(crate) fn remaining(&self) -> &'a [u8] {
+        &self.slice[self.index..]
+    }
+}
+
+impl<'a> Read<'a> for SliceRead<'a> {
+    fn peek(&mut self) -> Result<Option<u8>> {
+        Ok(self.slice.get(self.index).copied())
+    }
+
+    fn next(&mut self) -> Result<Option<u8>> {
+        let b = self.peek()?;
+        if b.is_some() {
+            self.index += 1;
+        }
+        Ok(b)
+    }
+
+    fn read_bytes<'s>(
+        &'s mut self,
+        len: usize,
+        _scratch: &'s mut Vec<u8>,
+    ) -> Result<Reference<'a, 's, [u8]>> {
+        let bytes = self.remaining().get(..len).ok_or(Error::Eof)?;
+        self.index += len;
+        Ok(Reference::Borrowed(bytes))
+    }
+
+    fn offset(&self) -> usize {
+        self.index
+    }
+
+    fn lookahead(&self) -> Option<&[u8]> {
+        Some(self.remaining())
+    }
+
+    fn read_digits<'s>(&'s mut self, _scratch: &'s mut Vec<u8>) -> Result<Reference<'a, 's, [u8]>> {
+        let rest = self.remaining();
+        let len = rest
+            .iter()
+            .position(|b| !b.is_ascii_digit())
+            .unwrap_or(rest.len());
+        self.index += len;
+        Ok(Reference::Borrowed(&rest[..len]))
+    }
+
+    fn captured(&self, start: usize) -> Reference<'a, '_, [u8]> {
+        Reference::Borrowed(&self.slice[start..self.index])
+    }
+}
+
+/// A `SliceRead` over input that `check_structure` accepted, so byte
+/// strings are sliced out without bounds checks.
+pub(crate) struct TrustedRead<'a>(SliceRead<'a>);
+
+impl<'a> TrustedRead<'a> {
+    /// Safety: `check_structure` must have accepted `slice`.
+    pub(crate) unsafe fn new(slice: &'a [u8]) -> Self {
+        TrustedRead(SliceRead::new(slice))
+    }
+}
+
+impl<'a> Read<'a> for TrustedRead<'a> {
+    fn peek(&mut self) -> Result<Option<u8>> {
+        self.0.peek()
+    }
+
+    fn next(&mut self) -> Result<Option<u8>> {
+        self.0.next()
+    }
+
+    fn read_bytes<'s>(
+        &'s mut self,
+        len: usize,
+        _scratch: &'s mut Vec<u8>,
+    ) -> Result<Reference<'a, 's, [u8]>> {
+        let SliceRead { slice, index } = &mut self.0;
+        debug_assert!(slice.len() - *index >= len);
+        // Safety: byte strings are only read right after their whole run of
+        // length digits and the colon, and the deserializer only ever stops
+        // between tokens, so this is a byte string `check_structure` found
+        // to fit in the input.
+        let bytes = unsafe { slice.get_unchecked(*index..*index + len) };
+        *index += len;
+        Ok(Reference::Borrowed(bytes))
+    }
+
+    fn offset(&self) -> usize {
+        self.0.offset()
+    }
+
+    fn lookahead(&self) -> Option<&[u8]> {
+        self.0.lookahead()
+    }
+
+    fn read_digits<'s>(&'s mut self, scratch: &'s mut Vec<u8>) -> Result<Reference<'a, 's, [u8]>> {
+        self.0.read_digits(scratch)
+    }
+
+    fn captured(&self, start: usize) -> Reference<'a, '_, [u8]> {
+        self.0.captured(start)
+    }
+}
+
+/// A copy of what's been consumed since the outermost open capture, for
+/// readers that don't keep their input around.
+#[cfg(feature = "std")]
+#[derive(Default)]
+struct Recorder {
+    bytes: Vec<u8>,
+    // the offset `bytes` starts at
+    start: usize,
+    depth: usize,
+}
+
+#[cfg(feature = "std")]
+impl Recorder {
+    fn begin(&mut self, offset: usize) {
+        if self.depth == 0 {
+            self.bytes.clear();
+            self.start = offset;
+        }
+        self.depth += 1;
+    }
+
+    fn end(&mut self) {
+        self.depth -= 1;
+    }
+
+    fn record(&mut self, b: &[u8]) {
+        if self.depth > 0 {
+            self.bytes.extend_from_slice(b);
+        }
+    }
+
+    fn since(&self, offset: usize) -> &[u8] {
+        &self.bytes[offset - self.start..]
+    }
+}
+
+#[cfg(feature = "std")]
+/// Input from an `io::Read`, read through a buffer as it's needed. Byte
+/// strings are copied into the deserializer's scratch buffer, so memory use
+/// follows the largest byte string rather than the whole document.
+pub struct IoRead<R> {
+    reader: R,
+    buf: Vec<u8>,
+    // the unread part of `buf` is `pos..filled`
+    pos: usize,
+    filled: usize,
+    offset: usize,
+    recorder: Recorder,
+}
+
+#[cfg(feature = "std")]
+const BUFFER_SIZE: usize = 8 * 1024;
+
+#[cfg(feature = "std")]
+impl<R: io::Read> IoRead<R> {
+    pub fn new(reader: R) -> Self {
+        IoRead::with_buffer(reader, Vec::new())
+    }
+
+    /// Reads through `buf`, reusing its allocation.
+    pub(crate) fn with_buffer(reader: R, mut buf: Vec<u8>) -> Self {
+        buf.resize(BUFFER_SIZE, 0);
+        IoRead {
+            reader,
+            buf,
+            pos: 0,
+            filled: 0,
+            offset: 0,
+            recorder: Recorder::default(),
+        }
+    }
+
+    /// Switches to `reader`, keeping the buffer but dropping anything read
+    /// ahead from the old one.
+    pub(crate) fn reset(&mut self, reader: R) -> R {
+        self.pos = 0;
+        self.filled = 0;
+        self.offset = 0;
+        self.recorder = Recorder::default();
+        mem::replace(&mut self.reader, reader)
+    }
+
+    /// Gives the buffer back, dropping anything read ahead.
+    pub(crate) fn into_buffer(self) -> Vec<u8> {
+        self.buf
+    }
+
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos == self.filled {
+            self.filled = loop {
+                match self.reader.read(&mut self.buf) {
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    result => break result?,
+                }
+            };
+            self.pos = 0;
+        }
+        Ok(&self.buf[self.pos..self.filled])
+    }
+
+    fn consume(&mut self, n: usize) {
+        self.recorder.record(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        self.offset += n;
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de, R: io::Read> Read<'de> for IoRead<R> {
+    fn peek(&mut self) -> Result<Option<u8>> {
+        let buf = self.fill_buf()?;
+        Ok(buf.first().copied())
+    }
+
+    fn next(&mut self) -> Result<Option<u8>> {
+        let b = self.peek()?;
+        if b.is_some() {
+            self.consume(1);
+        }
+        Ok(b)
+    }
+
+    fn read_bytes<'s>(
+        &'s mut self,
+        len: usize,
+        scratch: &'s mut Vec<u8>,
+    ) -> Result<Reference<'de, 's, [u8]>> {
+        scratch.clear();
+        // grows with what's actually read, not the length the input claims
+        while scratch.len() < len {
+            let buf = self.fill_buf()?;
+            if buf.is_empty() {
+                return Err(Error::Eof);
+            }
+            let n = buf.len().min(len - scratch.len());
+            scratch.extend_from_slice(&buf[..n]);
+            self.consume(n);
+        }
+        Ok(Reference::Copied(scratch))
+    }
+
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn begin_capture(&mut self) {
+        self.recorder.begin(self.offset);
+    }
+
+    fn end_capture(&mut self) {
+        self.recorder.end();
+    }
+
+    fn captured(&self, start: usize) -> Reference<'de, '_, [u8]> {
+        Reference::Copied(self.recorder.since(start))
+    }
+}
+
+/// Input from a `bytes::Buf`, which may be split across several chunks.
+/// Byte strings are copied into the deserializer's scratch buffer.
+#[cfg(feature = "bytes")]
+pub struct BytesRead<B> {
+    buf: B,
+    offset: usize,
+    recorder: Recorder,
+}
+
+#[cfg(feature = "bytes")]
+impl<B: bytes::Buf> BytesRead<B> {
+    pub fn new(buf: B) -> Self {
+        BytesRead {
+            buf,
+            offset: 0,
+            recorder: Recorder::default(),
+        }
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl<'de, B: bytes::Buf> Read<'de> for BytesRead<B> {
+    fn peek(&mut self) -> Result<Option<u8>> {
+        Ok(self.buf.chunk().first().copied())
+    }
+
+    fn next(&mut self) -> Result<Option<u8>> {
+        let b = self.peek()?;
+        if let Some(b) = b {
+            self.recorder.record(&[b]);
+            self.buf.advance(1);
+            self.offset += 1;
+        }
+        Ok(b)
+    }
+
+    fn read_bytes<'s>(
+        &'s mut self,
+        len: usize,
+        scratch: &'s mut Vec<u8>,
+    ) -> Result<Reference<'de, 's, [u8]>> {
+        if self.buf.remaining() < len {
+            return Err(Error::Eof);
+        }
+        scratch.clear();
+        scratch.resize(len, 0);
+        self.buf.copy_to_slice(scratch);
+        self.recorder.record(scratch);
+        self.offset += len;
+        Ok(Reference::Copied(scratch))
+    }
+
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn begin_capture(&mut self) {
+        self.recorder.begin(self.offset);
+    }
+
+    fn end_capture(&mut self) {
+        self.recorder.end();
+    }
+
+    fn captured(&self, start: usize) -> Reference<'de, '_, [u8]> {
+        Reference::Copied(self.recorder.since(start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "std")]
+    use std::io;
+
+    #[cfg(feature = "std")]
+    use super::IoRead;
+    use super::{Read, SliceRead};
+    #[cfg(feature = "std")]
+    use crate::error::Error;
+
+    #[test]
+    fn test_slice_read() {
+        let mut scratch = Vec::new();
+        let mut read = SliceRead::new(b"ab3:xyz");
+        assert_eq!(Ok(Some(b'a')), read.peek());
+        assert_eq!(Ok(Some(b'a')), read.next());
+        assert_eq!(&b"b3"[..], &*read.read_bytes(2, &mut scratch).unwrap());
+        assert_eq!(3, read.offset());
+        assert!(read.read_bytes(5, &mut scratch).is_err());
+        assert_eq!(&b":xyz"[..], &*read.read_bytes(4, &mut scratch).unwrap());
+        assert_eq!(Ok(None), read.next());
+        assert!(scratch.is_empty());
+
+        let mut read = SliceRead::new(b"123e");
+        assert_eq!(&b"123"[..], &*read.read_digits(&mut scratch).unwrap());
+        assert_eq!(&b""[..], &*read.read_digits(&mut scratch).unwrap());
+        assert_eq!(3, read.offset());
+    }
+
+    // hands out one byte per read, so byte strings span many buffer fills
+    #[cfg(feature = "std")]
+    struct Trickle<'a>(&'a [u8]);
+
+    #[cfg(feature = "std")]
+    impl io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match (self.0.split_first(), buf.first_mut()) {
+                (Some((b, rest)), Some(out)) => {
+                    *out = *b;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_io_read() {
+        let mut scratch = Vec::new();
+        let mut read = IoRead::new(Trickle(b"ab3:xyz"));
+        assert_eq!(Ok(Some(b'a')), Read::peek(&mut read));
+        assert_eq!(Ok(Some(b'a')), Read::next(&mut read));
+        assert_eq!(
+            &b"b3"[..],
+            &*Read::read_bytes(&mut read, 2, &mut scratch).unwrap()
+        );
+        assert_eq!(3, Read::offset(&read));
+        assert_eq!(
+            &b":xyz"[..],
+            &*Read::read_bytes(&mut read, 4, &mut scratch).unwrap()
+        );
+        assert_eq!(b":xyz", &scratch[..]);
+        assert_eq!(Ok(None), Read::next(&mut read));
+        assert_eq!(
+            Err(Error::Eof),
+            Read::read_bytes(&mut read, 1, &mut scratch).map(|_| ())
+        );
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn test_bytes_read() {
+        use bytes::Buf;
+
+        let mut scratch = Vec::new();
+        let chained = (&b"ab3"[..]).chain(&b":xyz"[..]);
+        let mut read = super::BytesRead::new(chained);
+        assert_eq!(Ok(Some(b'a')), Read::next(&mut read));
+        assert_eq!(Ok(Some(b'b')), Read::next(&mut read));
+        assert_eq!(
+            &b"3:xy"[..],
+            &*Read::read_bytes(&mut read, 4, &mut scratch).unwrap()
+        );
+        assert_eq!(6, Read::offset(&read));
+        assert_eq!(
+            Err(Error::Eof),
+            Read::read_bytes(&mut read, 2, &mut scratch).map(|_| ())
+        );
+        assert_eq!(Ok(Some(b'z')), Read::peek(&mut read));
+    }
+}
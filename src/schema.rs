@@ -0,0 +1,272 @@
+use core::fmt::{self, Display};
+
+use crate::de::from_bytes;
+use crate::error::Error;
+use crate::value::ValueRef;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// The shape a value is expected to have.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Schema {
+    Any,
+    Int,
+    Bytes,
+    /// A byte string that's valid UTF-8.
+    Str,
+    /// A list whose elements all match the schema.
+    List(Box<Schema>),
+    Dict(DictSchema),
+}
+
+impl Schema {
+    pub fn list(element: Schema) -> Schema {
+        Schema::List(Box::new(element))
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Schema::Any => "any value",
+            Schema::Int => "integer",
+            Schema::Bytes => "byte string",
+            Schema::Str => "UTF-8 string",
+            Schema::List(_) => "list",
+            Schema::Dict(_) => "dictionary",
+        }
+    }
+}
+
+/// The keys a dictionary is expected to have. Keys the schema doesn't
+/// mention are allowed unless `deny_unknown` is called.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DictSchema {
+    fields: Vec<(Vec<u8>, Schema, bool)>,
+    deny_unknown: bool,
+}
+
+impl DictSchema {
+    pub fn new() -> Self {
+        DictSchema::default()
+    }
+
+    pub fn required<K: AsRef<[u8]>>(mut self, key: K, schema: Schema) -> Self {
+        self.fields.push((key.as_ref().to_vec(), schema, true));
+        self
+    }
+
+    pub fn optional<K: AsRef<[u8]>>(mut self, key: K, schema: Schema) -> Self {
+        self.fields.push((key.as_ref().to_vec(), schema, false));
+        self
+    }
+
+    pub fn deny_unknown(mut self) -> Self {
+        self.deny_unknown = true;
+        self
+    }
+}
+
+impl From<DictSchema> for Schema {
+    fn from(dict: DictSchema) -> Schema {
+        Schema::Dict(dict)
+    }
+}
+
+/// A way a document doesn't match a schema, and the path to where.
+#[derive(Debug, PartialEq)]
+pub struct SchemaViolation {
+    /// Keys and indexes like `info.files[3].length`, empty for the root.
+    pub path: String,
+    pub kind: SchemaViolationKind,
+}
+
+#[derive(Debug, PartialEq)]
+#[non_exhaustive]
+pub enum SchemaViolationKind {
+    /// A required key is missing; it's the last part of the path.
+    Missing,
+    /// A key the schema doesn't allow.
+    Unknown,
+    WrongType {
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// The input isn't valid bencode.
+    Invalid(Error),
+}
+
+impl Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        match &self.kind {
+            SchemaViolationKind::Missing => f.write_str("missing required key"),
+            SchemaViolationKind::Unknown => f.write_str("unknown key"),
+            SchemaViolationKind::WrongType { expected, found } => {
+                write!(f, "expected {}, found {}", expected, found)
+            }
+            SchemaViolationKind::Invalid(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Checks `input` against `schema`, reporting every mismatch.
+pub fn validate_against(input: &[u8], schema: &Schema) -> Result<(), Vec<SchemaViolation>> {
+    let value: ValueRef = match from_bytes(input) {
+        Ok(value) => value,
+        Err(e) => {
+            return Err(vec![SchemaViolation {
+                path: String::new(),
+                kind: SchemaViolationKind::Invalid(e),
+            }])
+        }
+    };
+    let mut checker = Checker {
+        path: String::new(),
+        violations: Vec::new(),
+    };
+    checker.check(&value, schema);
+    match checker.violations.is_empty() {
+        true => Ok(()),
+        false => Err(checker.violations),
+    }
+}
+
+struct Checker {
+    path: String,
+    violations: Vec<SchemaViolation>,
+}
+
+impl Checker {
+    fn report(&mut self, kind: SchemaViolationKind) {
+        self.violations.push(SchemaViolation {
+            path: self.path.clone(),
+            kind,
+        });
+    }
+
+    // runs `f` with `segment` appended to the path
+    fn at<F: FnOnce(&mut Checker)>(&mut self, segment: fmt::Arguments<'_>, f: F) {
+        let len = self.path.len();
+        let _ = fmt::Write::write_fmt(&mut self.path, segment);
+        f(self);
+        self.path.truncate(len);
+    }
+
+    fn at_key<F: FnOnce(&mut Checker)>(&mut self, key: &[u8], f: F) {
+        let dot = if self.path.is_empty() { "" } else { "." };
+        self.at(format_args!("{}{}", dot, String::from_utf8_lossy(key)), f)
+    }
+
+    fn check(&mut self, value: &ValueRef, schema: &Schema) {
+        let matches = match (schema, value) {
+            (Schema::Any, _) => true,
+            (Schema::Int, ValueRef::Int(_)) => true,
+            (Schema::Bytes, ValueRef::Bytes(_)) => true,
+            (Schema::Str, ValueRef::Bytes(b)) => core::str::from_utf8(b).is_ok(),
+            (Schema::List(element), ValueRef::List(list)) => {
+                for (i, v) in list.iter().enumerate() {
+                    self.at(format_args!("[{}]", i), |c| c.check(v, element));
+                }
+                true
+            }
+            (Schema::Dict(dict), ValueRef::Dict(map)) => {
+                for (key, schema, required) in &dict.fields {
+                    match map.get(&key[..]) {
+                        Some(v) => self.at_key(key, |c| c.check(v, schema)),
+                        None if *required => {
+                            self.at_key(key, |c| c.report(SchemaViolationKind::Missing))
+                        }
+                        None => {}
+                    }
+                }
+                if dict.deny_unknown {
+                    for key in map.keys() {
+                        if !dict.fields.iter().any(|(k, _, _)| k == key) {
+                            self.at_key(key, |c| c.report(SchemaViolationKind::Unknown));
+                        }
+                    }
+                }
+                true
+            }
+            _ => false,
+        };
+        if !matches {
+            let found = match value {
+                ValueRef::Int(_) => "integer",
+                ValueRef::Bytes(_) => "byte string",
+                ValueRef::List(_) => "list",
+                ValueRef::Dict(_) => "dictionary",
+            };
+            self.report(SchemaViolationKind::WrongType {
+                expected: schema.name(),
+                found,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_against, DictSchema, Schema, SchemaViolationKind};
+
+    fn torrent() -> Schema {
+        let file = DictSchema::new()
+            .required("length", Schema::Int)
+            .required("path", Schema::list(Schema::Str));
+        let info = DictSchema::new()
+            .required("name", Schema::Str)
+            .required("piece length", Schema::Int)
+            .required("pieces", Schema::Bytes)
+            .optional("length", Schema::Int)
+            .optional("files", Schema::list(file.into()));
+        DictSchema::new()
+            .required("info", info.into())
+            .optional("announce", Schema::Str)
+            .into()
+    }
+
+    #[test]
+    fn test_validate_against() {
+        let input = include_bytes!("../debian.torrent").to_vec();
+        assert_eq!(Ok(()), validate_against(&input, &torrent()));
+
+        let input = b"d4:infod5:filesld6:lengthi1e4:pathl1:aeed4:pathli1eeee\
+                      4:name1:x6:pieces0:ee";
+        let violations: Vec<_> = validate_against(input, &torrent())
+            .unwrap_err()
+            .into_iter()
+            .map(|v| v.to_string())
+            .collect();
+        assert_eq!(
+            vec![
+                "info.piece length: missing required key",
+                "info.files[1].length: missing required key",
+                "info.files[1].path[0]: expected UTF-8 string, found integer",
+            ],
+            violations
+        );
+    }
+
+    #[test]
+    fn test_validate_against_strict() {
+        let schema: Schema = DictSchema::new()
+            .required("a", Schema::Int)
+            .deny_unknown()
+            .into();
+        assert_eq!(Ok(()), validate_against(b"d1:ai1ee", &schema));
+        let violations = validate_against(b"d1:ai1e1:bi2ee", &schema).unwrap_err();
+        assert_eq!("b", violations[0].path);
+        assert_eq!(SchemaViolationKind::Unknown, violations[0].kind);
+
+        let violations = validate_against(b"li1e", &schema).unwrap_err();
+        assert!(matches!(
+            violations[0].kind,
+            SchemaViolationKind::Invalid(_)
+        ));
+        let violations = validate_against(b"le", &schema).unwrap_err();
+        assert_eq!("expected dictionary, found list", violations[0].to_string());
+    }
+}
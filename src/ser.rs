@@ -0,0 +1,1245 @@
+use serde::ser::{self, Impossible, Serialize};
+
+use crate::error::{Error, Result};
+use crate::io;
+use crate::len::encoded_len;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+
+/// How `bool` is represented, since bencode has no boolean type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoolPolicy {
+    /// `false` => `i0e`, `true` => `i1e`
+    Int,
+    Error,
+}
+
+/// How `f32`/`f64` are represented, since bencode has no float type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FloatPolicy {
+    Error,
+    /// The shortest decimal text that round-trips, as a byte string
+    String,
+}
+
+/// What to do with `None` and `()`. Skipped values in a dictionary drop
+/// their key as well; in a list or tuple there is nothing to drop, so they
+/// are an error either way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NonePolicy {
+    Skip,
+    Error,
+}
+
+/// How strictly the output follows the canonical form of the spec.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Canonical {
+    /// Dictionary keys are written in the order they are serialized
+    Lenient,
+    /// Dictionary keys are sorted by their raw bytes
+    Sorted,
+    /// Sorted, and anything without a single canonical encoding is an
+    /// error instead of being written. Options that convert values to make
+    /// them fit (`FloatPolicy::String`, `KeyPolicy::Decimal` and
+    /// `KeyPolicy::Custom`) are turned down before anything is written.
+    Strict,
+}
+
+pub type KeyConverter = dyn Fn(&[u8]) -> Result<Vec<u8>> + Send + Sync;
+
+/// How dictionary keys that aren't strings or bytes (`HashMap<u32, _>`,
+/// tuple keys, ...) are written.
+#[derive(Clone)]
+pub enum KeyPolicy {
+    Error,
+    /// Integer keys are written as their decimal text
+    Decimal,
+    /// The key's own bencode encoding is passed to the closure, which
+    /// returns the key bytes to use
+    Custom(Arc<KeyConverter>),
+}
+
+impl KeyPolicy {
+    pub fn custom<F>(f: F) -> Self
+    where
+        F: Fn(&[u8]) -> Result<Vec<u8>> + Send + Sync + 'static,
+    {
+        KeyPolicy::Custom(Arc::new(f))
+    }
+}
+
+impl fmt::Debug for KeyPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyPolicy::Error => f.write_str("Error"),
+            KeyPolicy::Decimal => f.write_str("Decimal"),
+            KeyPolicy::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SerializerOptions {
+    pub(crate) bool_policy: BoolPolicy,
+    pub(crate) float_policy: FloatPolicy,
+    pub(crate) none_policy: NonePolicy,
+    pub(crate) canonical: Canonical,
+    pub(crate) key_policy: KeyPolicy,
+    pub(crate) presize: bool,
+}
+
+impl Default for SerializerOptions {
+    fn default() -> Self {
+        SerializerOptions {
+            bool_policy: BoolPolicy::Int,
+            float_policy: FloatPolicy::Error,
+            none_policy: NonePolicy::Skip,
+            canonical: Canonical::Sorted,
+            key_policy: KeyPolicy::Error,
+            presize: false,
+        }
+    }
+}
+
+impl SerializerOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn bool_policy(mut self, policy: BoolPolicy) -> Self {
+        self.bool_policy = policy;
+        self
+    }
+
+    pub fn float_policy(mut self, policy: FloatPolicy) -> Self {
+        self.float_policy = policy;
+        self
+    }
+
+    pub fn none_policy(mut self, policy: NonePolicy) -> Self {
+        self.none_policy = policy;
+        self
+    }
+
+    pub fn canonical(mut self, canonical: Canonical) -> Self {
+        self.canonical = canonical;
+        self
+    }
+
+    pub fn key_policy(mut self, policy: KeyPolicy) -> Self {
+        self.key_policy = policy;
+        self
+    }
+
+    /// Makes `to_bytes_with_options` measure the value first (see
+    /// `serialized_len`) and allocate the output at its exact size, trading
+    /// a second walk for not regrowing it. Dictionaries are still put
+    /// together in a scratch buffer, so their keys can be sorted.
+    pub fn presize(mut self, presize: bool) -> Self {
+        self.presize = presize;
+        self
+    }
+
+    // Fails on a policy that `Canonical::Strict` rules out, so the options
+    // are turned down whatever the value turns out to hold.
+    pub(crate) fn check(&self) -> Result<()> {
+        if self.canonical != Canonical::Strict {
+            return Ok(());
+        }
+        if self.float_policy == FloatPolicy::String {
+            return Err(Error::NonCanonical("float"));
+        }
+        match self.key_policy {
+            KeyPolicy::Error => Ok(()),
+            KeyPolicy::Decimal | KeyPolicy::Custom(_) => {
+                Err(Error::NonCanonical("converted dictionary key"))
+            }
+        }
+    }
+}
+
+pub struct Serializer<W> {
+    output: W,
+    options: SerializerOptions,
+    // bytes written so far, to tell a skipped list element from a real one
+    written: usize,
+}
+
+impl<W: io::Write> Serializer<W> {
+    pub fn new(output: W) -> Self {
+        Serializer {
+            output,
+            options: SerializerOptions::default(),
+            written: 0,
+        }
+    }
+
+    /// Fails if `options` combine `Canonical::Strict` with a policy it rules
+    /// out.
+    pub fn with_options(output: W, options: SerializerOptions) -> Result<Self> {
+        options.check()?;
+        Ok(Serializer {
+            output,
+            options,
+            written: 0,
+        })
+    }
+
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+}
+
+pub fn to_bytes<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: ?Sized + Serialize,
+{
+    to_bytes_with_options(value, SerializerOptions::default())
+}
+
+pub fn to_bytes_with_options<T>(value: &T, options: SerializerOptions) -> Result<Vec<u8>>
+where
+    T: ?Sized + Serialize,
+{
+    options.check()?;
+    let output = if options.presize {
+        Vec::with_capacity(encoded_len(value, &options)?)
+    } else {
+        Vec::new()
+    };
+    let mut serializer = Serializer::with_options(output, options)?;
+    value.serialize(&mut serializer)?;
+    Ok(serializer.output)
+}
+
+/// Appends the encoding of `value` to `buf`, so one buffer can be reused
+/// across many calls. On error `buf` is left as it was.
+pub fn to_bytes_in<T>(value: &T, buf: &mut Vec<u8>) -> Result<()>
+where
+    T: ?Sized + Serialize,
+{
+    let start = buf.len();
+    let mut serializer = Serializer::new(&mut *buf);
+    let result = value.serialize(&mut serializer);
+    if result.is_err() {
+        buf.truncate(start);
+    }
+    result
+}
+
+/// Appends the encoding of `value` to `buf`. On error `buf` is left as it
+/// was.
+#[cfg(feature = "bytes")]
+pub fn to_bytes_mut<T>(value: &T, buf: &mut bytes::BytesMut) -> Result<()>
+where
+    T: ?Sized + Serialize,
+{
+    use bytes::BufMut;
+
+    let start = buf.len();
+    let mut serializer = Serializer::new((&mut *buf).writer());
+    let result = value.serialize(&mut serializer);
+    if result.is_err() {
+        buf.truncate(start);
+    }
+    result
+}
+
+pub fn to_writer<W, T>(writer: W, value: &T) -> Result<()>
+where
+    W: io::Write,
+    T: ?Sized + Serialize,
+{
+    let mut serializer = Serializer::new(writer);
+    value.serialize(&mut serializer)
+}
+
+// basic encoding functions
+impl<W: io::Write> Serializer<W> {
+    fn write(&mut self, b: &[u8]) -> Result<()> {
+        self.output.write_all(b)?;
+        self.written += b.len();
+        Ok(())
+    }
+
+    fn write_int<T: core::fmt::Display>(&mut self, v: T) -> Result<()> {
+        self.write(format!("i{}e", v).as_bytes())
+    }
+
+    fn write_byte_array(&mut self, b: &[u8]) -> Result<()> {
+        self.write(format!("{}:", b.len()).as_bytes())?;
+        self.write(b)
+    }
+
+    fn write_none(&mut self) -> Result<()> {
+        match self.options.none_policy {
+            NonePolicy::Skip => Ok(()),
+            NonePolicy::Error => Err(Error::Unsupported("none")),
+        }
+    }
+
+    fn write_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let start = self.written;
+        value.serialize(&mut *self)?;
+        if self.written == start {
+            return Err(Error::Unsupported("none in a list"));
+        }
+        Ok(())
+    }
+
+    fn child(&self) -> Serializer<Vec<u8>> {
+        Serializer {
+            output: Vec::new(),
+            options: self.options.clone(),
+            written: 0,
+        }
+    }
+}
+
+impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = MapWriter<'a, W>;
+    type SerializeStruct = MapWriter<'a, W>;
+    type SerializeStructVariant = MapWriter<'a, W>;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        match self.options.bool_policy {
+            BoolPolicy::Int => self.write_int(v as u8),
+            BoolPolicy::Error => Err(Error::Unsupported("bool")),
+        }
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.write_int(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.write_int(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.write_int(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.write_int(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<()> {
+        self.write_int(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.write_int(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.write_int(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.write_int(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.write_int(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<()> {
+        self.write_int(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        if self.options.canonical == Canonical::Strict {
+            return Err(Error::NonCanonical("float"));
+        }
+        match self.options.float_policy {
+            FloatPolicy::Error => Err(Error::Unsupported("float")),
+            FloatPolicy::String => self.write_byte_array(v.to_string().as_bytes()),
+        }
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.write_byte_array(v.encode_utf8(&mut [0; 4]).as_bytes())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.write_byte_array(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.write_byte_array(v)
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.write_none()
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        self.write_none()
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        self.write_none()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    // enums with data are a single-key dictionary of variant name to value
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        // a skipped value drops the whole variant, like a dictionary entry
+        let mut child = self.child();
+        value.serialize(&mut child)?;
+        if child.output.is_empty() {
+            return Ok(());
+        }
+        self.write(b"d")?;
+        self.write_byte_array(variant.as_bytes())?;
+        self.write(&child.output)?;
+        self.write(b"e")
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        self.write(b"l")?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        self.write(b"d")?;
+        self.write_byte_array(variant.as_bytes())?;
+        self.write(b"l")?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Ok(MapWriter::new(self, false))
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        self.write(b"d")?;
+        self.write_byte_array(variant.as_bytes())?;
+        Ok(MapWriter::new(self, true))
+    }
+}
+
+impl<W: io::Write> ser::SerializeSeq for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.write_element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.write(b"e")
+    }
+}
+
+impl<W: io::Write> ser::SerializeTuple for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.write_element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.write(b"e")
+    }
+}
+
+impl<W: io::Write> ser::SerializeTupleStruct for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.write_element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.write(b"e")
+    }
+}
+
+impl<W: io::Write> ser::SerializeTupleVariant for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.write_element(value)
+    }
+
+    // closes both the list and the enclosing variant dictionary
+    fn end(self) -> Result<()> {
+        self.write(b"ee")
+    }
+}
+
+// Dictionary entries are buffered so they can be sorted, and so that a
+// value which serializes to nothing (a skipped `None`) also drops its key.
+// Values go back to back into one buffer, with each entry holding its key
+// and where its value lies.
+pub struct MapWriter<'a, W> {
+    ser: &'a mut Serializer<W>,
+    values: Serializer<Vec<u8>>,
+    entries: Vec<(Vec<u8>, Range<usize>)>,
+    key: Option<Vec<u8>>,
+    // set for struct variants, which need an extra `e` for the wrapper
+    variant: bool,
+}
+
+impl<'a, W: io::Write> MapWriter<'a, W> {
+    fn new(ser: &'a mut Serializer<W>, variant: bool) -> Self {
+        MapWriter {
+            values: ser.child(),
+            ser,
+            entries: Vec::new(),
+            key: None,
+            variant,
+        }
+    }
+
+    fn push_value<T>(&mut self, key: Vec<u8>, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let start = self.values.output.len();
+        value.serialize(&mut self.values)?;
+        let end = self.values.output.len();
+        if end > start {
+            self.entries.push((key, start..end));
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<()> {
+        let duplicate = match self.ser.options.canonical {
+            Canonical::Lenient => {
+                let mut keys: Vec<&[u8]> = self.entries.iter().map(|e| e.0.as_slice()).collect();
+                keys.sort_unstable();
+                keys.windows(2).find(|w| w[0] == w[1]).map(|w| w[0])
+            }
+            Canonical::Sorted | Canonical::Strict => {
+                self.entries.sort_by(|a, b| a.0.cmp(&b.0));
+                self.entries
+                    .windows(2)
+                    .find(|w| w[0].0 == w[1].0)
+                    .map(|w| w[0].0.as_slice())
+            }
+        };
+        if let Some(key) = duplicate {
+            return Err(Error::DuplicateKey(
+                String::from_utf8_lossy(key).into_owned(),
+            ));
+        }
+
+        self.ser.write(b"d")?;
+        for (key, range) in &self.entries {
+            self.ser.write_byte_array(key)?;
+            self.ser.write(&self.values.output[range.clone()])?;
+        }
+        self.ser.write(b"e")?;
+        if self.variant {
+            self.ser.write(b"e")?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: io::Write> ser::SerializeMap for MapWriter<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.key = Some(map_key(key, &self.ser.options, |k| Ok(k.to_vec()))?);
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error::Message("value serialized before its key".to_string()))?;
+        self.push_value(key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: io::Write> ser::SerializeStruct for MapWriter<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push_value(key.as_bytes().to_vec(), value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: io::Write> ser::SerializeStructVariant for MapWriter<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push_value(key.as_bytes().to_vec(), value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+// Resolves a dictionary key to its raw bytes according to the key policy
+// and hands them to `f`, so callers can copy or just measure them.
+pub(crate) fn map_key<T, F, R>(key: &T, options: &SerializerOptions, f: F) -> Result<R>
+where
+    T: ?Sized + Serialize,
+    F: Fn(&[u8]) -> Result<R>,
+{
+    let decimal = matches!(options.key_policy, KeyPolicy::Decimal);
+    match key.serialize(MapKeySerializer { f: &f, decimal }) {
+        Err(Error::KeyMustBeByteString) => match &options.key_policy {
+            KeyPolicy::Custom(convert) => {
+                f(&convert(&to_bytes_with_options(key, options.clone())?)?)
+            }
+            _ => Err(Error::KeyMustBeByteString),
+        },
+        result => result,
+    }
+}
+
+// Dictionary keys must be byte strings; integers are allowed too when
+// `decimal` is set.
+struct MapKeySerializer<F> {
+    f: F,
+    decimal: bool,
+}
+
+impl<F> MapKeySerializer<F> {
+    fn decimal<T, R>(self, v: T) -> Result<R>
+    where
+        T: fmt::Display,
+        F: Fn(&[u8]) -> Result<R>,
+    {
+        if self.decimal {
+            (self.f)(v.to_string().as_bytes())
+        } else {
+            Err(Error::KeyMustBeByteString)
+        }
+    }
+}
+
+impl<F, R> ser::Serializer for MapKeySerializer<F>
+where
+    F: Fn(&[u8]) -> Result<R>,
+{
+    type Ok = R;
+    type Error = Error;
+
+    type SerializeSeq = Impossible<R, Error>;
+    type SerializeTuple = Impossible<R, Error>;
+    type SerializeTupleStruct = Impossible<R, Error>;
+    type SerializeTupleVariant = Impossible<R, Error>;
+    type SerializeMap = Impossible<R, Error>;
+    type SerializeStruct = Impossible<R, Error>;
+    type SerializeStructVariant = Impossible<R, Error>;
+
+    fn serialize_str(self, v: &str) -> Result<R> {
+        (self.f)(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<R> {
+        (self.f)(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<R> {
+        (self.f)(v.encode_utf8(&mut [0; 4]).as_bytes())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<R> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<R>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_bool(self, _v: bool) -> Result<R> {
+        Err(Error::KeyMustBeByteString)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<R> {
+        self.decimal(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<R> {
+        self.decimal(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<R> {
+        self.decimal(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<R> {
+        self.decimal(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<R> {
+        self.decimal(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<R> {
+        self.decimal(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<R> {
+        self.decimal(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<R> {
+        self.decimal(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<R> {
+        self.decimal(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<R> {
+        self.decimal(v)
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<R> {
+        Err(Error::KeyMustBeByteString)
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<R> {
+        Err(Error::KeyMustBeByteString)
+    }
+
+    fn serialize_none(self) -> Result<R> {
+        Err(Error::KeyMustBeByteString)
+    }
+
+    fn serialize_some<T>(self, _value: &T) -> Result<R>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(Error::KeyMustBeByteString)
+    }
+
+    fn serialize_unit(self) -> Result<R> {
+        Err(Error::KeyMustBeByteString)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<R> {
+        Err(Error::KeyMustBeByteString)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<R>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(Error::KeyMustBeByteString)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(Error::KeyMustBeByteString)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(Error::KeyMustBeByteString)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(Error::KeyMustBeByteString)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(Error::KeyMustBeByteString)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(Error::KeyMustBeByteString)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(Error::KeyMustBeByteString)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(Error::KeyMustBeByteString)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use super::{
+        to_bytes, to_bytes_in, to_bytes_with_options, BoolPolicy, Canonical, FloatPolicy,
+        KeyPolicy, NonePolicy, Serializer, SerializerOptions,
+    };
+    use crate::error::Error;
+    use serde::{Deserialize, Serialize};
+
+    #[test]
+    fn test_num() {
+        assert_eq!(b"i123e".to_vec(), to_bytes(&123i64).unwrap());
+        assert_eq!(b"i-123e".to_vec(), to_bytes(&-123i32).unwrap());
+        assert_eq!(b"i0e".to_vec(), to_bytes(&0u8).unwrap());
+    }
+
+    #[test]
+    fn test_byte_string() {
+        assert_eq!(b"5:hello".to_vec(), to_bytes("hello").unwrap());
+        assert_eq!(b"0:".to_vec(), to_bytes("").unwrap());
+    }
+
+    #[test]
+    fn test_list() {
+        assert_eq!(
+            b"l5:hello5:worlde".to_vec(),
+            to_bytes(&["hello", "world"]).unwrap()
+        );
+        assert_eq!(b"li10e5:Helloe".to_vec(), to_bytes(&(10, "Hello")).unwrap());
+    }
+
+    #[test]
+    fn test_struct_keys_sorted() {
+        #[derive(Serialize)]
+        struct Test {
+            b: i64,
+            a: &'static str,
+        }
+
+        let v = Test { b: 69, a: "Hello" };
+        assert_eq!(b"d1:a5:Hello1:bi69ee".to_vec(), to_bytes(&v).unwrap());
+    }
+
+    #[test]
+    fn test_map() {
+        let v = HashMap::from([("b", 20), ("a", 69)]);
+        assert_eq!(b"d1:ai69e1:bi20ee".to_vec(), to_bytes(&v).unwrap());
+
+        let v = BTreeMap::from([(1, 2)]);
+        assert_eq!(Err(Error::KeyMustBeByteString), to_bytes(&v));
+    }
+
+    #[test]
+    fn test_enum() {
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        enum Enum {
+            Unit,
+            Newtype(i32),
+            Tuple(i32, i32),
+            Struct { a: i32 },
+        }
+
+        assert_eq!(b"4:Unit".to_vec(), to_bytes(&Enum::Unit).unwrap());
+        assert_eq!(
+            b"d7:Newtypei-1ee".to_vec(),
+            to_bytes(&Enum::Newtype(-1)).unwrap()
+        );
+        assert_eq!(
+            b"d5:Tupleli1ei10eee".to_vec(),
+            to_bytes(&Enum::Tuple(1, 10)).unwrap()
+        );
+        assert_eq!(
+            b"d6:Structd1:ai1eee".to_vec(),
+            to_bytes(&Enum::Struct { a: 1 }).unwrap()
+        );
+
+        for e in [
+            Enum::Unit,
+            Enum::Newtype(-1),
+            Enum::Tuple(1, 10),
+            Enum::Struct { a: 1 },
+        ] {
+            let bytes = to_bytes(&e).unwrap();
+            assert_eq!(e, crate::from_bytes(&bytes).unwrap());
+            #[cfg(feature = "std")]
+            assert_eq!(e, crate::from_reader(&bytes[..]).unwrap());
+        }
+        let list: Vec<Enum> = crate::from_bytes(b"l4:Unitd7:Newtypei2eee").unwrap();
+        assert_eq!(vec![Enum::Unit, Enum::Newtype(2)], list);
+
+        for input in [
+            &b"d4:Unitdee"[..],
+            b"7:Newtype",
+            b"d7:Newtypei1e5:Tupleleee",
+            b"i1e",
+        ] {
+            assert!(crate::from_bytes::<Enum>(input).is_err());
+        }
+        assert_eq!(
+            "unknown variant `Other`, expected one of `Unit`, `Newtype`, `Tuple`, `Struct`",
+            crate::from_bytes::<Enum>(b"5:Other")
+                .unwrap_err()
+                .inner()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_bool_policy() {
+        assert_eq!(b"li1ei0ee".to_vec(), to_bytes(&[true, false]).unwrap());
+
+        let options = SerializerOptions::new().bool_policy(BoolPolicy::Error);
+        assert_eq!(
+            Err(Error::Unsupported("bool")),
+            to_bytes_with_options(&true, options)
+        );
+    }
+
+    #[test]
+    fn test_float_policy() {
+        assert_eq!(Err(Error::Unsupported("float")), to_bytes(&1.5f64));
+
+        let options = SerializerOptions::new().float_policy(FloatPolicy::String);
+        assert_eq!(
+            b"3:1.5".to_vec(),
+            to_bytes_with_options(&1.5f32, options).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_none_policy() {
+        #[derive(Serialize)]
+        struct Test {
+            a: Option<i64>,
+            b: Option<i64>,
+        }
+
+        let v = Test {
+            a: None,
+            b: Some(1),
+        };
+        assert_eq!(b"d1:bi1ee".to_vec(), to_bytes(&v).unwrap());
+
+        let options = SerializerOptions::new().none_policy(NonePolicy::Error);
+        assert_eq!(
+            Err(Error::Unsupported("none")),
+            to_bytes_with_options(&v, options)
+        );
+
+        // a list has no key to drop along with the value
+        let err = Err(Error::Unsupported("none in a list"));
+        assert_eq!(err, to_bytes(&(None::<i32>, 5)));
+        assert_eq!(err, to_bytes(&vec![Some(1), None, Some(3)]));
+        assert_eq!(err, to_bytes(&vec![()]));
+        assert_eq!(
+            b"li1ei3ee".to_vec(),
+            to_bytes(&vec![Some(1), Some(3)]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_canonical_lenient() {
+        #[derive(Serialize)]
+        struct Test {
+            b: i64,
+            a: i64,
+        }
+
+        let options = SerializerOptions::new().canonical(Canonical::Lenient);
+        let v = Test { b: 1, a: 2 };
+        assert_eq!(
+            b"d1:bi1e1:ai2ee".to_vec(),
+            to_bytes_with_options(&v, options).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_to_bytes_in() {
+        let mut buf = Vec::with_capacity(64);
+        for i in 0..3 {
+            buf.clear();
+            to_bytes_in(&[i], &mut buf).unwrap();
+            assert_eq!(format!("li{}ee", i).as_bytes(), buf.as_slice());
+        }
+
+        buf.clear();
+        to_bytes_in("a", &mut buf).unwrap();
+        assert!(to_bytes_in(&1.5f64, &mut buf).is_err());
+        assert_eq!(b"1:a", buf.as_slice());
+    }
+
+    #[test]
+    fn test_reuse_serializer() {
+        let mut buf = Vec::new();
+        let mut ser = Serializer::new(&mut buf);
+        "a".serialize(&mut ser).unwrap();
+        1i64.serialize(&mut ser).unwrap();
+        assert_eq!(b"1:ai1e", buf.as_slice());
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn test_to_bytes_mut() {
+        let mut buf = bytes::BytesMut::new();
+        super::to_bytes_mut(&("a", 1), &mut buf).unwrap();
+        assert!(super::to_bytes_mut(&1.5f64, &mut buf).is_err());
+        assert_eq!(b"l1:ai1ee", &buf.freeze()[..]);
+    }
+
+    #[test]
+    fn test_presize() {
+        #[derive(Serialize)]
+        struct Test {
+            pieces: Vec<u8>,
+            name: &'static str,
+        }
+
+        let v = Test {
+            pieces: vec![7; 1000],
+            name: "test",
+        };
+        let options = SerializerOptions::new().presize(true);
+        let b = to_bytes_with_options(&v, options).unwrap();
+        assert_eq!(to_bytes(&v).unwrap(), b);
+        assert_eq!(b.len(), b.capacity());
+    }
+
+    #[test]
+    fn test_key_policy() {
+        let v = BTreeMap::from([(2u32, "b"), (10u32, "a")]);
+        assert_eq!(Err(Error::KeyMustBeByteString), to_bytes(&v));
+
+        let options = SerializerOptions::new().key_policy(KeyPolicy::Decimal);
+        assert_eq!(
+            b"d2:101:a1:21:be".to_vec(),
+            to_bytes_with_options(&v, options).unwrap()
+        );
+
+        let options = SerializerOptions::new().key_policy(KeyPolicy::custom(|k| {
+            Ok(k.iter().filter(|b| b.is_ascii_digit()).copied().collect())
+        }));
+        let v = BTreeMap::from([((1, 2), "a")]);
+        assert_eq!(
+            b"d2:121:ae".to_vec(),
+            to_bytes_with_options(&v, options).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_canonical_strict() {
+        let options = SerializerOptions::new().canonical(Canonical::Strict);
+
+        let v = HashMap::from([("b", 20), ("a", 69)]);
+        assert_eq!(
+            b"d1:ai69e1:bi20ee".to_vec(),
+            to_bytes_with_options(&v, options.clone()).unwrap()
+        );
+
+        // turned down up front, even for values that don't need them
+        let float = options.clone().float_policy(FloatPolicy::String);
+        assert_eq!(
+            Err(Error::NonCanonical("float")),
+            to_bytes_with_options(&1.5f64, float.clone())
+        );
+        assert_eq!(
+            Err(Error::NonCanonical("float")),
+            to_bytes_with_options(&1, float)
+        );
+
+        let err = || Error::NonCanonical("converted dictionary key");
+        let decimal = options.clone().key_policy(KeyPolicy::Decimal);
+        assert_eq!(Err(err()), to_bytes_with_options(&v, decimal.clone()));
+        assert_eq!(Err(err()), crate::serialized_len_with_options(&v, decimal));
+        let custom = options.key_policy(KeyPolicy::custom(|k| Ok(k.to_vec())));
+        assert!(Serializer::with_options(Vec::new(), custom).is_err());
+
+        let lenient = SerializerOptions::new().key_policy(KeyPolicy::Decimal);
+        assert!(Serializer::with_options(Vec::new(), lenient).is_ok());
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let v = vec![("b", 1), ("a", 2), ("b", 3)]
+            .into_iter()
+            .collect::<Pairs>();
+        assert_eq!(Err(Error::DuplicateKey("b".to_string())), to_bytes(&v));
+
+        for canonical in [Canonical::Lenient, Canonical::Strict] {
+            let options = SerializerOptions::new().canonical(canonical);
+            assert_eq!(
+                Err(Error::DuplicateKey("b".to_string())),
+                to_bytes_with_options(&v, options)
+            );
+        }
+
+        #[derive(Serialize)]
+        struct Renamed {
+            #[serde(rename = "name")]
+            a: i64,
+            name: i64,
+        }
+        assert_eq!(
+            Err(Error::DuplicateKey("name".to_string())),
+            to_bytes(&Renamed { a: 1, name: 2 })
+        );
+
+        let options = SerializerOptions::new().key_policy(KeyPolicy::Decimal);
+        let v = HashMap::from([(Key::Int(1), 1), (Key::Str("1"), 2)]);
+        assert_eq!(
+            Err(Error::DuplicateKey("1".to_string())),
+            to_bytes_with_options(&v, options)
+        );
+    }
+
+    #[derive(Serialize, PartialEq, Eq, Hash)]
+    #[serde(untagged)]
+    enum Key {
+        Int(i64),
+        Str(&'static str),
+    }
+
+    // a map that can hold the same key twice
+    struct Pairs(Vec<(&'static str, i64)>);
+
+    impl FromIterator<(&'static str, i64)> for Pairs {
+        fn from_iter<I: IntoIterator<Item = (&'static str, i64)>>(iter: I) -> Self {
+            Pairs(iter.into_iter().collect())
+        }
+    }
+
+    impl Serialize for Pairs {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_map(self.0.iter().map(|(k, v)| (k, v)))
+        }
+    }
+}
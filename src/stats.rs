@@ -0,0 +1,109 @@
+use crate::error::Result;
+use crate::events::{parse_events, Handler};
+
+/// Counts of what a document contains. Dictionary keys are counted in
+/// `keys` only, not as byte strings.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub ints: usize,
+    pub byte_strings: usize,
+    pub lists: usize,
+    pub dicts: usize,
+    pub keys: usize,
+    /// How deeply lists and dictionaries nest; 0 for a scalar.
+    pub max_depth: usize,
+    /// The length of the longest byte string, keys included.
+    pub largest_byte_string: usize,
+    /// The total length of all byte strings, keys included.
+    pub byte_string_bytes: usize,
+}
+
+/// Gathers `Stats` for `input` in a single pass, without building values.
+pub fn stats(input: &[u8]) -> Result<Stats> {
+    let mut counter = Counter::default();
+    parse_events(input, &mut counter)?;
+    Ok(counter.stats)
+}
+
+#[derive(Default)]
+struct Counter {
+    stats: Stats,
+    depth: usize,
+}
+
+impl Counter {
+    fn bytes(&mut self, len: usize) {
+        self.stats.largest_byte_string = self.stats.largest_byte_string.max(len);
+        self.stats.byte_string_bytes += len;
+    }
+
+    fn open(&mut self) {
+        self.depth += 1;
+        self.stats.max_depth = self.stats.max_depth.max(self.depth);
+    }
+}
+
+impl Handler for Counter {
+    fn on_int(&mut self, _value: i64) -> Result<()> {
+        self.stats.ints += 1;
+        Ok(())
+    }
+
+    fn on_bytes(&mut self, value: &[u8]) -> Result<()> {
+        self.stats.byte_strings += 1;
+        self.bytes(value.len());
+        Ok(())
+    }
+
+    fn on_list_start(&mut self) -> Result<()> {
+        self.stats.lists += 1;
+        self.open();
+        Ok(())
+    }
+
+    fn on_list_end(&mut self) -> Result<()> {
+        self.depth -= 1;
+        Ok(())
+    }
+
+    fn on_dict_start(&mut self) -> Result<()> {
+        self.stats.dicts += 1;
+        self.open();
+        Ok(())
+    }
+
+    fn on_dict_key(&mut self, key: &[u8]) -> Result<()> {
+        self.stats.keys += 1;
+        self.bytes(key.len());
+        Ok(())
+    }
+
+    fn on_dict_end(&mut self) -> Result<()> {
+        self.depth -= 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{stats, Stats};
+
+    #[test]
+    fn test_stats() {
+        assert_eq!(
+            Ok(Stats {
+                ints: 2,
+                byte_strings: 2,
+                lists: 2,
+                dicts: 2,
+                keys: 3,
+                max_depth: 3,
+                largest_byte_string: 5,
+                byte_string_bytes: 9,
+            }),
+            stats(b"d1:ali1eli2e5:helloee1:bde1:c1:xe")
+        );
+        assert_eq!(0, stats(b"i1e").unwrap().max_depth);
+        assert!(stats(b"d1:ai1e").is_err());
+    }
+}
@@ -0,0 +1,159 @@
+//! `#[serde(with = ...)]` helpers for timestamps stored as integer seconds
+//! since the Unix epoch, like a torrent's `creation date`. Each has an
+//! `option` submodule for fields that may be missing.
+
+/// Maps timestamps to `chrono::DateTime<Utc>`.
+#[cfg(feature = "chrono")]
+pub mod chrono {
+    use ::chrono::{DateTime, Utc};
+    use serde::de::{Error as _, Unexpected};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(value: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_i64(value.timestamp())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let secs = i64::deserialize(deserializer)?;
+        DateTime::from_timestamp(secs, 0)
+            .ok_or_else(|| D::Error::invalid_value(Unexpected::Signed(secs), &"a Unix timestamp"))
+    }
+
+    pub mod option {
+        use ::chrono::{DateTime, Utc};
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        pub fn serialize<S>(value: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            value.map(|value| value.timestamp()).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            #[derive(Deserialize)]
+            struct Wrapper(#[serde(with = "super")] DateTime<Utc>);
+
+            Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|w| w.0))
+        }
+    }
+}
+
+/// Maps timestamps to `time::OffsetDateTime`, in UTC.
+#[cfg(feature = "time")]
+pub mod time {
+    use ::time::OffsetDateTime;
+    use serde::de::{Error as _, Unexpected};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(value: &OffsetDateTime, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_i64(value.unix_timestamp())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<OffsetDateTime, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let secs = i64::deserialize(deserializer)?;
+        OffsetDateTime::from_unix_timestamp(secs)
+            .ok()
+            .ok_or_else(|| D::Error::invalid_value(Unexpected::Signed(secs), &"a Unix timestamp"))
+    }
+
+    pub mod option {
+        use ::time::OffsetDateTime;
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        pub fn serialize<S>(
+            value: &Option<OffsetDateTime>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            value
+                .map(|value| value.unix_timestamp())
+                .serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<OffsetDateTime>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            #[derive(Deserialize)]
+            struct Wrapper(#[serde(with = "super")] OffsetDateTime);
+
+            Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|w| w.0))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_bytes, to_bytes};
+    use serde::{Deserialize, Serialize};
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono() {
+        use chrono::{DateTime, Utc};
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Torrent {
+            #[serde(rename = "creation date", with = "super::chrono")]
+            creation_date: DateTime<Utc>,
+            #[serde(default, with = "super::chrono::option")]
+            updated: Option<DateTime<Utc>>,
+        }
+
+        let input = b"d13:creation datei1662813552ee";
+        let t: Torrent = from_bytes(input).unwrap();
+        assert_eq!(
+            DateTime::from_timestamp(1662813552, 0),
+            Some(t.creation_date)
+        );
+        assert_eq!(None, t.updated);
+        assert_eq!(input.to_vec(), to_bytes(&t).unwrap());
+
+        let input = b"d13:creation datei0e7:updatedi1ee";
+        let t: Torrent = from_bytes(input).unwrap();
+        assert_eq!(Some(1), t.updated.map(|u| u.timestamp()));
+        assert_eq!(input.to_vec(), to_bytes(&t).unwrap());
+
+        assert!(from_bytes::<Torrent>(b"d13:creation datei9223372036854775807ee").is_err());
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_time() {
+        use time::OffsetDateTime;
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Torrent {
+            #[serde(rename = "creation date", with = "super::time")]
+            creation_date: OffsetDateTime,
+            #[serde(default, with = "super::time::option")]
+            updated: Option<OffsetDateTime>,
+        }
+
+        let input = b"d13:creation datei1662813552e7:updatedi-1ee";
+        let t: Torrent = from_bytes(input).unwrap();
+        assert_eq!(2022, t.creation_date.year());
+        assert_eq!(Some(-1), t.updated.map(|u| u.unix_timestamp()));
+        assert_eq!(input.to_vec(), to_bytes(&t).unwrap());
+
+        assert!(from_bytes::<Torrent>(b"d13:creation datei9223372036854775807ee").is_err());
+    }
+}
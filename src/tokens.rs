@@ -0,0 +1,126 @@
+use core::ops::Range;
+
+use crate::de::Deserializer;
+use crate::error::{Error, Result};
+use crate::read::{Reference, SliceRead};
+
+/// One lexical token of bencode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Token<'a> {
+    Int(i64),
+    Bytes(&'a [u8]),
+    ListStart,
+    DictStart,
+    /// Closes the innermost list or dictionary.
+    End,
+}
+
+/// An iterator over the tokens of a single value, each with the range of
+/// input bytes it came from.
+///
+/// Lists and dictionaries are checked for balance, but nothing more: keys
+/// aren't required to be byte strings. The iterator stops after the first
+/// error.
+pub struct Tokens<'a> {
+    de: Deserializer<SliceRead<'a>>,
+    depth: usize,
+    done: bool,
+}
+
+impl<'a> Tokens<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        Tokens {
+            de: Deserializer::from_bytes(input),
+            depth: 0,
+            done: false,
+        }
+    }
+
+    /// How many bytes have been consumed so far.
+    pub fn offset(&self) -> usize {
+        self.de.offset()
+    }
+
+    fn next_token(&mut self) -> Result<Token<'a>> {
+        let token = match self.de.peek_byte()? {
+            b'i' => Token::Int(self.de.parse_num()?),
+            b'0'..=b'9' => match self.de.parse_byte_array()? {
+                Reference::Borrowed(b) => Token::Bytes(b),
+                // slices are never copied into scratch
+                Reference::Copied(_) => unreachable!(),
+            },
+            b'l' | b'd' => {
+                self.depth += 1;
+                match self.de.next_byte()? {
+                    b'l' => Token::ListStart,
+                    _ => Token::DictStart,
+                }
+            }
+            b'e' if self.depth > 0 => {
+                self.de.next_byte()?;
+                self.depth -= 1;
+                Token::End
+            }
+            _ => return Err(Error::Syntax),
+        };
+        if self.depth == 0 {
+            self.de.end()?;
+        }
+        Ok(token)
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Result<(Token<'a>, Range<usize>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let start = self.offset();
+        let token = self.next_token();
+        self.done = token.is_err() || self.depth == 0;
+        Some(token.map(|t| (t, start..self.offset())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Token, Tokens};
+    use crate::error::Error;
+
+    #[test]
+    fn test_tokens() {
+        let tokens: Vec<_> = Tokens::new(b"d1:ali-2eee")
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            vec![
+                (Token::DictStart, 0..1),
+                (Token::Bytes(b"a"), 1..4),
+                (Token::ListStart, 4..5),
+                (Token::Int(-2), 5..9),
+                (Token::End, 9..10),
+                (Token::End, 10..11),
+            ],
+            tokens
+        );
+        assert_eq!(
+            vec![Ok((Token::Bytes(b"xy"), 0..4))],
+            Tokens::new(b"2:xy").collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_tokens_errors() {
+        let mut tokens = Tokens::new(b"li1e");
+        assert_eq!(Some(Ok((Token::ListStart, 0..1))), tokens.next());
+        assert_eq!(Some(Ok((Token::Int(1), 1..4))), tokens.next());
+        assert_eq!(Some(Err(Error::Eof)), tokens.next());
+        assert_eq!(None, tokens.next());
+
+        let last = Tokens::new(b"i1ei2e").last();
+        assert_eq!(Some(Err(Error::TrailingCharacters)), last);
+        assert_eq!(Some(Err(Error::Syntax)), Tokens::new(b"e").next());
+    }
+}
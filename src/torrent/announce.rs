@@ -0,0 +1,107 @@
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, SerializeSeq, Serializer};
+use url::Url;
+
+/// An `announce-list` (BEP 12): tiers of trackers, tried in order. Each
+/// URL is validated and normalized when deserialized.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AnnounceList(pub Vec<Vec<Url>>);
+
+impl AnnounceList {
+    /// Parses every URL in `tiers`, the way deserializing does.
+    pub fn parse<S: AsRef<str>>(tiers: &[Vec<S>]) -> crate::Result<AnnounceList> {
+        let tiers = tiers
+            .iter()
+            .map(|tier| {
+                tier.iter()
+                    .map(|url| {
+                        let url = url.as_ref();
+                        Url::parse(url).map_err(|e| {
+                            crate::Error::Message(format!("invalid tracker URL {:?}: {}", url, e))
+                        })
+                    })
+                    .collect()
+            })
+            .collect::<crate::Result<_>>()?;
+        Ok(AnnounceList(tiers))
+    }
+
+    pub fn tiers(&self) -> &[Vec<Url>] {
+        &self.0
+    }
+
+    /// Every tracker in tier order, keeping only the first of duplicates.
+    pub fn trackers(&self) -> Vec<&Url> {
+        let mut trackers = Vec::new();
+        for url in self.0.iter().flatten() {
+            if !trackers.contains(&url) {
+                trackers.push(url);
+            }
+        }
+        trackers
+    }
+}
+
+impl Serialize for AnnounceList {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        for tier in &self.0 {
+            let tier: Vec<&str> = tier.iter().map(Url::as_str).collect();
+            seq.serialize_element(&tier)?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for AnnounceList {
+    fn deserialize<D>(deserializer: D) -> Result<AnnounceList, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let tiers = Vec::<Vec<String>>::deserialize(deserializer)?;
+        AnnounceList::parse(&tiers).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AnnounceList;
+    use crate::torrent::Torrent;
+    use crate::{from_bytes, to_bytes};
+
+    #[test]
+    fn test_announce_list() {
+        let input = b"ll17:HTTP://A.example/18:udp://b.example:80el17:http://a.example/ee";
+        let list: AnnounceList = from_bytes(input).unwrap();
+        assert_eq!(2, list.tiers().len());
+        let trackers: Vec<_> = list.trackers().iter().map(|u| u.as_str()).collect();
+        assert_eq!(vec!["http://a.example/", "udp://b.example:80"], trackers);
+        assert_eq!(
+            b"ll17:http://a.example/18:udp://b.example:80el17:http://a.example/ee".to_vec(),
+            to_bytes(&list).unwrap()
+        );
+
+        let err = from_bytes::<AnnounceList>(b"ll9:not a urlee").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("invalid tracker URL \"not a url\""));
+        assert!(AnnounceList::parse(&[vec!["not a url"]]).is_err());
+    }
+
+    #[test]
+    fn test_torrent_announce_list() {
+        let input = b"d13:announce-listll17:HTTP://A.example/ee4:infod6:lengthi0e\
+            4:name0:12:piece lengthi1e6:pieces0:ee";
+        let torrent: Torrent = from_bytes(input).unwrap();
+        let list = torrent.announce_list.as_ref().unwrap();
+        assert_eq!("http://a.example/", list.tiers()[0][0].as_str());
+        assert_eq!(vec!["http://a.example/"], torrent.trackers());
+
+        let invalid = b"d13:announce-listll9:not a urlee4:infod6:lengthi0e\
+            4:name0:12:piece lengthi1e6:pieces0:ee";
+        assert!(from_bytes::<Torrent>(invalid).is_err());
+    }
+}
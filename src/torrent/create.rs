@@ -0,0 +1,445 @@
+use sha1::{Digest, Sha1};
+use std::fs;
+use std::io::Read as _;
+use std::path::{Path, PathBuf};
+
+use super::{File, Info, Layout, Torrent};
+use crate::error::{Error, Result};
+
+/// Creates a torrent for a file or a directory, hashing its contents.
+///
+/// ```no_run
+/// # use serde_bencode::torrent::TorrentBuilder;
+/// let torrent = TorrentBuilder::new("dataset")
+///     .announce("http://tracker.example/announce")
+///     .piece_length(1 << 20)
+///     .build()?;
+/// std::fs::write("dataset.torrent", serde_bencode::to_bytes(&torrent)?)?;
+/// # Ok::<(), serde_bencode::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct TorrentBuilder {
+    path: PathBuf,
+    name: Option<String>,
+    piece_length: u64,
+    announce: Option<String>,
+    announce_list: Option<Vec<Vec<String>>>,
+    comment: Option<String>,
+    created_by: Option<String>,
+    creation_date: Option<i64>,
+    private: bool,
+    url_list: Vec<String>,
+}
+
+impl TorrentBuilder {
+    /// Pieces are 256 KiB unless set with `piece_length`.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        TorrentBuilder {
+            path: path.into(),
+            name: None,
+            piece_length: 1 << 18,
+            announce: None,
+            announce_list: None,
+            comment: None,
+            created_by: None,
+            creation_date: None,
+            private: false,
+            url_list: Vec::new(),
+        }
+    }
+
+    /// Defaults to the last component of the path.
+    pub fn name<S: Into<String>>(mut self, name: S) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Must be a power of two, and at least 16 KiB.
+    pub fn piece_length(mut self, piece_length: u64) -> Self {
+        self.piece_length = piece_length;
+        self
+    }
+
+    pub fn announce<S: Into<String>>(mut self, url: S) -> Self {
+        self.announce = Some(url.into());
+        self
+    }
+
+    /// With the `url` feature, `build` fails on a tracker that isn't a
+    /// valid URL.
+    pub fn announce_list(mut self, tiers: Vec<Vec<String>>) -> Self {
+        self.announce_list = Some(tiers);
+        self
+    }
+
+    pub fn comment<S: Into<String>>(mut self, comment: S) -> Self {
+        self.comment = Some(comment.into());
+        self
+    }
+
+    pub fn created_by<S: Into<String>>(mut self, created_by: S) -> Self {
+        self.created_by = Some(created_by.into());
+        self
+    }
+
+    /// Seconds since the Unix epoch
+    pub fn creation_date(mut self, creation_date: i64) -> Self {
+        self.creation_date = Some(creation_date);
+        self
+    }
+
+    pub fn private(mut self, private: bool) -> Self {
+        self.private = private;
+        self
+    }
+
+    pub fn url_list(mut self, urls: Vec<String>) -> Self {
+        self.url_list = urls;
+        self
+    }
+
+    /// Reads every file and hashes it into pieces. Files in a directory are
+    /// taken in order of their paths, which is the order they appear in.
+    ///
+    /// Symlinks in a directory aren't followed. One that points to something
+    /// else in the directory is listed as a symlink (BEP 47), with no
+    /// contents of its own; any other is left out.
+    pub fn build(&self) -> Result<Torrent> {
+        if !self.piece_length.is_power_of_two() || self.piece_length < 1 << 14 {
+            return Err(Error::Message(format!(
+                "piece length {} is not a power of two of at least 16 KiB",
+                self.piece_length
+            )));
+        }
+        let name = match &self.name {
+            Some(name) => name.clone(),
+            None => utf8(self.path.file_name().unwrap_or(self.path.as_os_str()))?.to_string(),
+        };
+
+        let mut paths = Vec::new();
+        let layout = if fs::metadata(&self.path)?.is_dir() {
+            let mut walker = Walker {
+                root: fs::canonicalize(&self.path)?,
+                prefix: Vec::new(),
+                files: Vec::new(),
+                paths: &mut paths,
+            };
+            walker.walk(&self.path)?;
+            if walker.files.is_empty() {
+                return Err(Error::Message(format!(
+                    "{} has no files",
+                    self.path.display()
+                )));
+            }
+            Layout::Multi {
+                files: walker.files,
+            }
+        } else {
+            paths.push(self.path.clone());
+            Layout::Single {
+                length: fs::metadata(&self.path)?.len(),
+            }
+        };
+
+        Ok(Torrent {
+            announce: self.announce.clone(),
+            #[cfg(not(feature = "url"))]
+            announce_list: self.announce_list.clone(),
+            #[cfg(feature = "url")]
+            announce_list: self
+                .announce_list
+                .as_deref()
+                .map(super::AnnounceList::parse)
+                .transpose()?,
+            comment: self.comment.clone(),
+            created_by: self.created_by.clone(),
+            creation_date: self.creation_date,
+            encoding: None,
+            info: Info {
+                name,
+                piece_length: self.piece_length,
+                pieces: hash_pieces(&paths, self.piece_length)?,
+                layout,
+                private: self.private,
+            },
+            url_list: self.url_list.clone(),
+        })
+    }
+}
+
+fn utf8(s: &std::ffi::OsStr) -> Result<&str> {
+    s.to_str()
+        .ok_or_else(|| Error::Message(format!("{:?} is not valid UTF-8", s)))
+}
+
+// Lists every file under a directory, sorted by path, along with where to
+// read the ones that have contents.
+struct Walker<'a> {
+    // the directory itself, with symlinks resolved
+    root: PathBuf,
+    // path components of the directory being walked, below `root`
+    prefix: Vec<String>,
+    files: Vec<File>,
+    paths: &'a mut Vec<PathBuf>,
+}
+
+impl Walker<'_> {
+    fn walk(&mut self, dir: &Path) -> Result<()> {
+        let mut entries = fs::read_dir(dir)?.collect::<std::io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|e| e.file_name());
+        for entry in entries {
+            self.prefix.push(utf8(&entry.file_name())?.to_string());
+            let metadata = fs::symlink_metadata(entry.path())?;
+            if metadata.is_symlink() {
+                if let Some(target) = self.link_target(&entry.path())? {
+                    self.files.push(File {
+                        path: self.prefix.clone(),
+                        attr: Some("l".to_string()),
+                        symlink_path: Some(target),
+                        ..File::default()
+                    });
+                }
+            } else if metadata.is_dir() {
+                self.walk(&entry.path())?;
+            } else {
+                self.files.push(File {
+                    length: metadata.len(),
+                    path: self.prefix.clone(),
+                    ..File::default()
+                });
+                self.paths.push(entry.path());
+            }
+            self.prefix.pop();
+        }
+        Ok(())
+    }
+
+    // The path components of what `link` points to below the root, or `None`
+    // for a link that's broken, loops or leads out of the root.
+    fn link_target(&self, link: &Path) -> Result<Option<Vec<String>>> {
+        let Ok(target) = fs::canonicalize(link) else {
+            return Ok(None);
+        };
+        let Ok(relative) = target.strip_prefix(&self.root) else {
+            return Ok(None);
+        };
+        let components = relative
+            .iter()
+            .map(|c| utf8(c).map(str::to_string))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(if components.is_empty() {
+            None
+        } else {
+            Some(components)
+        })
+    }
+}
+
+// Reads the files as one stream, a piece at a time, so pieces can span files.
+struct PieceReader<'a> {
+    paths: std::slice::Iter<'a, PathBuf>,
+    file: Option<fs::File>,
+    piece_length: usize,
+}
+
+impl<'a> PieceReader<'a> {
+    fn new(paths: &'a [PathBuf], piece_length: u64) -> Self {
+        PieceReader {
+            paths: paths.iter(),
+            file: None,
+            piece_length: piece_length as usize,
+        }
+    }
+
+    // false once there's nothing left; only the last piece can be short
+    fn read_piece(&mut self, piece: &mut Vec<u8>) -> Result<bool> {
+        piece.clear();
+        while piece.len() < self.piece_length {
+            let file = match &mut self.file {
+                Some(file) => file,
+                None => match self.paths.next() {
+                    Some(path) => self.file.insert(fs::File::open(path)?),
+                    None => break,
+                },
+            };
+            let want = self.piece_length - piece.len();
+            if file.take(want as u64).read_to_end(piece)? < want {
+                self.file = None;
+            }
+        }
+        Ok(!piece.is_empty())
+    }
+}
+
+#[cfg(not(feature = "rayon"))]
+fn hash_pieces(paths: &[PathBuf], piece_length: u64) -> Result<Vec<[u8; 20]>> {
+    let mut reader = PieceReader::new(paths, piece_length);
+    let mut piece = Vec::with_capacity(piece_length as usize);
+    let mut pieces = Vec::new();
+    while reader.read_piece(&mut piece)? {
+        pieces.push(Sha1::digest(&piece).into());
+    }
+    Ok(pieces)
+}
+
+// Reads a batch of pieces while the one before it is hashed across the
+// thread pool, holding two batches of one piece per thread in memory.
+#[cfg(feature = "rayon")]
+fn hash_pieces(paths: &[PathBuf], piece_length: u64) -> Result<Vec<[u8; 20]>> {
+    use rayon::prelude::*;
+
+    let batch = rayon::current_num_threads();
+    let mut reader = PieceReader::new(paths, piece_length);
+    let mut read_batch = |mut buffers: Vec<Vec<u8>>| -> Result<Vec<Vec<u8>>> {
+        buffers.resize_with(batch, || Vec::with_capacity(piece_length as usize));
+        let mut filled = 0;
+        while filled < batch && reader.read_piece(&mut buffers[filled])? {
+            filled += 1;
+        }
+        buffers.truncate(filled);
+        Ok(buffers)
+    };
+
+    let mut pieces = Vec::new();
+    let mut current = read_batch(Vec::new())?;
+    let mut spare = Vec::new();
+    while !current.is_empty() {
+        let (next, hashes) = rayon::join(
+            || read_batch(std::mem::take(&mut spare)),
+            || {
+                current
+                    .par_iter()
+                    .map(|piece| <[u8; 20]>::from(Sha1::digest(piece)))
+                    .collect::<Vec<_>>()
+            },
+        );
+        pieces.extend(hashes);
+        spare = std::mem::replace(&mut current, next?);
+    }
+    Ok(pieces)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hash_pieces, TorrentBuilder};
+    use crate::torrent::{Layout, Torrent};
+    use crate::{from_bytes, to_bytes};
+    use sha1::{Digest, Sha1};
+    use std::fs;
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", ignore = "no filesystem")]
+    fn test_build() {
+        let dir = std::env::temp_dir().join(format!("serde-bencode-create-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        let a = vec![1; 20000];
+        let b = vec![2; 30000];
+        fs::write(dir.join("sub/b"), &b).unwrap();
+        fs::write(dir.join("a"), &a).unwrap();
+
+        let torrent = TorrentBuilder::new(&dir)
+            .name("data")
+            .piece_length(1 << 14)
+            .announce("http://tracker.example/announce")
+            .private(true)
+            .build()
+            .unwrap();
+        let content = [a.clone(), b].concat();
+        let pieces: Vec<[u8; 20]> = content
+            .chunks(1 << 14)
+            .map(|piece| Sha1::digest(piece).into())
+            .collect();
+        assert_eq!(4, pieces.len());
+        assert_eq!(pieces, torrent.info.pieces);
+        let Layout::Multi { files } = &torrent.info.layout else {
+            panic!("{:?}", torrent.info.layout);
+        };
+        assert_eq!(vec!["a"], files[0].path);
+        assert_eq!(vec!["sub", "b"], files[1].path);
+        assert_eq!(50000, torrent.info.layout.total_length());
+
+        let bytes = to_bytes(&torrent).unwrap();
+        assert!(crate::is_canonical(&bytes));
+        assert_eq!(torrent, from_bytes::<Torrent>(&bytes).unwrap());
+
+        let single = TorrentBuilder::new(dir.join("a")).build().unwrap();
+        assert_eq!("a", single.info.name);
+        assert_eq!(Layout::Single { length: 20000 }, single.info.layout);
+        assert_eq!(vec![<[u8; 20]>::from(Sha1::digest(&a))], single.info.pieces);
+
+        assert!(TorrentBuilder::new(&dir)
+            .piece_length(1000)
+            .build()
+            .is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_build_symlinks() {
+        use crate::torrent::File;
+        use std::os::unix::fs::symlink;
+
+        let dir = std::env::temp_dir().join(format!("serde-bencode-links-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        let a = vec![1; 20000];
+        fs::write(dir.join("a"), &a).unwrap();
+        symlink("a", dir.join("link")).unwrap();
+        // a loop, a link back up to the root and one leading out of it
+        symlink("loop", dir.join("loop")).unwrap();
+        symlink("..", dir.join("sub/up")).unwrap();
+        symlink(std::env::temp_dir(), dir.join("out")).unwrap();
+
+        let torrent = TorrentBuilder::new(&dir)
+            .piece_length(1 << 14)
+            .build()
+            .unwrap();
+        let Layout::Multi { files } = &torrent.info.layout else {
+            panic!("{:?}", torrent.info.layout);
+        };
+        let link = File {
+            path: vec!["link".to_string()],
+            attr: Some("l".to_string()),
+            symlink_path: Some(vec!["a".to_string()]),
+            ..File::default()
+        };
+        assert_eq!(2, files.len());
+        assert_eq!(vec!["a"], files[0].path);
+        assert_eq!(link, files[1]);
+        // the link adds nothing to the pieces
+        let pieces: Vec<[u8; 20]> = a
+            .chunks(1 << 14)
+            .map(|piece| Sha1::digest(piece).into())
+            .collect();
+        assert_eq!(pieces, torrent.info.pieces);
+
+        let bytes = to_bytes(&torrent).unwrap();
+        assert_eq!(torrent, from_bytes::<Torrent>(&bytes).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", ignore = "no filesystem")]
+    fn test_hash_pieces() {
+        let dir = std::env::temp_dir().join(format!("serde-bencode-pieces-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let contents: Vec<Vec<u8>> = (0..5u8).map(|i| vec![i; 37 * i as usize]).collect();
+        let paths: Vec<_> = (0..5).map(|i| dir.join(i.to_string())).collect();
+        for (path, content) in paths.iter().zip(&contents) {
+            fs::write(path, content).unwrap();
+        }
+
+        let expected: Vec<[u8; 20]> = contents
+            .concat()
+            .chunks(16)
+            .map(|piece| Sha1::digest(piece).into())
+            .collect();
+        assert_eq!(24, expected.len());
+        assert_eq!(expected, hash_pieces(&paths, 16).unwrap());
+        assert!(hash_pieces(&paths[..1], 16).unwrap().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,121 @@
+//! The extension protocol handshake (BEP 10), sent as the payload of
+//! extended message 0.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Handshake {
+    /// The message id the sender wants to receive each extension on, keyed
+    /// by extension name. An id of 0 turns an extension off.
+    #[serde(default)]
+    pub m: BTreeMap<String, u8>,
+    /// The sender's listen port
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub p: Option<u16>,
+    /// The sender's client name and version
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub v: Option<String>,
+    /// The receiver's address as the sender sees it
+    #[serde(default, with = "yourip", skip_serializing_if = "Option::is_none")]
+    pub yourip: Option<IpAddr>,
+    /// How many outstanding requests the sender allows
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reqq: Option<u64>,
+    /// The size of the info dictionary, for `ut_metadata` (BEP 9)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata_size: Option<u64>,
+}
+
+impl Handshake {
+    /// The id to send `extension` messages with, if the peer supports it.
+    pub fn message_id(&self, extension: &str) -> Option<u8> {
+        self.m.get(extension).copied().filter(|&id| id != 0)
+    }
+}
+
+// 4 bytes for IPv4, 16 for IPv6
+mod yourip {
+    use serde::de::{self, Deserializer, Visitor};
+    use serde::Serializer;
+    use std::fmt;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    pub fn serialize<S>(ip: &Option<IpAddr>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match ip {
+            Some(IpAddr::V4(ip)) => serializer.serialize_bytes(&ip.octets()),
+            Some(IpAddr::V6(ip)) => serializer.serialize_bytes(&ip.octets()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<IpAddr>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(IpVisitor).map(Some)
+    }
+
+    struct IpVisitor;
+
+    impl<'de> Visitor<'de> for IpVisitor {
+        type Value = IpAddr;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a 4- or 16-byte address")
+        }
+
+        fn visit_bytes<E>(self, v: &[u8]) -> Result<IpAddr, E>
+        where
+            E: de::Error,
+        {
+            if let Ok(ip) = <[u8; 4]>::try_from(v) {
+                Ok(Ipv4Addr::from(ip).into())
+            } else if let Ok(ip) = <[u8; 16]>::try_from(v) {
+                Ok(Ipv6Addr::from(ip).into())
+            } else {
+                Err(E::invalid_length(v.len(), &self))
+            }
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<IpAddr, E>
+        where
+            E: de::Error,
+        {
+            self.visit_bytes(v.as_bytes())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Handshake;
+    use crate::{from_bytes, to_bytes};
+
+    #[test]
+    fn test_handshake() {
+        let input = b"d1:md11:ut_metadatai3e6:ut_pexi0ee1:pi6881e4:reqqi250e\
+            1:v13:Transmission 6:yourip4:\x0a\x00\x00\x0113:metadata_sizei31235ee";
+        let handshake: Handshake = from_bytes(input).unwrap();
+        assert_eq!(Some(3), handshake.message_id("ut_metadata"));
+        assert_eq!(None, handshake.message_id("ut_pex"));
+        assert_eq!(None, handshake.message_id("lt_donthave"));
+        assert_eq!(Some(6881), handshake.p);
+        assert_eq!(Some(250), handshake.reqq);
+        assert_eq!(Some(31235), handshake.metadata_size);
+        assert_eq!(Some("10.0.0.1".parse().unwrap()), handshake.yourip);
+
+        let canonical = to_bytes(&handshake).unwrap();
+        assert_eq!(handshake, from_bytes(&canonical).unwrap());
+
+        let handshake: Handshake = from_bytes(b"de").unwrap();
+        assert_eq!(Handshake::default(), handshake);
+        assert_eq!(b"d1:mdee".to_vec(), to_bytes(&handshake).unwrap());
+
+        assert!(from_bytes::<Handshake>(b"d6:yourip3:abce").is_err());
+    }
+}
@@ -0,0 +1,74 @@
+use std::fmt::Write as _;
+
+use crate::error::{Error, Result};
+
+/// Builds a magnet link (BEP 9). A 20-byte `info_hash` is a v1 infohash and
+/// is written as `urn:btih`; a 32-byte one is a v2 infohash and is written
+/// as `urn:btmh`, the SHA-256 multihash.
+///
+/// ```
+/// # use serde_bencode::torrent::magnet_uri;
+/// let uri = magnet_uri(&[0xab; 20], Some("a b"), &["udp://t.example:80"]).unwrap();
+/// assert_eq!(
+///     "magnet:?xt=urn:btih:abababababababababababababababababababab&dn=a%20b\
+///      &tr=udp%3A%2F%2Ft.example%3A80",
+///     uri
+/// );
+/// ```
+pub fn magnet_uri(info_hash: &[u8], name: Option<&str>, trackers: &[&str]) -> Result<String> {
+    let mut uri = String::from("magnet:?xt=");
+    match info_hash.len() {
+        20 => uri.push_str("urn:btih:"),
+        32 => uri.push_str("urn:btmh:1220"),
+        n => {
+            return Err(Error::Message(format!(
+                "an infohash is 20 or 32 bytes, not {}",
+                n
+            )))
+        }
+    }
+    for b in info_hash {
+        write!(uri, "{:02x}", b).unwrap();
+    }
+    if let Some(name) = name {
+        uri.push_str("&dn=");
+        percent_encode(name, &mut uri);
+    }
+    for tracker in trackers {
+        uri.push_str("&tr=");
+        percent_encode(tracker, &mut uri);
+    }
+    Ok(uri)
+}
+
+// everything but the unreserved characters of RFC 3986
+pub(crate) fn percent_encode(s: &str, out: &mut String) {
+    for &b in s.as_bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            out.push(b as char);
+        } else {
+            write!(out, "%{:02X}", b).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::magnet_uri;
+
+    #[test]
+    fn test_magnet_uri() {
+        assert_eq!(
+            Ok(format!("magnet:?xt=urn:btmh:1220{}", "00".repeat(32))),
+            magnet_uri(&[0; 32], None, &[])
+        );
+        assert_eq!(
+            Ok(format!(
+                "magnet:?xt=urn:btih:{}&dn=%C3%A9t%C3%A9&tr=a&tr=b",
+                "01".repeat(20)
+            )),
+            magnet_uri(&[1; 20], Some("été"), &["a", "b"])
+        );
+        assert!(magnet_uri(&[0; 16], None, &[]).is_err());
+    }
+}
@@ -0,0 +1,382 @@
+//! Typed metainfo (`.torrent`) files, as described by BEP 3.
+//!
+//! Optional fields that are absent deserialize to `None` and are left out
+//! again when serialized, so a torrent round-trips byte for byte as long as
+//! it only uses the fields modelled here.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "url")]
+mod announce;
+#[cfg(feature = "create")]
+mod create;
+pub mod extension;
+mod magnet;
+pub mod pex;
+pub mod pieces;
+pub mod private;
+pub mod tracker;
+pub mod url_list;
+mod validate;
+
+#[cfg(feature = "url")]
+pub use announce::AnnounceList;
+#[cfg(feature = "create")]
+pub use create::TorrentBuilder;
+pub use magnet::magnet_uri;
+pub use validate::Problem;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Torrent {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub announce: Option<String>,
+    #[serde(
+        rename = "announce-list",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[cfg(not(feature = "url"))]
+    pub announce_list: Option<Vec<Vec<String>>>,
+    /// With the `url` feature, every tracker URL is validated
+    #[serde(
+        rename = "announce-list",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[cfg(feature = "url")]
+    pub announce_list: Option<AnnounceList>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    #[serde(
+        rename = "created by",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub created_by: Option<String>,
+    /// Seconds since the Unix epoch
+    #[serde(
+        rename = "creation date",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub creation_date: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    pub info: Info,
+    /// Web seeds (BEP 19)
+    #[serde(
+        rename = "url-list",
+        default,
+        with = "url_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub url_list: Vec<String>,
+}
+
+impl Torrent {
+    /// Every tracker in the `announce-list`, in tier order, or just
+    /// `announce` if there is no list. Duplicates are dropped.
+    pub fn trackers(&self) -> Vec<&str> {
+        let mut trackers = Vec::new();
+        #[cfg(feature = "url")]
+        let tiers = self.announce_list.as_ref().map(AnnounceList::tiers);
+        #[cfg(not(feature = "url"))]
+        let tiers = self.announce_list.as_deref();
+        match tiers {
+            Some(tiers) if !tiers.is_empty() => {
+                for url in tiers.iter().flatten() {
+                    if !trackers.contains(&url.as_str()) {
+                        trackers.push(url.as_str());
+                    }
+                }
+            }
+            _ => trackers.extend(self.announce.as_deref()),
+        }
+        trackers
+    }
+
+    /// A magnet link with the infohash, name, trackers and web seeds.
+    ///
+    /// `info_hash` has to come from the original torrent bytes, with
+    /// `info_hash_v1` (or `info_hash_v2` for a `btmh` link): `info` alone
+    /// can't give it, since `Info` leaves out fields it doesn't model.
+    pub fn magnet(&self, info_hash: &[u8]) -> crate::Result<String> {
+        let mut uri = magnet_uri(info_hash, Some(&self.info.name), &self.trackers())?;
+        for url in &self.url_list {
+            uri.push_str("&ws=");
+            magnet::percent_encode(url, &mut uri);
+        }
+        Ok(uri)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawInfo", into = "RawInfo")]
+pub struct Info {
+    pub name: String,
+    pub piece_length: u64,
+    /// The SHA-1 hash of every piece
+    pub pieces: Vec<[u8; 20]>,
+    pub layout: Layout,
+    /// Peers should only come from the trackers (BEP 27)
+    pub private: bool,
+}
+
+/// Whether a torrent holds one file or a directory of them. The two are
+/// told apart by which of `length` and `files` the info dictionary has;
+/// having both or neither is an error.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Layout {
+    /// A single file called `Info::name`
+    Single { length: u64 },
+    /// Files in a directory called `Info::name`
+    Multi { files: Vec<File> },
+}
+
+impl Layout {
+    /// The files of a multi-file torrent, leaving out padding files. A
+    /// single-file torrent has none.
+    pub fn files(&self) -> impl Iterator<Item = &File> {
+        let files = match self {
+            Layout::Single { .. } => &[][..],
+            Layout::Multi { files } => files,
+        };
+        files.iter().filter(|f| !f.is_padding())
+    }
+
+    /// The size of the content, including padding. Lengths that add up to
+    /// more than `u64::MAX` saturate rather than overflow.
+    pub fn total_length(&self) -> u64 {
+        match self {
+            Layout::Single { length } => *length,
+            Layout::Multi { files } => files.iter().fold(0u64, |n, f| n.saturating_add(f.length)),
+        }
+    }
+}
+
+// the info dictionary as it is encoded
+#[derive(Serialize, Deserialize)]
+struct RawInfo {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    files: Option<Vec<File>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    length: Option<u64>,
+    name: String,
+    #[serde(rename = "piece length")]
+    piece_length: u64,
+    #[serde(with = "pieces")]
+    pieces: Vec<[u8; 20]>,
+    #[serde(default, with = "private", skip_serializing_if = "is_false")]
+    private: bool,
+}
+
+fn is_false(b: &bool) -> bool {
+    !b
+}
+
+impl TryFrom<RawInfo> for Info {
+    type Error = &'static str;
+
+    fn try_from(raw: RawInfo) -> Result<Info, &'static str> {
+        let layout = match (raw.length, raw.files) {
+            (Some(length), None) => Layout::Single { length },
+            (None, Some(files)) => Layout::Multi { files },
+            (Some(_), Some(_)) => return Err("info has both `length` and `files`"),
+            (None, None) => return Err("info has neither `length` nor `files`"),
+        };
+        Ok(Info {
+            name: raw.name,
+            piece_length: raw.piece_length,
+            pieces: raw.pieces,
+            layout,
+            private: raw.private,
+        })
+    }
+}
+
+impl From<Info> for RawInfo {
+    fn from(info: Info) -> RawInfo {
+        let (length, files) = match info.layout {
+            Layout::Single { length } => (Some(length), None),
+            Layout::Multi { files } => (None, Some(files)),
+        };
+        RawInfo {
+            files,
+            length,
+            name: info.name,
+            piece_length: info.piece_length,
+            pieces: info.pieces,
+            private: info.private,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct File {
+    pub length: u64,
+    /// Path components, relative to the directory named by `Info::name`
+    pub path: Vec<String>,
+    /// One letter for each attribute of the file (BEP 47): `p` for padding,
+    /// `x` executable, `h` hidden and `l` a symlink
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attr: Option<String>,
+    /// The SHA-1 of the file's contents
+    #[serde(default, with = "serde_bytes", skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<[u8; 20]>,
+    /// Where a symlink points, as path components relative to the torrent's
+    /// root
+    #[serde(
+        rename = "symlink path",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub symlink_path: Option<Vec<String>>,
+}
+
+impl File {
+    fn has_attr(&self, attr: char) -> bool {
+        self.attr.as_deref().is_some_and(|a| a.contains(attr))
+    }
+
+    /// Padding files only exist to align the next file to a piece boundary;
+    /// they are all zeroes and aren't written to disk.
+    pub fn is_padding(&self) -> bool {
+        self.has_attr('p')
+    }
+
+    pub fn is_executable(&self) -> bool {
+        self.has_attr('x')
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.has_attr('h')
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.has_attr('l')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{File, Layout, Torrent};
+    use crate::{from_bytes, to_bytes};
+
+    #[test]
+    fn test_debian() {
+        let input = include_bytes!("../../debian.torrent").to_vec();
+        let torrent: Torrent = from_bytes(&input).unwrap();
+        assert_eq!(
+            Some("http://bttracker.debian.org:6969/announce"),
+            torrent.announce.as_deref()
+        );
+        assert_eq!(Some(1662813552), torrent.creation_date);
+        assert_eq!("debian-11.5.0-amd64-netinst.iso", torrent.info.name);
+        assert_eq!(262144, torrent.info.piece_length);
+        assert_eq!(Layout::Single { length: 400556032 }, torrent.info.layout);
+        assert_eq!(1528, torrent.info.pieces.len());
+        assert_eq!(
+            vec!["http://bttracker.debian.org:6969/announce"],
+            torrent.trackers()
+        );
+        assert_eq!(2, torrent.url_list.len());
+        assert_eq!(input, to_bytes(&torrent).unwrap());
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    fn test_magnet() {
+        let input = include_bytes!("../../debian.torrent").to_vec();
+        let torrent: Torrent = from_bytes(&input).unwrap();
+        let magnet = torrent
+            .magnet(&crate::info_hash_v1(&input).unwrap())
+            .unwrap();
+        assert!(magnet.starts_with(
+            "magnet:?xt=urn:btih:d55be2cd263efa84aeb9495333a4fabc428a4250\
+            &dn=debian-11.5.0-amd64-netinst.iso\
+            &tr=http%3A%2F%2Fbttracker.debian.org%3A6969%2Fannounce\
+            &ws=https%3A%2F%2Fcdimage.debian.org%2F"
+        ));
+
+        // fields `Info` doesn't model still count towards the hash
+        let input = b"d4:infod6:lengthi3e6:md5sum32:0123456789abcdef0123456789abcdef\
+            4:name1:a12:piece lengthi16384e6:pieces0:ee";
+        let torrent: Torrent = from_bytes(input).unwrap();
+        let info_hash = crate::info_hash_v1(input).unwrap();
+        let magnet = torrent.magnet(&info_hash).unwrap();
+        let hex: String = info_hash.iter().map(|b| format!("{:02x}", b)).collect();
+        assert!(magnet.starts_with(&format!("magnet:?xt=urn:btih:{}&", hex)));
+        assert_ne!(
+            crate::info_hash_v1(&to_bytes(&torrent).unwrap()).unwrap(),
+            info_hash
+        );
+    }
+
+    #[test]
+    fn test_multi_file() {
+        let input = b"d4:infod5:filesld6:lengthi3e4:pathl1:a1:beee4:name3:dir\
+            12:piece lengthi16384e6:pieces0:7:privatei1eee";
+        let torrent: Torrent = from_bytes(input).unwrap();
+        assert_eq!(
+            Layout::Multi {
+                files: vec![File {
+                    length: 3,
+                    path: vec!["a".to_string(), "b".to_string()],
+                    ..File::default()
+                }],
+            },
+            torrent.info.layout
+        );
+        assert_eq!(3, torrent.info.layout.total_length());
+
+        let files = vec![
+            File {
+                length: u64::MAX,
+                ..File::default()
+            };
+            2
+        ];
+        assert_eq!(u64::MAX, Layout::Multi { files }.total_length());
+        assert!(torrent.info.private);
+        assert_eq!(None, torrent.announce);
+        assert_eq!(input.to_vec(), to_bytes(&torrent).unwrap());
+    }
+
+    #[test]
+    fn test_padding() {
+        let input = b"d4:infod5:filesld6:lengthi3e4:pathl1:aee\
+            d4:attr1:p6:lengthi16381e4:pathl4:.pad5:16381ee\
+            d4:attr2:lx6:lengthi0e4:pathl1:be12:symlink pathl1:aee\
+            d6:lengthi1e4:pathl1:ce4:sha120:aaaaaaaaaaaaaaaaaaaaee\
+            4:name3:dir12:piece lengthi16384e6:pieces0:ee";
+        let torrent: Torrent = from_bytes(input).unwrap();
+        let files: Vec<_> = torrent.info.layout.files().collect();
+        assert_eq!(3, files.len());
+        assert_eq!(vec!["b"], files[1].path);
+        assert!(files[1].is_symlink());
+        assert!(files[1].is_executable());
+        assert!(!files[1].is_hidden());
+        assert_eq!(Some(vec!["a".to_string()]), files[1].symlink_path);
+        assert_eq!(Some([b'a'; 20]), files[2].sha1);
+        assert_eq!(16385, torrent.info.layout.total_length());
+        assert_eq!(input.to_vec(), to_bytes(&torrent).unwrap());
+    }
+
+    #[test]
+    fn test_trackers() {
+        let input = b"d8:announce9:http://a/13:announce-listll9:http://b/9:http://a/e\
+            l9:http://b/ee4:infod6:lengthi0e4:name0:12:piece lengthi1e6:pieces0:ee";
+        let mut torrent: Torrent = from_bytes(input).unwrap();
+        assert_eq!(vec!["http://b/", "http://a/"], torrent.trackers());
+        torrent.announce_list = None;
+        assert_eq!(vec!["http://a/"], torrent.trackers());
+    }
+
+    #[test]
+    fn test_layout_errors() {
+        let both = b"d5:filesle6:lengthi1e4:name1:a12:piece lengthi1e6:pieces0:e";
+        assert!(from_bytes::<super::Info>(both).is_err());
+        let neither = b"d4:name1:a12:piece lengthi1e6:pieces0:e";
+        assert!(from_bytes::<super::Info>(neither).is_err());
+    }
+}
@@ -0,0 +1,155 @@
+//! Peer exchange messages (`ut_pex`, BEP 11).
+
+use serde::{Deserialize, Serialize};
+use std::net::{SocketAddrV4, SocketAddrV6};
+
+/// The peers that connected and disconnected since the last message.
+/// `added_f` and `added6_f` hold a `PexFlags` for each peer in `added` and
+/// `added6`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pex {
+    #[serde(default, with = "crate::compact::v4_list")]
+    pub added: Vec<SocketAddrV4>,
+    #[serde(rename = "added.f", default, with = "flags")]
+    pub added_f: Vec<PexFlags>,
+    #[serde(default, with = "crate::compact::v6_list")]
+    pub added6: Vec<SocketAddrV6>,
+    #[serde(rename = "added6.f", default, with = "flags")]
+    pub added6_f: Vec<PexFlags>,
+    #[serde(default, with = "crate::compact::v4_list")]
+    pub dropped: Vec<SocketAddrV4>,
+    #[serde(default, with = "crate::compact::v6_list")]
+    pub dropped6: Vec<SocketAddrV6>,
+}
+
+impl Pex {
+    /// Each added IPv4 peer with its flags, which are empty if missing.
+    pub fn added_with_flags(&self) -> impl Iterator<Item = (SocketAddrV4, PexFlags)> + '_ {
+        let flags = self
+            .added_f
+            .iter()
+            .copied()
+            .chain(std::iter::repeat(PexFlags(0)));
+        self.added.iter().copied().zip(flags)
+    }
+
+    /// Each added IPv6 peer with its flags, which are empty if missing.
+    pub fn added6_with_flags(&self) -> impl Iterator<Item = (SocketAddrV6, PexFlags)> + '_ {
+        let flags = self
+            .added6_f
+            .iter()
+            .copied()
+            .chain(std::iter::repeat(PexFlags(0)));
+        self.added6.iter().copied().zip(flags)
+    }
+}
+
+/// What a peer in `added` is known to support, one bit each.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PexFlags(pub u8);
+
+impl PexFlags {
+    pub const PREFERS_ENCRYPTION: u8 = 0x01;
+    pub const SEED: u8 = 0x02;
+    pub const SUPPORTS_UTP: u8 = 0x04;
+    pub const SUPPORTS_HOLEPUNCH: u8 = 0x08;
+    pub const REACHABLE: u8 = 0x10;
+
+    pub fn contains(self, flag: u8) -> bool {
+        self.0 & flag == flag
+    }
+
+    pub fn prefers_encryption(self) -> bool {
+        self.contains(Self::PREFERS_ENCRYPTION)
+    }
+
+    pub fn is_seed(self) -> bool {
+        self.contains(Self::SEED)
+    }
+
+    pub fn supports_utp(self) -> bool {
+        self.contains(Self::SUPPORTS_UTP)
+    }
+
+    pub fn supports_holepunch(self) -> bool {
+        self.contains(Self::SUPPORTS_HOLEPUNCH)
+    }
+
+    pub fn is_reachable(self) -> bool {
+        self.contains(Self::REACHABLE)
+    }
+}
+
+// one byte per peer
+mod flags {
+    use serde::de::{self, Deserializer, Visitor};
+    use serde::Serializer;
+    use std::fmt;
+
+    use super::PexFlags;
+
+    pub fn serialize<S>(flags: &[PexFlags], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let bytes: Vec<u8> = flags.iter().map(|f| f.0).collect();
+        serializer.serialize_bytes(&bytes)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<PexFlags>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(FlagsVisitor)
+    }
+
+    struct FlagsVisitor;
+
+    impl<'de> Visitor<'de> for FlagsVisitor {
+        type Value = Vec<PexFlags>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a byte string of peer flags")
+        }
+
+        fn visit_bytes<E>(self, v: &[u8]) -> Result<Vec<PexFlags>, E>
+        where
+            E: de::Error,
+        {
+            Ok(v.iter().map(|&b| PexFlags(b)).collect())
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Vec<PexFlags>, E>
+        where
+            E: de::Error,
+        {
+            self.visit_bytes(v.as_bytes())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Pex, PexFlags};
+    use crate::{from_bytes, to_bytes};
+
+    #[test]
+    fn test_pex() {
+        let input = b"d5:added12:\x0a\x00\x00\x01\x1a\xe1\x0a\x00\x00\x02\x1a\xe2\
+            7:added.f1:\x126:added60:8:added6.f0:7:dropped6:\x0a\x00\x00\x03\x00\x50\
+            8:dropped60:e";
+        let pex: Pex = from_bytes(input).unwrap();
+        let added: Vec<_> = pex.added_with_flags().collect();
+        assert_eq!(2, added.len());
+        assert_eq!("10.0.0.1:6881", added[0].0.to_string());
+        assert!(added[0].1.is_seed());
+        assert!(added[0].1.is_reachable());
+        assert!(!added[0].1.supports_utp());
+        assert_eq!(PexFlags(0), added[1].1);
+        assert_eq!("10.0.0.3:80", pex.dropped[0].to_string());
+        assert_eq!(input.to_vec(), to_bytes(&pex).unwrap());
+
+        let pex: Pex = from_bytes(b"de").unwrap();
+        assert_eq!(Pex::default(), pex);
+    }
+}
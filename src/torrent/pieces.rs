@@ -0,0 +1,75 @@
+//! Reads the `pieces` byte string as a list of SHA-1 hashes, and writes it
+//! back. Use it with `#[serde(with = "serde_bencode::torrent::pieces")]`
+//! on a `Vec<[u8; 20]>` field.
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::Serializer;
+use std::fmt;
+
+pub fn serialize<S>(pieces: &[[u8; 20]], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_bytes(&pieces.concat())
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<[u8; 20]>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_bytes(PiecesVisitor)
+}
+
+struct PiecesVisitor;
+
+impl<'de> Visitor<'de> for PiecesVisitor {
+    type Value = Vec<[u8; 20]>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a byte string whose length is a multiple of 20")
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        if !v.len().is_multiple_of(20) {
+            return Err(E::invalid_length(v.len(), &self));
+        }
+        Ok(v.chunks_exact(20)
+            .map(|hash| hash.try_into().unwrap())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_bytes, to_bytes};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Info {
+        #[serde(with = "super")]
+        pieces: Vec<[u8; 20]>,
+    }
+
+    #[test]
+    fn test_pieces() {
+        let mut input = b"d6:pieces40:".to_vec();
+        input.extend([1; 20]);
+        input.extend([2; 20]);
+        input.push(b'e');
+        let info: Info = from_bytes(&input).unwrap();
+        assert_eq!(vec![[1; 20], [2; 20]], info.pieces);
+        assert_eq!(input, to_bytes(&info).unwrap());
+
+        let empty: Info = from_bytes(b"d6:pieces0:e").unwrap();
+        assert!(empty.pieces.is_empty());
+
+        let err = from_bytes::<Info>(b"d6:pieces3:abce").unwrap_err();
+        assert_eq!(
+            "invalid length 3, expected a byte string whose length is a multiple of 20",
+            err.inner().to_string()
+        );
+    }
+}
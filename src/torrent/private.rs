@@ -0,0 +1,111 @@
+//! Reads the `private` flag (BEP 27) as a `bool`, accepting the forms seen
+//! in the wild: an integer, or `0` or `1` as a byte string. Writes `i1e` or
+//! `i0e`; pair it with `#[serde(default)]` so a missing flag is `false`.
+
+use serde::de::{self, Deserializer, Unexpected, Visitor};
+use serde::Serializer;
+use std::fmt;
+
+pub fn serialize<S>(private: &bool, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_i64(*private as i64)
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_any(PrivateVisitor)
+}
+
+struct PrivateVisitor;
+
+impl<'de> Visitor<'de> for PrivateVisitor {
+    type Value = bool;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("0 or 1")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<bool, E>
+    where
+        E: de::Error,
+    {
+        Ok(v)
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<bool, E>
+    where
+        E: de::Error,
+    {
+        match v {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(E::invalid_value(Unexpected::Signed(v), &self)),
+        }
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<bool, E>
+    where
+        E: de::Error,
+    {
+        match v {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(E::invalid_value(Unexpected::Unsigned(v), &self)),
+        }
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<bool, E>
+    where
+        E: de::Error,
+    {
+        match v {
+            "0" => Ok(false),
+            "1" => Ok(true),
+            _ => Err(E::invalid_value(Unexpected::Str(v), &self)),
+        }
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<bool, E>
+    where
+        E: de::Error,
+    {
+        match v {
+            b"0" => Ok(false),
+            b"1" => Ok(true),
+            _ => Err(E::invalid_value(Unexpected::Bytes(v), &self)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_bytes, to_bytes};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Info {
+        #[serde(default, with = "super")]
+        private: bool,
+    }
+
+    #[test]
+    fn test_private() {
+        let private = |input: &[u8]| from_bytes::<Info>(input).map(|info| info.private);
+        assert_eq!(Ok(true), private(b"d7:privatei1ee"));
+        assert_eq!(Ok(false), private(b"d7:privatei0ee"));
+        assert_eq!(Ok(true), private(b"d7:private1:1e"));
+        assert_eq!(Ok(false), private(b"d7:private1:0e"));
+        assert_eq!(Ok(false), private(b"de"));
+        assert!(private(b"d7:privatei2ee").is_err());
+        assert!(private(b"d7:private3:yese").is_err());
+
+        assert_eq!(
+            b"d7:privatei1ee".to_vec(),
+            to_bytes(&Info { private: true }).unwrap()
+        );
+    }
+}
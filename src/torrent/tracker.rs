@@ -0,0 +1,385 @@
+//! Responses from HTTP trackers (BEP 3, BEP 23).
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::{SocketAddr, SocketAddrV6};
+
+/// The response to an announce. A tracker that refuses the request sends
+/// only a `failure reason`, so everything else is optional.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnnounceResponse {
+    #[serde(
+        rename = "failure reason",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub failure_reason: Option<String>,
+    #[serde(
+        rename = "warning message",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub warning_message: Option<String>,
+    /// Seconds to wait between regular announces
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval: Option<u64>,
+    #[serde(
+        rename = "min interval",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub min_interval: Option<u64>,
+    #[serde(
+        rename = "tracker id",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub tracker_id: Option<String>,
+    /// Number of seeders
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub complete: Option<u64>,
+    /// Number of leechers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub incomplete: Option<u64>,
+    #[serde(default, with = "peers", skip_serializing_if = "Vec::is_empty")]
+    pub peers: Vec<Peer>,
+    /// IPv6 peers, which only come in the compact form (BEP 7)
+    #[serde(
+        default,
+        with = "crate::compact::v6_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub peers6: Vec<SocketAddrV6>,
+}
+
+/// A peer from `peers`. The compact form only holds IPv4 addresses, but a
+/// dictionary's `ip` can also be an IPv6 address or a host name.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Peer {
+    Addr(SocketAddr),
+    /// A host name and port, left for the caller to resolve
+    Host(String, u16),
+}
+
+/// Reads `peers` in either of its forms: a list of dictionaries with `ip`
+/// and `port`, or the compact byte string of 6 bytes per peer. Peers are
+/// written in the compact form when they are all IPv4 addresses, and as
+/// dictionaries otherwise.
+pub mod peers {
+    use serde::de::{self, Deserializer, SeqAccess, Visitor};
+    use serde::{Deserialize, Serialize, Serializer};
+    use std::fmt;
+    use std::net::{IpAddr, SocketAddr, SocketAddrV4};
+
+    use super::Peer;
+    use crate::compact::{decode_list, encode_list};
+
+    pub fn serialize<S>(peers: &[Peer], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let compact: Option<Vec<SocketAddrV4>> = peers
+            .iter()
+            .map(|peer| match peer {
+                Peer::Addr(SocketAddr::V4(addr)) => Some(*addr),
+                _ => None,
+            })
+            .collect();
+        match compact {
+            Some(addrs) => serializer.serialize_bytes(&encode_list(&addrs)),
+            None => serializer.collect_seq(peers.iter().map(|peer| match peer {
+                Peer::Addr(addr) => PeerDict {
+                    ip: addr.ip().to_string(),
+                    port: addr.port(),
+                },
+                Peer::Host(host, port) => PeerDict {
+                    ip: host.clone(),
+                    port: *port,
+                },
+            })),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<Peer>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(PeersVisitor)
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct PeerDict {
+        ip: String,
+        port: u16,
+    }
+
+    struct PeersVisitor;
+
+    impl PeersVisitor {
+        fn compact<E: de::Error>(v: &[u8]) -> Result<Vec<Peer>, E> {
+            let addrs: Vec<SocketAddrV4> = decode_list(v)?;
+            Ok(addrs
+                .into_iter()
+                .map(|addr| Peer::Addr(addr.into()))
+                .collect())
+        }
+    }
+
+    impl<'de> Visitor<'de> for PeersVisitor {
+        type Value = Vec<Peer>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a list of peers or a compact peer string")
+        }
+
+        fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            PeersVisitor::compact(v)
+        }
+
+        // compact strings that happen to be valid UTF-8
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            PeersVisitor::compact(v.as_bytes())
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut peers = Vec::new();
+            while let Some(peer) = seq.next_element::<PeerDict>()? {
+                peers.push(match peer.ip.parse::<IpAddr>() {
+                    Ok(ip) => Peer::Addr(SocketAddr::new(ip, peer.port)),
+                    Err(_) => Peer::Host(peer.ip, peer.port),
+                });
+            }
+            Ok(peers)
+        }
+    }
+}
+
+/// The response to a scrape, with the swarm of each requested torrent keyed
+/// by its 20-byte infohash.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScrapeResponse {
+    #[serde(
+        rename = "failure reason",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub failure_reason: Option<String>,
+    #[serde(default, with = "scrape_files")]
+    pub files: BTreeMap<[u8; 20], ScrapeStats>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScrapeStats {
+    /// Number of seeders
+    pub complete: u64,
+    /// Number of times the torrent has been downloaded in full
+    pub downloaded: u64,
+    /// Number of leechers
+    pub incomplete: u64,
+}
+
+// infohashes are raw bytes, not strings, so the keys need their own handling
+mod scrape_files {
+    use serde::de::{self, Deserializer, MapAccess, Visitor};
+    use serde::{Deserialize, Serialize, Serializer};
+    use std::collections::BTreeMap;
+    use std::fmt;
+
+    use super::ScrapeStats;
+
+    pub fn serialize<S>(
+        files: &BTreeMap<[u8; 20], ScrapeStats>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_map(files.iter().map(|(hash, stats)| (InfoHash(*hash), stats)))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<BTreeMap<[u8; 20], ScrapeStats>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(FilesVisitor)
+    }
+
+    struct InfoHash([u8; 20]);
+
+    impl Serialize for InfoHash {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for InfoHash {
+        fn deserialize<D>(deserializer: D) -> Result<InfoHash, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_bytes(InfoHashVisitor)
+        }
+    }
+
+    struct InfoHashVisitor;
+
+    impl<'de> Visitor<'de> for InfoHashVisitor {
+        type Value = InfoHash;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a 20-byte infohash")
+        }
+
+        fn visit_bytes<E>(self, v: &[u8]) -> Result<InfoHash, E>
+        where
+            E: de::Error,
+        {
+            v.try_into()
+                .map(InfoHash)
+                .map_err(|_| E::invalid_length(v.len(), &self))
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<InfoHash, E>
+        where
+            E: de::Error,
+        {
+            self.visit_bytes(v.as_bytes())
+        }
+    }
+
+    struct FilesVisitor;
+
+    impl<'de> Visitor<'de> for FilesVisitor {
+        type Value = BTreeMap<[u8; 20], ScrapeStats>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a dictionary keyed by infohash")
+        }
+
+        fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+        where
+            A: MapAccess<'de>,
+        {
+            let mut files = BTreeMap::new();
+            while let Some((InfoHash(hash), stats)) = map.next_entry()? {
+                files.insert(hash, stats);
+            }
+            Ok(files)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnnounceResponse, Peer, ScrapeResponse, ScrapeStats};
+    use crate::{from_bytes, to_bytes};
+
+    #[test]
+    fn test_compact_peers() {
+        let input = b"d8:completei2e10:incompletei1e8:intervali1800e\
+            12:min intervali900e5:peers12:\x0a\x00\x00\x01\x1a\xe1\x7f\x00\x00\x01\x00\x50e";
+        let response: AnnounceResponse = from_bytes(input).unwrap();
+        assert_eq!(Some(1800), response.interval);
+        assert_eq!(Some(900), response.min_interval);
+        assert_eq!(Some(2), response.complete);
+        assert_eq!(Some(1), response.incomplete);
+        assert_eq!(
+            vec![
+                Peer::Addr("10.0.0.1:6881".parse().unwrap()),
+                Peer::Addr("127.0.0.1:80".parse().unwrap()),
+            ],
+            response.peers
+        );
+        assert_eq!(input.to_vec(), to_bytes(&response).unwrap());
+
+        // six bytes that are valid UTF-8
+        let response: AnnounceResponse = from_bytes(b"d5:peers6:abcdefe").unwrap();
+        assert_eq!(
+            Peer::Addr("97.98.99.100:25958".parse().unwrap()),
+            response.peers[0]
+        );
+
+        assert!(from_bytes::<AnnounceResponse>(b"d5:peers5:abcdee").is_err());
+    }
+
+    #[test]
+    fn test_peers6() {
+        let mut input = b"d6:peers618:".to_vec();
+        input.extend([0; 15]);
+        input.extend([1, 0x1a, 0xe1, b'e']);
+        let response: AnnounceResponse = from_bytes(&input).unwrap();
+        assert_eq!("[::1]:6881", response.peers6[0].to_string());
+        assert!(response.peers.is_empty());
+        assert_eq!(input, to_bytes(&response).unwrap());
+    }
+
+    #[test]
+    fn test_dict_peers() {
+        let input = b"d8:intervali60e5:peersld2:ip8:10.0.0.17:peer id3:abc4:porti6881eeee";
+        let response: AnnounceResponse = from_bytes(input).unwrap();
+        assert_eq!(
+            vec![Peer::Addr("10.0.0.1:6881".parse().unwrap())],
+            response.peers
+        );
+        // IPv4 peers alone are written back in the compact form
+        assert_eq!(
+            b"d8:intervali60e5:peers6:\x0a\x00\x00\x01\x1a\xe1e".to_vec(),
+            to_bytes(&response).unwrap()
+        );
+
+        // host names and IPv6 addresses are kept, not an error
+        let input = b"d5:peersld2:ip15:tracker.example4:porti1eed2:ip3:::14:porti2eeee";
+        let response: AnnounceResponse = from_bytes(input).unwrap();
+        assert_eq!(
+            vec![
+                Peer::Host("tracker.example".to_string(), 1),
+                Peer::Addr("[::1]:2".parse().unwrap()),
+            ],
+            response.peers
+        );
+        assert_eq!(input.to_vec(), to_bytes(&response).unwrap());
+
+        let input = b"d5:peersld2:ip1:a4:porti99999eeee";
+        assert!(from_bytes::<AnnounceResponse>(input).is_err());
+    }
+
+    #[test]
+    fn test_failure() {
+        let response: AnnounceResponse = from_bytes(b"d14:failure reason4:nopee").unwrap();
+        assert_eq!(Some("nope"), response.failure_reason.as_deref());
+        assert!(response.peers.is_empty());
+        assert_eq!(None, response.interval);
+    }
+
+    #[test]
+    fn test_scrape() {
+        let mut input = b"d5:filesd20:".to_vec();
+        input.extend([0xaa; 20]);
+        input.extend(b"d8:completei5e10:downloadedi50e10:incompletei10eeee");
+        let response: ScrapeResponse = from_bytes(&input).unwrap();
+        assert_eq!(
+            Some(&ScrapeStats {
+                complete: 5,
+                downloaded: 50,
+                incomplete: 10,
+            }),
+            response.files.get(&[0xaa; 20])
+        );
+        assert_eq!(input, to_bytes(&response).unwrap());
+
+        let input = b"d5:filesd3:abcd8:completei0e10:downloadedi0e10:incompletei0eeee";
+        assert!(from_bytes::<ScrapeResponse>(input).is_err());
+    }
+}
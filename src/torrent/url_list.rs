@@ -0,0 +1,113 @@
+//! Reads `url-list` (BEP 19), which real torrents write either as a single
+//! URL or as a list of them, into a `Vec<String>`. It is always written as
+//! a list.
+
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::Serializer;
+use std::fmt;
+
+pub fn serialize<S>(urls: &[String], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_seq(urls)
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_any(UrlListVisitor)
+}
+
+struct UrlListVisitor;
+
+impl<'de> Visitor<'de> for UrlListVisitor {
+    type Value = Vec<String>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a URL or a list of URLs")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Vec<String>, E>
+    where
+        E: de::Error,
+    {
+        // some torrents have an empty string for no web seeds
+        if v.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(vec![v.to_string()])
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Vec<String>, E>
+    where
+        E: de::Error,
+    {
+        match std::str::from_utf8(v) {
+            Ok(s) => self.visit_str(s),
+            Err(_) => Err(E::invalid_value(de::Unexpected::Bytes(v), &self)),
+        }
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Vec<String>, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut urls = Vec::new();
+        while let Some(url) = seq.next_element()? {
+            urls.push(url);
+        }
+        Ok(urls)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_bytes, from_value, to_bytes, Value};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Torrent {
+        #[serde(rename = "url-list", default, with = "super")]
+        url_list: Vec<String>,
+    }
+
+    #[test]
+    fn test_url_list() {
+        let urls = |input: &[u8]| from_bytes::<Torrent>(input).unwrap().url_list;
+        assert_eq!(vec!["http://a/"], urls(b"d8:url-list9:http://a/e"));
+        assert_eq!(
+            vec!["http://a/", "http://b/"],
+            urls(b"d8:url-listl9:http://a/9:http://b/ee")
+        );
+        assert!(urls(b"d8:url-list0:e").is_empty());
+        assert!(urls(b"de").is_empty());
+        assert!(from_bytes::<Torrent>(b"d8:url-listi1ee").is_err());
+
+        let torrent = Torrent {
+            url_list: vec!["http://a/".to_string()],
+        };
+        assert_eq!(
+            b"d8:url-listl9:http://a/ee".to_vec(),
+            to_bytes(&torrent).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_url_list_from_value() {
+        let value: Value = from_bytes(b"d8:url-list9:http://a/e").unwrap();
+        let borrowed = Torrent::deserialize(&value).unwrap();
+        assert_eq!(vec!["http://a/"], borrowed.url_list);
+        assert_eq!(borrowed, from_value::<Torrent>(value).unwrap());
+
+        // the whole torrent type, with a single web seed
+        let mut value: Value = from_bytes(include_bytes!("../../debian.torrent")).unwrap();
+        value["url-list"] = Value::Bytes(b"http://a/"[..].into());
+        let torrent: crate::torrent::Torrent = from_value(value).unwrap();
+        assert_eq!(vec!["http://a/"], torrent.url_list);
+
+        let value: Value = from_bytes(b"d8:url-list1:\xffe").unwrap();
+        assert!(from_value::<Torrent>(value).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+use std::fmt::{self, Display};
+
+use super::{Layout, Torrent};
+
+/// Something in a torrent that is inconsistent or unsafe to act on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Problem {
+    /// The piece length is zero or not a power of two.
+    PieceLength(u64),
+    /// The number of piece hashes doesn't fit the total length.
+    PieceCount { expected: u64, found: usize },
+    /// A multi-file torrent has no files.
+    NoFiles,
+    /// A file in `files`, by index, has an empty path.
+    EmptyPath(usize),
+    /// A file in `files`, by index, has a path component that could escape
+    /// the torrent's directory: empty, `.`, `..`, or containing a separator.
+    UnsafePath(usize, String),
+    /// The name could escape the download directory, like an unsafe path
+    /// component.
+    UnsafeName(String),
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::PieceLength(n) => write!(f, "piece length {} is not a power of two", n),
+            Problem::PieceCount { expected, found } => {
+                write!(f, "expected {} pieces, found {}", expected, found)
+            }
+            Problem::NoFiles => f.write_str("torrent has no files"),
+            Problem::EmptyPath(i) => write!(f, "file {} has an empty path", i),
+            Problem::UnsafePath(i, c) => {
+                write!(f, "file {} has an unsafe path component {:?}", i, c)
+            }
+            Problem::UnsafeName(name) => write!(f, "unsafe name {:?}", name),
+        }
+    }
+}
+
+fn is_unsafe(component: &str) -> bool {
+    matches!(component, "" | "." | "..") || component.contains(['/', '\\', '\0'])
+}
+
+impl Torrent {
+    /// Checks that the torrent is internally consistent and that its paths
+    /// are safe to create on disk, reporting every problem found.
+    ///
+    /// BitTorrent v2 fields (`file tree`, `piece layers`) aren't modelled
+    /// by `Torrent`, so they aren't checked.
+    pub fn validate(&self) -> Result<(), Vec<Problem>> {
+        let mut problems = Vec::new();
+        let info = &self.info;
+        if is_unsafe(&info.name) {
+            problems.push(Problem::UnsafeName(info.name.clone()));
+        }
+        if info.piece_length.is_power_of_two() {
+            let expected = info.layout.total_length().div_ceil(info.piece_length);
+            if expected != info.pieces.len() as u64 {
+                problems.push(Problem::PieceCount {
+                    expected,
+                    found: info.pieces.len(),
+                });
+            }
+        } else {
+            problems.push(Problem::PieceLength(info.piece_length));
+        }
+        if let Layout::Multi { files } = &info.layout {
+            if files.is_empty() {
+                problems.push(Problem::NoFiles);
+            }
+            for (i, file) in files.iter().enumerate() {
+                if file.path.is_empty() {
+                    problems.push(Problem::EmptyPath(i));
+                }
+                for component in file.path.iter().filter(|c| is_unsafe(c)) {
+                    problems.push(Problem::UnsafePath(i, component.clone()));
+                }
+            }
+        }
+        match problems.is_empty() {
+            true => Ok(()),
+            false => Err(problems),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Problem;
+    use crate::from_bytes;
+    use crate::torrent::Torrent;
+
+    #[test]
+    fn test_validate() {
+        let torrent: Torrent = from_bytes(include_bytes!("../../debian.torrent")).unwrap();
+        assert_eq!(Ok(()), torrent.validate());
+
+        let input = b"d4:infod5:filesld6:lengthi3e4:pathl2:..1:aeed6:lengthi1e4:pathleee\
+            4:name1:/12:piece lengthi16384e6:pieces0:ee";
+        let torrent: Torrent = from_bytes(input).unwrap();
+        assert_eq!(
+            Err(vec![
+                Problem::UnsafeName("/".to_string()),
+                Problem::PieceCount {
+                    expected: 1,
+                    found: 0
+                },
+                Problem::UnsafePath(0, "..".to_string()),
+                Problem::EmptyPath(1),
+            ]),
+            torrent.validate()
+        );
+
+        let input = b"d4:infod6:lengthi0e4:name1:a12:piece lengthi1000e6:pieces0:ee";
+        let torrent: Torrent = from_bytes(input).unwrap();
+        let problems = torrent.validate().unwrap_err();
+        assert_eq!(vec![Problem::PieceLength(1000)], problems);
+        assert_eq!(
+            "piece length 1000 is not a power of two",
+            problems[0].to_string()
+        );
+    }
+}
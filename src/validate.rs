@@ -0,0 +1,391 @@
+use core::fmt::{self, Display};
+
+use crate::de::DEFAULT_MAX_DEPTH;
+use crate::error::Error;
+use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+
+/// Something in a document that breaks the bencode spec, and where.
+#[derive(Debug, PartialEq)]
+pub struct Violation {
+    pub offset: usize,
+    pub kind: ViolationKind,
+}
+
+/// Non-canonical input that the deserializer accepted; see
+/// `from_bytes_with_warnings`. Its kind is never `Invalid`.
+pub type Warning = Violation;
+
+#[derive(Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ViolationKind {
+    /// A dictionary key sorts before the key preceding it.
+    UnsortedKey,
+    /// A dictionary key appears more than once.
+    DuplicateKey,
+    /// An integer or a byte string length has a leading zero.
+    LeadingZero,
+    /// `i-0e`.
+    NegativeZero,
+    /// An integer doesn't fit in an i64.
+    IntegerOutOfRange,
+    /// There's more input after the value.
+    TrailingBytes,
+    /// The input can't be parsed past this point.
+    Invalid(Error),
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ViolationKind::UnsortedKey => f.write_str("dictionary key out of order")?,
+            ViolationKind::DuplicateKey => f.write_str("duplicate dictionary key")?,
+            ViolationKind::LeadingZero => f.write_str("number with a leading zero")?,
+            ViolationKind::NegativeZero => f.write_str("integer is negative zero")?,
+            ViolationKind::IntegerOutOfRange => f.write_str("integer does not fit in an i64")?,
+            ViolationKind::TrailingBytes => f.write_str("unexpected data after the value")?,
+            ViolationKind::Invalid(e) => write!(f, "{}", e)?,
+        }
+        write!(f, " at byte {}", self.offset)
+    }
+}
+
+/// Checks that `input` is a single value in canonical form, reporting every
+/// violation rather than only the first. Checking stops at the first
+/// violation that can't be parsed past, which is always the last one.
+pub fn validate(input: &[u8]) -> Result<(), Vec<Violation>> {
+    let mut checker = Checker::new(input, Mode::All);
+    // the violations say why it stopped
+    let _ = checker.document();
+    match checker.violations.is_empty() {
+        true => Ok(()),
+        false => Err(checker.violations),
+    }
+}
+
+/// Checks that `input` is a single value in canonical form, stopping at the
+/// first violation. Use `validate` to find out what's wrong.
+pub fn is_canonical(input: &[u8]) -> bool {
+    Checker::new(input, Mode::First).document().is_ok()
+}
+
+/// Checks that `input` is a single value the deserializer can parse,
+/// whether or not it's canonical, for `Validated`. The error is the first
+/// thing that stopped it, located in `input`.
+pub(crate) fn check_structure(input: &[u8]) -> Result<(), Error> {
+    let mut checker = Checker::new(input, Mode::Structure);
+    if checker.document().is_ok() {
+        return Ok(());
+    }
+    // `Structure` only stops with one violation
+    let Violation { offset, kind } = checker.violations.pop().unwrap();
+    let error = match kind {
+        ViolationKind::Invalid(Error::Eof) => return Err(Error::Eof),
+        ViolationKind::Invalid(e) => e,
+        _ => Error::TrailingCharacters,
+    };
+    Err(Error::Located {
+        offset,
+        found: input.get(offset).copied(),
+        error: Box::new(error),
+    })
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    // stop at the first violation
+    First,
+    // go on after every violation that can be parsed past
+    All,
+    // only stop for what the deserializer can't parse, ignoring
+    // non-canonical input
+    Structure,
+}
+
+// Err(()) means checking can't or shouldn't go on; the reason is already in
+// `violations`
+type Checked<T> = core::result::Result<T, ()>;
+
+struct Checker<'a> {
+    input: &'a [u8],
+    pos: usize,
+    violations: Vec<Violation>,
+    mode: Mode,
+    // lists and dictionaries open around `pos`
+    depth: usize,
+}
+
+impl<'a> Checker<'a> {
+    fn new(input: &'a [u8], mode: Mode) -> Self {
+        Checker {
+            input,
+            pos: 0,
+            violations: Vec::new(),
+            mode,
+            depth: 0,
+        }
+    }
+
+    fn document(&mut self) -> Checked<()> {
+        self.value()?;
+        if self.pos < self.input.len() {
+            self.report(self.pos, ViolationKind::TrailingBytes)?;
+        }
+        Ok(())
+    }
+
+    fn report(&mut self, offset: usize, kind: ViolationKind) -> Checked<()> {
+        match self.mode {
+            Mode::Structure if kind != ViolationKind::TrailingBytes => return Ok(()),
+            _ => {}
+        }
+        self.violations.push(Violation { offset, kind });
+        match self.mode {
+            Mode::All => Ok(()),
+            Mode::First | Mode::Structure => Err(()),
+        }
+    }
+
+    fn fail<T>(&mut self, error: Error) -> Checked<T> {
+        self.violations.push(Violation {
+            offset: self.pos,
+            kind: ViolationKind::Invalid(error),
+        });
+        Err(())
+    }
+
+    fn peek(&mut self) -> Checked<u8> {
+        match self.input.get(self.pos) {
+            Some(b) => Ok(*b),
+            None => self.fail(Error::Eof),
+        }
+    }
+
+    fn expect(&mut self, b: u8, error: Error) -> Checked<()> {
+        if self.peek()? != b {
+            return self.fail(error);
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    // the digits of a number, with a leading zero reported
+    fn digits(&mut self) -> Checked<&'a [u8]> {
+        let start = self.pos;
+        let rest = &self.input[start..];
+        let len = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+        if len == 0 {
+            return self.fail(Error::ExpectedInteger);
+        }
+        if len > 1 && rest[0] == b'0' {
+            self.report(start, ViolationKind::LeadingZero)?;
+        }
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+
+    fn value(&mut self) -> Checked<()> {
+        match self.peek()? {
+            b'i' => self.int(),
+            b'0'..=b'9' => self.bytes().map(|_| ()),
+            // checking is recursive too
+            b'l' | b'd' if self.depth == DEFAULT_MAX_DEPTH => self.fail(Error::DepthLimitExceeded),
+            b'l' => {
+                self.pos += 1;
+                self.depth += 1;
+                while self.peek()? != b'e' {
+                    self.value()?;
+                }
+                self.depth -= 1;
+                self.pos += 1;
+                Ok(())
+            }
+            b'd' => {
+                self.depth += 1;
+                self.dict()?;
+                self.depth -= 1;
+                Ok(())
+            }
+            _ => self.fail(Error::Syntax),
+        }
+    }
+
+    fn int(&mut self) -> Checked<()> {
+        let start = self.pos;
+        self.pos += 1;
+        let negative = self.peek()? == b'-';
+        if negative {
+            self.pos += 1;
+        }
+        let digits = self.digits()?;
+        if negative && digits == b"0" {
+            self.report(start, ViolationKind::NegativeZero)?;
+        }
+        let text = &self.input[start + 1..self.pos];
+        // all ASCII, so this can't fail
+        if core::str::from_utf8(text).unwrap().parse::<i64>().is_err() {
+            self.report(start, ViolationKind::IntegerOutOfRange)?;
+        }
+        self.expect(b'e', Error::ExpectedE)
+    }
+
+    fn bytes(&mut self) -> Checked<&'a [u8]> {
+        let start = self.pos;
+        let digits = self.digits()?;
+        let len = match core::str::from_utf8(digits).unwrap().parse::<usize>() {
+            Ok(len) => len,
+            Err(_) => {
+                self.pos = start;
+                return self.fail(Error::IntegerOutOfRange);
+            }
+        };
+        self.expect(b':', Error::ExpectedColon)?;
+        match self.input.get(self.pos..).and_then(|rest| rest.get(..len)) {
+            Some(bytes) => {
+                self.pos += len;
+                Ok(bytes)
+            }
+            None => self.fail(Error::Eof),
+        }
+    }
+
+    fn dict(&mut self) -> Checked<()> {
+        self.pos += 1;
+        // the keys so far, to tell a duplicate from a key out of order
+        let mut keys: BTreeSet<&[u8]> = BTreeSet::new();
+        let mut last = None;
+        while self.peek()? != b'e' {
+            let offset = self.pos;
+            if !self.peek()?.is_ascii_digit() {
+                return self.fail(Error::KeyMustBeByteString);
+            }
+            let key = self.bytes()?;
+            // `Structure` doesn't report either
+            if self.mode != Mode::Structure {
+                if !keys.insert(key) {
+                    self.report(offset, ViolationKind::DuplicateKey)?;
+                } else if last.is_some_and(|last| key < last) {
+                    self.report(offset, ViolationKind::UnsortedKey)?;
+                }
+            }
+            last = Some(key);
+            self.value()?;
+        }
+        self.pos += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_canonical, validate, Checker, Mode, Violation, ViolationKind};
+    use crate::error::Error;
+
+    fn kinds(input: &[u8]) -> Vec<(usize, ViolationKind)> {
+        match validate(input) {
+            Ok(()) => Vec::new(),
+            Err(v) => v.into_iter().map(|v| (v.offset, v.kind)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_validate_ok() {
+        assert_eq!(Ok(()), validate(b"d1:ai-1e1:bl0:i0eee"));
+        assert_eq!(Ok(()), validate(include_bytes!("../debian.torrent")));
+    }
+
+    #[test]
+    fn test_validate_all_violations() {
+        assert_eq!(
+            vec![
+                (5, ViolationKind::LeadingZero),
+                (8, ViolationKind::UnsortedKey),
+                (11, ViolationKind::NegativeZero),
+                (15, ViolationKind::DuplicateKey),
+                (18, ViolationKind::LeadingZero),
+                (24, ViolationKind::TrailingBytes),
+            ],
+            kinds(b"d1:bi01e1:ai-0e1:b02:xye5:junk")
+        );
+        assert_eq!(
+            vec![(1, ViolationKind::IntegerOutOfRange)],
+            kinds(b"li9223372036854775808ee")
+        );
+    }
+
+    #[test]
+    fn test_validate_invalid() {
+        assert_eq!(
+            vec![
+                (7, ViolationKind::UnsortedKey),
+                (16, ViolationKind::Invalid(Error::Eof))
+            ],
+            kinds(b"d1:bi1e1:ai2e1:c")
+        );
+        assert_eq!(
+            vec![(1, ViolationKind::Invalid(Error::KeyMustBeByteString))],
+            kinds(b"di1ei2ee")
+        );
+        assert_eq!(
+            vec![(1, ViolationKind::Invalid(Error::IntegerOutOfRange))],
+            kinds(b"l99999999999999999999999:ae")
+        );
+        assert_eq!(
+            vec![(512, ViolationKind::Invalid(Error::DepthLimitExceeded))],
+            kinds(&b"d1:a".repeat(200))
+        );
+        assert_eq!(
+            "duplicate dictionary key at byte 7",
+            Violation {
+                offset: 7,
+                kind: ViolationKind::DuplicateKey
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn test_is_canonical() {
+        assert!(is_canonical(b"d1:ai-1e1:bl0:i0eee"));
+        assert!(is_canonical(include_bytes!("../debian.torrent")));
+        assert!(!is_canonical(b"d1:bi1e1:ai2ee"));
+        assert!(!is_canonical(b"d1:ai1e1:ai2ee"));
+        assert!(!is_canonical(b"i01e"));
+        assert!(!is_canonical(b"i1ei2e"));
+        assert!(!is_canonical(b"li1e"));
+        assert!(!is_canonical(&b"d1:a".repeat(100_000)));
+    }
+
+    #[test]
+    fn test_first_violation() {
+        let first = |input: &[u8]| {
+            let mut checker = Checker::new(input, Mode::First);
+            let _ = checker.document();
+            checker.violations.pop().map(|v| (v.offset, v.kind))
+        };
+        // a repeat of the key before is a duplicate, not out of order
+        assert_eq!(
+            Some((7, ViolationKind::DuplicateKey)),
+            first(b"d1:ai1e1:ai2ee")
+        );
+        assert_eq!(
+            Some((7, ViolationKind::UnsortedKey)),
+            first(b"d1:bi1e1:ai2ee")
+        );
+        assert_eq!(None, first(b"d1:ai1e1:bi2ee"));
+    }
+
+    #[test]
+    fn test_many_unsorted_keys() {
+        // keys out of order from the start, which mustn't take quadratic time
+        let mut input = b"d".to_vec();
+        for i in (0..100_000).rev() {
+            input.extend_from_slice(format!("6:{:06}i0e", i).as_bytes());
+        }
+        input.extend_from_slice(b"6:000000i0ee");
+        let violations = validate(&input).unwrap_err();
+        assert_eq!(100_000, violations.len());
+        assert_eq!(ViolationKind::DuplicateKey, violations.last().unwrap().kind);
+    }
+}
@@ -0,0 +1,66 @@
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use super::{byte_string, Map, Value};
+
+// Keeps generated documents small enough to round-trip quickly.
+const MAX_DEPTH: usize = 4;
+const MAX_LEN: usize = 8;
+
+impl<'a> Arbitrary<'a> for Value {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Value> {
+        arbitrary_value(u, MAX_DEPTH)
+    }
+}
+
+fn arbitrary_value(u: &mut Unstructured<'_>, depth: usize) -> Result<Value> {
+    // only scalars once the depth runs out
+    let kinds = if depth == 0 { 2 } else { 4 };
+    Ok(match u.choose_index(kinds)? {
+        0 => Value::Int(u.arbitrary()?),
+        1 => Value::Bytes(byte_string(u.arbitrary()?)),
+        2 => {
+            let len = u.int_in_range(0..=MAX_LEN)?;
+            let list = (0..len)
+                .map(|_| arbitrary_value(u, depth - 1))
+                .collect::<Result<_>>()?;
+            Value::List(list)
+        }
+        _ => {
+            let len = u.int_in_range(0..=MAX_LEN)?;
+            let mut dict = Map::new();
+            for _ in 0..len {
+                dict.insert(byte_string(u.arbitrary()?), arbitrary_value(u, depth - 1)?);
+            }
+            Value::Dict(dict)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use arbitrary::{Arbitrary, Unstructured};
+
+    use crate::de::from_bytes;
+    use crate::ser::to_bytes;
+    use crate::value::Value;
+
+    #[test]
+    fn test_arbitrary_round_trip() {
+        // a small xorshift generator stands in for fuzzer input
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        for _ in 0..200 {
+            let data: Vec<u8> = (0..256)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state as u8
+                })
+                .collect();
+            let v = Value::arbitrary(&mut Unstructured::new(&data)).unwrap();
+            let encoded = to_bytes(&v).unwrap();
+            assert_eq!(v.encoded_len(), encoded.len());
+            assert_eq!(v, from_bytes::<Value>(&encoded).unwrap());
+        }
+    }
+}
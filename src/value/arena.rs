@@ -0,0 +1,235 @@
+use bumpalo::collections::Vec as BumpVec;
+use bumpalo::Bump;
+use core::fmt;
+use serde::de::{self, DeserializeSeed, MapAccess, SeqAccess, Unexpected, Visitor};
+use serde::ser::{self, Serialize, SerializeMap};
+
+use super::ser::Bytes;
+use super::Value;
+use crate::de::Deserializer;
+use crate::error::Result;
+
+/// A value parsed into a bump arena by [`Value::parse_in`]. Byte strings
+/// borrow from the input and lists and dictionaries are slices in the arena,
+/// so dropping one is free and resetting the arena reclaims everything.
+///
+/// Dictionary entries keep their input order; lookups are linear and the
+/// last of a duplicated key wins, as when parsing a `Value`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BumpValue<'a> {
+    Int(i64),
+    Bytes(&'a [u8]),
+    List(&'a [BumpValue<'a>]),
+    Dict(&'a [(&'a [u8], BumpValue<'a>)]),
+}
+
+impl<'a> BumpValue<'a> {
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            BumpValue::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self {
+            BumpValue::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// The byte string as text, if it is valid UTF-8.
+    pub fn as_str(&self) -> Option<&'a str> {
+        self.as_bytes().and_then(|b| core::str::from_utf8(b).ok())
+    }
+
+    pub fn as_list(&self) -> Option<&'a [BumpValue<'a>]> {
+        match self {
+            BumpValue::List(l) => Some(l),
+            _ => None,
+        }
+    }
+
+    pub fn as_dict(&self) -> Option<&'a [(&'a [u8], BumpValue<'a>)]> {
+        match self {
+            BumpValue::Dict(d) => Some(d),
+            _ => None,
+        }
+    }
+
+    /// Looks up a dictionary key, `None` if it's missing or this isn't a
+    /// dictionary.
+    pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Option<&'a BumpValue<'a>> {
+        let key = key.as_ref();
+        self.as_dict()?
+            .iter()
+            .rev()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| v)
+    }
+
+    /// Copies the value out of the arena into an owned `Value`.
+    pub fn to_owned_value(&self) -> Value {
+        match *self {
+            BumpValue::Int(i) => Value::Int(i),
+            BumpValue::Bytes(b) => Value::Bytes(b.into()),
+            BumpValue::List(l) => Value::List(l.iter().map(BumpValue::to_owned_value).collect()),
+            BumpValue::Dict(d) => Value::Dict(
+                d.iter()
+                    .map(|(k, v)| ((*k).into(), v.to_owned_value()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl Value {
+    /// Parses `input` with every list and dictionary allocated in `bump`
+    /// instead of on the heap, for parsing many documents and throwing them
+    /// away. Byte strings borrow from `input`.
+    pub fn parse_in<'a>(bump: &'a Bump, input: &'a [u8]) -> Result<BumpValue<'a>> {
+        let mut de = Deserializer::from_bytes(input);
+        BumpSeed(bump)
+            .deserialize(&mut de)
+            .and_then(|v| de.end().map(|()| v))
+            .map_err(|e| de.locate(e))
+    }
+}
+
+impl Serialize for BumpValue<'_> {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        match self {
+            BumpValue::Int(i) => serializer.serialize_i64(*i),
+            BumpValue::Bytes(b) => serializer.serialize_bytes(b),
+            BumpValue::List(l) => serializer.collect_seq(l.iter()),
+            BumpValue::Dict(d) => {
+                let mut map = serializer.serialize_map(Some(d.len()))?;
+                for (k, v) in d.iter() {
+                    map.serialize_entry(&Bytes(k), v)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct BumpSeed<'a>(&'a Bump);
+
+impl<'de: 'a, 'a> DeserializeSeed<'de> for BumpSeed<'a> {
+    type Value = BumpValue<'a>;
+
+    fn deserialize<D>(self, deserializer: D) -> core::result::Result<BumpValue<'a>, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de: 'a, 'a> Visitor<'de> for BumpSeed<'a> {
+    type Value = BumpValue<'a>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any bencode value borrowed from the input")
+    }
+
+    fn visit_i64<E>(self, v: i64) -> core::result::Result<BumpValue<'a>, E> {
+        Ok(BumpValue::Int(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> core::result::Result<BumpValue<'a>, E> {
+        match i64::try_from(v) {
+            Ok(v) => Ok(BumpValue::Int(v)),
+            Err(_) => Err(E::invalid_value(Unexpected::Unsigned(v), &self)),
+        }
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> core::result::Result<BumpValue<'a>, E> {
+        Ok(BumpValue::Bytes(v.as_bytes()))
+    }
+
+    fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> core::result::Result<BumpValue<'a>, E> {
+        Ok(BumpValue::Bytes(v))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> core::result::Result<BumpValue<'a>, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut list = BumpVec::with_capacity_in(seq.size_hint().unwrap_or(0), self.0);
+        while let Some(v) = seq.next_element_seed(self)? {
+            list.push(v);
+        }
+        Ok(BumpValue::List(list.into_bump_slice()))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> core::result::Result<BumpValue<'a>, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut dict = BumpVec::with_capacity_in(map.size_hint().unwrap_or(0), self.0);
+        while let Some(k) = map.next_key_seed(self)? {
+            let k = match k {
+                BumpValue::Bytes(k) => k,
+                other => {
+                    return Err(de::Error::invalid_type(
+                        other.to_owned_value().unexpected(),
+                        &"a byte string",
+                    ))
+                }
+            };
+            dict.push((k, map.next_value_seed(self)?));
+        }
+        Ok(BumpValue::Dict(dict.into_bump_slice()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bumpalo::Bump;
+
+    use super::BumpValue;
+    use crate::error::Error;
+    use crate::ser::to_bytes;
+    use crate::value::Value;
+
+    #[test]
+    fn test_parse_in() {
+        let bump = Bump::new();
+        let input = b"d4:infod5:filesl1:a1:be6:lengthi5ee4:listli1e1:aee";
+        let v = Value::parse_in(&bump, input).unwrap();
+        let info = v.get("info").unwrap();
+        assert_eq!(Some(5), info.get("length").and_then(BumpValue::as_int));
+        let files = info.get("files").and_then(BumpValue::as_list).unwrap();
+        assert_eq!(Some("b"), files[1].as_str());
+        assert!(input
+            .as_ptr_range()
+            .contains(&files[0].as_bytes().unwrap().as_ptr()));
+        assert_eq!(None, v.get("missing"));
+        assert_eq!(to_bytes(&v).unwrap(), input);
+
+        let owned: Value = crate::from_bytes(input).unwrap();
+        assert_eq!(owned, v.to_owned_value());
+    }
+
+    #[test]
+    fn test_parse_in_errors() {
+        let bump = Bump::new();
+        assert_eq!(
+            Error::TrailingCharacters,
+            Value::parse_in(&bump, b"i1ei2e").unwrap_err()
+        );
+        assert!(Value::parse_in(&bump, b"di1ei2ee").is_err());
+        assert_eq!(
+            Some(2),
+            Value::parse_in(&bump, b"d1:ai1e1:ai2ee")
+                .unwrap()
+                .get("a")
+                .and_then(BumpValue::as_int)
+        );
+    }
+}
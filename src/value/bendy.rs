@@ -0,0 +1,70 @@
+//! Conversions to and from `bendy`'s `Value`. Both hold the same data, so
+//! neither way can fail; converting back gives an owned `Value<'static>`.
+
+use ::bendy::value::Value as Other;
+use alloc::borrow::Cow;
+
+use super::{byte_string, into_vec, Map, Value};
+
+impl From<Other<'_>> for Value {
+    fn from(v: Other<'_>) -> Value {
+        match v {
+            Other::Integer(i) => Value::Int(i),
+            Other::Bytes(b) => Value::Bytes(byte_string(b.into_owned())),
+            Other::List(l) => Value::List(l.into_iter().map(Value::from).collect()),
+            // already sorted, `bendy` keeps dictionaries in a `BTreeMap`
+            Other::Dict(d) => Value::Dict(
+                d.into_iter()
+                    .map(|(k, v)| (byte_string(k.into_owned()), Value::from(v)))
+                    .collect::<Map<_, _>>(),
+            ),
+        }
+    }
+}
+
+impl From<Value> for Other<'static> {
+    fn from(v: Value) -> Other<'static> {
+        match v {
+            Value::Int(i) => Other::Integer(i),
+            Value::Bytes(b) => Other::Bytes(Cow::Owned(into_vec(b))),
+            Value::List(l) => Other::List(l.into_iter().map(Other::from).collect()),
+            Value::Dict(d) => Other::Dict(
+                d.into_iter()
+                    .map(|(k, v)| (Cow::Owned(into_vec(k)), Other::from(v)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::collections::BTreeMap;
+
+    use ::bendy::value::Value as Other;
+
+    use crate::value::Value;
+    use crate::{from_bytes, to_bytes};
+
+    #[test]
+    fn test_convert() {
+        let input = include_bytes!("../../debian.torrent");
+        let value: Value = from_bytes(input).unwrap();
+        let other = Other::from(value.clone());
+        assert_eq!(value, Value::from(other.clone()));
+        assert_eq!(&input[..], to_bytes(&Value::from(other)).unwrap());
+
+        let name: &[u8] = b"name";
+        let other = Other::Dict(BTreeMap::from([
+            (Cow::Borrowed(name), Other::Bytes(Cow::Borrowed(b"x"))),
+            (
+                Cow::Borrowed(&b"b"[..]),
+                Other::List(vec![Other::Integer(-2)]),
+            ),
+        ]));
+        let value = Value::from(other.clone());
+        assert_eq!(bencode!({ "b": [-2], "name": "x" }), value);
+        assert_eq!(other, Other::from(value));
+    }
+}
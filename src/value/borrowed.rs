@@ -0,0 +1,227 @@
+use core::fmt;
+use serde::de::{self, Deserialize, MapAccess, SeqAccess, Unexpected, Visitor};
+use serde::ser::{self, Serialize, SerializeMap};
+
+use super::ser::Bytes;
+use super::{Map, Value};
+use alloc::vec::Vec;
+
+/// A `Value` whose byte strings borrow from the input instead of being
+/// copied, for inspecting large documents like torrents cheaply.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValueRef<'a> {
+    Int(i64),
+    Bytes(&'a [u8]),
+    List(Vec<ValueRef<'a>>),
+    Dict(Map<&'a [u8], ValueRef<'a>>),
+}
+
+impl<'a> ValueRef<'a> {
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            ValueRef::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self {
+            ValueRef::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// The byte string as text, if it is valid UTF-8.
+    pub fn as_str(&self) -> Option<&'a str> {
+        self.as_bytes().and_then(|b| core::str::from_utf8(b).ok())
+    }
+
+    pub fn as_list(&self) -> Option<&Vec<ValueRef<'a>>> {
+        match self {
+            ValueRef::List(l) => Some(l),
+            _ => None,
+        }
+    }
+
+    pub fn as_dict(&self) -> Option<&Map<&'a [u8], ValueRef<'a>>> {
+        match self {
+            ValueRef::Dict(d) => Some(d),
+            _ => None,
+        }
+    }
+
+    /// Looks up a dictionary key, `None` if it's missing or this isn't a
+    /// dictionary.
+    pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Option<&ValueRef<'a>> {
+        self.as_dict().and_then(|d| d.get(key.as_ref()))
+    }
+
+    /// Copies the borrowed byte strings into an owned `Value`.
+    pub fn into_owned(self) -> Value {
+        match self {
+            ValueRef::Int(i) => Value::Int(i),
+            ValueRef::Bytes(b) => Value::Bytes(b.into()),
+            ValueRef::List(l) => Value::List(l.into_iter().map(ValueRef::into_owned).collect()),
+            ValueRef::Dict(d) => Value::Dict(
+                d.into_iter()
+                    .map(|(k, v)| (k.into(), v.into_owned()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<ValueRef<'_>> for Value {
+    fn from(v: ValueRef<'_>) -> Value {
+        v.into_owned()
+    }
+}
+
+impl<'a> From<&'a Value> for ValueRef<'a> {
+    fn from(v: &'a Value) -> ValueRef<'a> {
+        match v {
+            Value::Int(i) => ValueRef::Int(*i),
+            Value::Bytes(b) => ValueRef::Bytes(b),
+            Value::List(l) => ValueRef::List(l.iter().map(ValueRef::from).collect()),
+            Value::Dict(d) => {
+                ValueRef::Dict(d.iter().map(|(k, v)| (&k[..], ValueRef::from(v))).collect())
+            }
+        }
+    }
+}
+
+impl Serialize for ValueRef<'_> {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        match self {
+            ValueRef::Int(i) => serializer.serialize_i64(*i),
+            ValueRef::Bytes(b) => serializer.serialize_bytes(b),
+            ValueRef::List(l) => serializer.collect_seq(l),
+            ValueRef::Dict(d) => {
+                let mut map = serializer.serialize_map(Some(d.len()))?;
+                for (k, v) in d {
+                    map.serialize_entry(&Bytes(k), v)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for ValueRef<'a> {
+    fn deserialize<D>(deserializer: D) -> core::result::Result<ValueRef<'a>, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ValueRefVisitor)
+    }
+}
+
+struct ValueRefVisitor;
+
+impl<'de> Visitor<'de> for ValueRefVisitor {
+    type Value = ValueRef<'de>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any bencode value borrowed from the input")
+    }
+
+    fn visit_i64<E>(self, v: i64) -> core::result::Result<ValueRef<'de>, E> {
+        Ok(ValueRef::Int(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> core::result::Result<ValueRef<'de>, E> {
+        match i64::try_from(v) {
+            Ok(v) => Ok(ValueRef::Int(v)),
+            Err(_) => Err(E::invalid_value(Unexpected::Unsigned(v), &self)),
+        }
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> core::result::Result<ValueRef<'de>, E> {
+        Ok(ValueRef::Bytes(v.as_bytes()))
+    }
+
+    fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> core::result::Result<ValueRef<'de>, E> {
+        Ok(ValueRef::Bytes(v))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> core::result::Result<ValueRef<'de>, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut list = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(v) = seq.next_element()? {
+            list.push(v);
+        }
+        Ok(ValueRef::List(list))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> core::result::Result<ValueRef<'de>, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut dict = Map::new();
+        while let Some((KeyRef(k), v)) = map.next_entry()? {
+            dict.insert(k, v);
+        }
+        Ok(ValueRef::Dict(dict))
+    }
+}
+
+struct KeyRef<'a>(&'a [u8]);
+
+impl<'de: 'a, 'a> Deserialize<'de> for KeyRef<'a> {
+    fn deserialize<D>(deserializer: D) -> core::result::Result<KeyRef<'a>, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        match ValueRef::deserialize(deserializer)? {
+            ValueRef::Bytes(b) => Ok(KeyRef(b)),
+            other => Err(de::Error::invalid_type(
+                Value::from(other).unexpected(),
+                &"a byte string",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ValueRef;
+    use crate::de::from_bytes;
+    use crate::ser::to_bytes;
+    use crate::value::Value;
+
+    #[test]
+    fn test_value_ref() {
+        let input = b"d4:infod6:lengthi5e6:pieces4:\x00\x01\x02\x03e4:listli1e1:aee";
+        let v: ValueRef = from_bytes(input).unwrap();
+        let pieces = v.get("info").and_then(|i| i.get("pieces")).unwrap();
+        let pieces = pieces.as_bytes().unwrap();
+        assert_eq!(&b"\x00\x01\x02\x03"[..], pieces);
+        assert!(input.as_ptr_range().contains(&pieces.as_ptr()));
+        assert_eq!(
+            Some(5),
+            v.get("info")
+                .and_then(|i| i.get(b"length"))
+                .and_then(ValueRef::as_int)
+        );
+        assert_eq!(None, v.get("missing"));
+        assert_eq!(to_bytes(&v).unwrap(), input);
+    }
+
+    #[test]
+    fn test_value_ref_owned() {
+        let v: Value = from_bytes(b"d1:ali1e1:bee").unwrap();
+        let r = ValueRef::from(&v);
+        assert_eq!(
+            Some("b"),
+            r.get("a")
+                .and_then(|a| a.as_list())
+                .and_then(|l| l[1].as_str())
+        );
+        assert_eq!(v, r.into_owned());
+    }
+}
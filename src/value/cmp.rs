@@ -0,0 +1,138 @@
+use core::cmp::Ordering;
+use core::hash::{Hash, Hasher};
+
+use super::{ByteString, Map, Value};
+
+// Dictionaries compare and hash by their entries in key order, so with
+// `preserve_order` two dictionaries that are equal also compare and hash
+// equal.
+fn entries(d: &Map<ByteString, Value>) -> impl Iterator<Item = (&ByteString, &Value)> {
+    #[cfg(not(feature = "preserve_order"))]
+    return d.iter();
+    #[cfg(feature = "preserve_order")]
+    {
+        let mut entries: Vec<_> = d.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        entries.into_iter()
+    }
+}
+
+impl Value {
+    // the order values of different types sort in, with the sentinel for
+    // missing index results last
+    fn rank(&self) -> u8 {
+        match self {
+            _ if self.is_missing() => 4,
+            Value::Int(_) => 0,
+            Value::Bytes(_) => 1,
+            Value::List(_) => 2,
+            Value::Dict(_) => 3,
+        }
+    }
+}
+
+/// Values are equal when they're the same type with equal contents. What
+/// indexing gives for a missing path is only equal to itself, not to an
+/// empty list.
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Bytes(a), Value::Bytes(b)) => a == b,
+            (Value::List(a), Value::List(b)) => self.is_missing() == other.is_missing() && a == b,
+            (Value::Dict(a), Value::Dict(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// Integers sort before byte strings, then lists, then dictionaries; values
+/// of the same type compare by their contents.
+impl Ord for Value {
+    fn cmp(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            _ if self.is_missing() || other.is_missing() => self.rank().cmp(&other.rank()),
+            (Value::Int(a), Value::Int(b)) => a.cmp(b),
+            (Value::Bytes(a), Value::Bytes(b)) => a.cmp(b),
+            (Value::List(a), Value::List(b)) => a.cmp(b),
+            (Value::Dict(a), Value::Dict(b)) => entries(a).cmp(entries(b)),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Value) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+        match self {
+            Value::Int(i) => i.hash(state),
+            Value::Bytes(b) => b.hash(state),
+            Value::List(l) => l.hash(state),
+            Value::Dict(d) => {
+                state.write_usize(d.len());
+                entries(d).for_each(|entry| entry.hash(state));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashSet;
+    use std::hash::{Hash, Hasher};
+
+    use crate::value::Value;
+
+    fn hash(v: &Value) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        v.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_ord() {
+        let mut values = vec![
+            bencode!({ "b": 1 }),
+            bencode!({ "a": 2 }),
+            bencode!([1, 2]),
+            bencode!([1]),
+            bencode!("b"),
+            bencode!("a"),
+            bencode!(2),
+            bencode!(-1),
+        ];
+        values.sort();
+        assert_eq!(
+            vec![
+                bencode!(-1),
+                bencode!(2),
+                bencode!("a"),
+                bencode!("b"),
+                bencode!([1]),
+                bencode!([1, 2]),
+                bencode!({ "a": 2 }),
+                bencode!({ "b": 1 }),
+            ],
+            values
+        );
+    }
+
+    #[test]
+    fn test_hash() {
+        let a = bencode!({ "x": 1, "y": [1, "z"] });
+        let b = bencode!({ "y": [1, "z"], "x": 1 });
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+        assert_eq!(std::cmp::Ordering::Equal, a.cmp(&b));
+
+        let set: HashSet<Value> = [a, b, bencode!({ "x": 1 })].into_iter().collect();
+        assert_eq!(2, set.len());
+    }
+}
@@ -0,0 +1,677 @@
+use alloc::vec;
+use core::fmt;
+use core::slice;
+use serde::de::value::BorrowedBytesDeserializer;
+use serde::de::{
+    self, Deserialize, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess,
+    SeqAccess, Unexpected, VariantAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+
+use super::{byte_string, into_vec, ByteString, Map, Value};
+use crate::error::{Error, Result};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any bencode value")
+    }
+
+    fn visit_i64<E>(self, v: i64) -> core::result::Result<Value, E> {
+        Ok(Value::Int(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> core::result::Result<Value, E> {
+        match i64::try_from(v) {
+            Ok(v) => Ok(Value::Int(v)),
+            Err(_) => Err(E::invalid_value(Unexpected::Unsigned(v), &self)),
+        }
+    }
+
+    fn visit_str<E>(self, v: &str) -> core::result::Result<Value, E> {
+        Ok(Value::Bytes(v.as_bytes().into()))
+    }
+
+    fn visit_string<E>(self, v: String) -> core::result::Result<Value, E> {
+        Ok(Value::Bytes(byte_string(v.into_bytes())))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> core::result::Result<Value, E> {
+        Ok(Value::Bytes(v.into()))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> core::result::Result<Value, E> {
+        Ok(Value::Bytes(byte_string(v)))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> core::result::Result<Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut list = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(v) = seq.next_element()? {
+            list.push(v);
+        }
+        Ok(Value::List(list))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> core::result::Result<Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut dict = Map::new();
+        while let Some((KeyBuf(k), v)) = map.next_entry()? {
+            dict.insert(k, v);
+        }
+        Ok(Value::Dict(dict))
+    }
+}
+
+// Dictionary keys are byte strings, but `Vec<u8>` only deserializes from a
+// sequence.
+struct KeyBuf(ByteString);
+
+impl<'de> Deserialize<'de> for KeyBuf {
+    fn deserialize<D>(deserializer: D) -> core::result::Result<KeyBuf, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        match Value::deserialize(deserializer)? {
+            Value::Bytes(b) => Ok(KeyBuf(b)),
+            other => Err(de::Error::invalid_type(
+                other.unexpected(),
+                &"a byte string",
+            )),
+        }
+    }
+}
+
+/// Interprets a `Value` as an instance of type `T`.
+pub fn from_value<T>(value: Value) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    T::deserialize(value)
+}
+
+impl Value {
+    pub(super) fn unexpected(&self) -> Unexpected<'_> {
+        match self {
+            Value::Int(i) => Unexpected::Signed(*i),
+            Value::Bytes(b) => Unexpected::Bytes(b),
+            Value::List(_) => Unexpected::Seq,
+            Value::Dict(_) => Unexpected::Map,
+        }
+    }
+}
+
+fn visit_list<'de, V>(list: Vec<Value>, visitor: V) -> Result<V::Value>
+where
+    V: Visitor<'de>,
+{
+    let mut seq = SeqReader {
+        iter: list.into_iter(),
+    };
+    let value = visitor.visit_seq(&mut seq)?;
+    match seq.iter.len() {
+        0 => Ok(value),
+        _ => Err(Error::ExpectedListEnd),
+    }
+}
+
+fn visit_dict<'de, V>(dict: Map<ByteString, Value>, visitor: V) -> Result<V::Value>
+where
+    V: Visitor<'de>,
+{
+    let mut map = MapReader {
+        iter: dict.into_iter(),
+        value: None,
+    };
+    let value = visitor.visit_map(&mut map)?;
+    match map.iter.len() {
+        0 => Ok(value),
+        _ => Err(Error::ExpectedDictEnd),
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Int(i) => visitor.visit_i64(i),
+            // visited as strings when they're valid UTF-8, like `from_bytes`
+            Value::Bytes(b) => match String::from_utf8(into_vec(b)) {
+                Ok(s) => visitor.visit_string(s),
+                Err(e) => visitor.visit_byte_buf(e.into_bytes()),
+            },
+            Value::List(l) => visit_list(l, visitor),
+            Value::Dict(d) => visit_dict(d, visitor),
+        }
+    }
+
+    // bools are written as i0e and i1e
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Int(0) => visitor.visit_bool(false),
+            Value::Int(1) => visitor.visit_bool(true),
+            _ => Err(de::Error::invalid_type(self.unexpected(), &visitor)),
+        }
+    }
+
+    // a value that is present is always `Some`; `None` is never written
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // unit variants are a byte string, the others a single-key dictionary
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Bytes(variant) => visitor.visit_enum(EnumReader {
+                variant,
+                value: None,
+            }),
+            Value::Dict(dict) if dict.len() == 1 => {
+                let (variant, value) = dict.into_iter().next().unwrap();
+                visitor.visit_enum(EnumReader {
+                    variant,
+                    value: Some(value),
+                })
+            }
+            other => Err(de::Error::invalid_type(other.unexpected(), &"enum")),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct SeqReader {
+    iter: vec::IntoIter<Value>,
+}
+
+impl<'de> SeqAccess<'de> for SeqReader {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some(value) => seed.deserialize(value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct MapReader {
+    iter: <Map<ByteString, Value> as IntoIterator>::IntoIter,
+    value: Option<Value>,
+}
+
+impl<'de> MapAccess<'de> for MapReader {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(Value::Bytes(key)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        match self.value.take() {
+            Some(value) => seed.deserialize(value),
+            None => Err(de::Error::custom("value is missing")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct EnumReader {
+    variant: ByteString,
+    value: Option<Value>,
+}
+
+impl<'de> EnumAccess<'de> for EnumReader {
+    type Error = Error;
+    type Variant = VariantReader;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, VariantReader)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(Value::Bytes(self.variant))?;
+        Ok((variant, VariantReader { value: self.value }))
+    }
+}
+
+struct VariantReader {
+    value: Option<Value>,
+}
+
+impl<'de> VariantAccess<'de> for VariantReader {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        match self.value {
+            None => Ok(()),
+            Some(value) => Err(de::Error::invalid_type(value.unexpected(), &"unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.value {
+            Some(value) => seed.deserialize(value),
+            None => Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"newtype variant",
+            )),
+        }
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Some(Value::List(list)) => visit_list(list, visitor),
+            Some(value) => Err(de::Error::invalid_type(
+                value.unexpected(),
+                &"tuple variant",
+            )),
+            None => Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"tuple variant",
+            )),
+        }
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Some(Value::Dict(dict)) => visit_dict(dict, visitor),
+            Some(value) => Err(de::Error::invalid_type(
+                value.unexpected(),
+                &"struct variant",
+            )),
+            None => Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"struct variant",
+            )),
+        }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value {
+    type Deserializer = Value;
+
+    fn into_deserializer(self) -> Value {
+        self
+    }
+}
+
+// Deserializing from a reference borrows byte strings from the `Value`
+// instead of copying them.
+
+fn visit_list_ref<'de, V>(list: &'de [Value], visitor: V) -> Result<V::Value>
+where
+    V: Visitor<'de>,
+{
+    let mut seq = SeqRefReader { iter: list.iter() };
+    let value = visitor.visit_seq(&mut seq)?;
+    match seq.iter.len() {
+        0 => Ok(value),
+        _ => Err(Error::ExpectedListEnd),
+    }
+}
+
+fn visit_dict_ref<'de, V>(dict: &'de Map<ByteString, Value>, visitor: V) -> Result<V::Value>
+where
+    V: Visitor<'de>,
+{
+    let mut map = MapRefReader {
+        iter: dict.iter(),
+        value: None,
+    };
+    let value = visitor.visit_map(&mut map)?;
+    match map.iter.len() {
+        0 => Ok(value),
+        _ => Err(Error::ExpectedDictEnd),
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &'de Value {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Int(i) => visitor.visit_i64(*i),
+            Value::Bytes(b) => match core::str::from_utf8(b) {
+                Ok(s) => visitor.visit_borrowed_str(s),
+                Err(_) => visitor.visit_borrowed_bytes(b),
+            },
+            Value::List(l) => visit_list_ref(l, visitor),
+            Value::Dict(d) => visit_dict_ref(d, visitor),
+        }
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Int(0) => visitor.visit_bool(false),
+            Value::Int(1) => visitor.visit_bool(true),
+            _ => Err(de::Error::invalid_type(self.unexpected(), &visitor)),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Bytes(variant) => visitor.visit_enum(EnumRefReader {
+                variant,
+                value: None,
+            }),
+            Value::Dict(dict) if dict.len() == 1 => {
+                let (variant, value) = dict.iter().next().unwrap();
+                visitor.visit_enum(EnumRefReader {
+                    variant,
+                    value: Some(value),
+                })
+            }
+            other => Err(de::Error::invalid_type(other.unexpected(), &"enum")),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for &'de Value {
+    type Deserializer = &'de Value;
+
+    fn into_deserializer(self) -> &'de Value {
+        self
+    }
+}
+
+struct SeqRefReader<'de> {
+    iter: slice::Iter<'de, Value>,
+}
+
+impl<'de> SeqAccess<'de> for SeqRefReader<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some(value) => seed.deserialize(value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct MapRefReader<'de> {
+    iter: <&'de Map<ByteString, Value> as IntoIterator>::IntoIter,
+    value: Option<&'de Value>,
+}
+
+impl<'de> MapAccess<'de> for MapRefReader<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(BorrowedBytesDeserializer::<Error>::new(key))
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        match self.value.take() {
+            Some(value) => seed.deserialize(value),
+            None => Err(de::Error::custom("value is missing")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct EnumRefReader<'de> {
+    variant: &'de [u8],
+    value: Option<&'de Value>,
+}
+
+impl<'de> EnumAccess<'de> for EnumRefReader<'de> {
+    type Error = Error;
+    type Variant = VariantRefReader<'de>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, VariantRefReader<'de>)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(BorrowedBytesDeserializer::<Error>::new(self.variant))?;
+        Ok((variant, VariantRefReader { value: self.value }))
+    }
+}
+
+struct VariantRefReader<'de> {
+    value: Option<&'de Value>,
+}
+
+impl<'de> VariantAccess<'de> for VariantRefReader<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        match self.value {
+            None => Ok(()),
+            Some(value) => Err(de::Error::invalid_type(value.unexpected(), &"unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.value {
+            Some(value) => seed.deserialize(value),
+            None => Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"newtype variant",
+            )),
+        }
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Some(Value::List(list)) => visit_list_ref(list, visitor),
+            Some(value) => Err(de::Error::invalid_type(
+                value.unexpected(),
+                &"tuple variant",
+            )),
+            None => Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"tuple variant",
+            )),
+        }
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Some(Value::Dict(dict)) => visit_dict_ref(dict, visitor),
+            Some(value) => Err(de::Error::invalid_type(
+                value.unexpected(),
+                &"struct variant",
+            )),
+            None => Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"struct variant",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::de::IntoDeserializer;
+    use serde::Deserialize;
+
+    use crate::de::from_bytes;
+    use crate::value::Value;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Info<'a> {
+        name: &'a str,
+        pieces: &'a [u8],
+        length: i64,
+        kind: Kind,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Kind {
+        Single,
+        Multi { count: u32 },
+    }
+
+    #[test]
+    fn test_deserialize_from_ref() {
+        let v: Value =
+            from_bytes(b"d4:infod4:kind6:Single6:lengthi5e4:name1:a6:pieces2:\x00\xffee").unwrap();
+        let info = Info::deserialize(&v["info"]).unwrap();
+        assert_eq!(
+            Info {
+                name: "a",
+                pieces: b"\x00\xff",
+                length: 5,
+                kind: Kind::Single,
+            },
+            info
+        );
+
+        let kind = Kind::deserialize(&bencode!({ "Multi": { "count": 2 } })).unwrap();
+        assert_eq!(Kind::Multi { count: 2 }, kind);
+    }
+
+    #[test]
+    fn test_into_deserializer() {
+        let v = bencode!([1, 2, 3]);
+        let list = Vec::<u8>::deserialize((&v).into_deserializer()).unwrap();
+        assert_eq!(vec![1, 2, 3], list);
+        let list = Vec::<u8>::deserialize(v.into_deserializer()).unwrap();
+        assert_eq!(vec![1, 2, 3], list);
+    }
+}
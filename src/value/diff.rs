@@ -0,0 +1,161 @@
+use core::fmt;
+
+use super::Value;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// One difference found by [`diff`], at a path in the same form
+/// `Value::pointer` takes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Difference<'a> {
+    Added {
+        path: String,
+        value: &'a Value,
+    },
+    Removed {
+        path: String,
+        value: &'a Value,
+    },
+    Changed {
+        path: String,
+        old: &'a Value,
+        new: &'a Value,
+    },
+}
+
+impl Difference<'_> {
+    pub fn path(&self) -> &str {
+        match self {
+            Difference::Added { path, .. }
+            | Difference::Removed { path, .. }
+            | Difference::Changed { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for Difference<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Difference::Added { path, value } => write!(f, "added {}: {}", path, value),
+            Difference::Removed { path, value } => write!(f, "removed {}: {}", path, value),
+            Difference::Changed { path, old, new } => {
+                write!(f, "changed {}: {} -> {}", path, old, new)
+            }
+        }
+    }
+}
+
+/// Lists the paths that were added, removed or changed going from `a` to
+/// `b`. Dictionaries are compared key by key and lists position by position;
+/// anything else that isn't equal is reported as changed.
+pub fn diff<'a>(a: &'a Value, b: &'a Value) -> Vec<Difference<'a>> {
+    let mut differences = Vec::new();
+    diff_into(&mut differences, String::new(), a, b);
+    differences
+}
+
+fn diff_into<'a>(out: &mut Vec<Difference<'a>>, path: String, a: &'a Value, b: &'a Value) {
+    match (a, b) {
+        (Value::Dict(a), Value::Dict(b)) => {
+            for (key, old) in a {
+                let path = child_path(&path, &String::from_utf8_lossy(key));
+                match b.get(key) {
+                    Some(new) => diff_into(out, path, old, new),
+                    None => out.push(Difference::Removed { path, value: old }),
+                }
+            }
+            for (key, new) in b {
+                if !a.contains_key(key) {
+                    let path = child_path(&path, &String::from_utf8_lossy(key));
+                    out.push(Difference::Added { path, value: new });
+                }
+            }
+        }
+        (Value::List(a), Value::List(b)) => {
+            for (i, old) in a.iter().enumerate() {
+                let path = child_path(&path, &i.to_string());
+                match b.get(i) {
+                    Some(new) => diff_into(out, path, old, new),
+                    None => out.push(Difference::Removed { path, value: old }),
+                }
+            }
+            for (i, new) in b.iter().enumerate().skip(a.len()) {
+                let path = child_path(&path, &i.to_string());
+                out.push(Difference::Added { path, value: new });
+            }
+        }
+        (old, new) if old != new => out.push(Difference::Changed { path, old, new }),
+        _ => {}
+    }
+}
+
+fn child_path(parent: &str, token: &str) -> String {
+    format!("{}/{}", parent, token.replace('~', "~0").replace('/', "~1"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, Difference};
+    use crate::value::Value;
+
+    #[test]
+    fn test_diff() {
+        let a = bencode!({
+            "announce": "http://a",
+            "info": { "files": [1, 2, 3], "name": "x" },
+            "url-list": ["u"],
+            "a/b": 1,
+        });
+        let b = bencode!({
+            "announce": "http://b",
+            "info": { "files": [1, 5], "name": "x" },
+            "comment": "new",
+            "a/b": 1,
+        });
+        let differences = diff(&a, &b);
+        let paths: Vec<&str> = differences.iter().map(Difference::path).collect();
+        assert_eq!(
+            vec![
+                "/announce",
+                "/info/files/1",
+                "/info/files/2",
+                "/url-list",
+                "/comment"
+            ],
+            paths
+        );
+        assert_eq!(
+            Difference::Changed {
+                path: "/announce".to_string(),
+                old: &a["announce"],
+                new: &b["announce"],
+            },
+            differences[0]
+        );
+        assert_eq!("removed /info/files/2: 3", differences[2].to_string());
+        assert_eq!(Some(&b["comment"]), b.pointer(differences[4].path()));
+        assert!(diff(&a, &a).is_empty());
+    }
+
+    #[test]
+    fn test_diff_type_change() {
+        let a = bencode!({ "k~": [1] });
+        let b = bencode!({ "k~": 1 });
+        assert_eq!(
+            vec![Difference::Changed {
+                path: "/k~0".to_string(),
+                old: &a["k~"],
+                new: &Value::Int(1),
+            }],
+            diff(&a, &b)
+        );
+        assert_eq!(
+            vec![Difference::Added {
+                path: "/0".to_string(),
+                value: &Value::Int(1),
+            }],
+            diff(&Value::List(vec![]), &Value::List(vec![Value::Int(1)]))
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use core::fmt::{self, Write};
+
+use super::Value;
+
+// Byte strings up to this long are shown as text when they're printable
+// UTF-8.
+const MAX_TEXT: usize = 128;
+// Only this many bytes of longer or binary strings are shown, as hex.
+const MAX_HEX: usize = 20;
+
+/// Renders on one line, or indented with `{:#}`. Byte strings show as quoted
+/// text when they're short printable UTF-8 and as truncated hex otherwise.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let indent = if f.alternate() { Some(0) } else { None };
+        write_value(f, self, indent)
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+fn write_value(f: &mut fmt::Formatter, value: &Value, indent: Option<usize>) -> fmt::Result {
+    match value {
+        Value::Int(i) => write!(f, "{}", i),
+        Value::Bytes(b) => write_bytes(f, b),
+        Value::List(l) if l.is_empty() => f.write_str("[]"),
+        Value::List(l) => {
+            f.write_char('[')?;
+            for (i, v) in l.iter().enumerate() {
+                write_separator(f, i, indent)?;
+                write_value(f, v, indent.map(|n| n + 1))?;
+            }
+            write_close(f, ']', indent)
+        }
+        Value::Dict(d) if d.is_empty() => f.write_str("{}"),
+        Value::Dict(d) => {
+            f.write_char('{')?;
+            for (i, (k, v)) in d.iter().enumerate() {
+                write_separator(f, i, indent)?;
+                write_bytes(f, k)?;
+                f.write_str(": ")?;
+                write_value(f, v, indent.map(|n| n + 1))?;
+            }
+            write_close(f, '}', indent)
+        }
+    }
+}
+
+fn write_separator(f: &mut fmt::Formatter, i: usize, indent: Option<usize>) -> fmt::Result {
+    match indent {
+        Some(n) => {
+            if i > 0 {
+                f.write_char(',')?;
+            }
+            f.write_char('\n')?;
+            write_indent(f, n + 1)
+        }
+        None if i > 0 => f.write_str(", "),
+        None => Ok(()),
+    }
+}
+
+fn write_close(f: &mut fmt::Formatter, close: char, indent: Option<usize>) -> fmt::Result {
+    if let Some(n) = indent {
+        f.write_char('\n')?;
+        write_indent(f, n)?;
+    }
+    f.write_char(close)
+}
+
+fn write_indent(f: &mut fmt::Formatter, n: usize) -> fmt::Result {
+    for _ in 0..n {
+        f.write_str("  ")?;
+    }
+    Ok(())
+}
+
+pub(crate) fn write_bytes<W: Write>(f: &mut W, b: &[u8]) -> fmt::Result {
+    if b.len() <= MAX_TEXT {
+        if let Ok(s) = core::str::from_utf8(b) {
+            if !s.chars().any(|c| c.is_control() && !c.is_whitespace()) {
+                return write!(f, "{:?}", s);
+            }
+        }
+    }
+    f.write_char('<')?;
+    for byte in b.iter().take(MAX_HEX) {
+        write!(f, "{:02x}", byte)?;
+    }
+    if b.len() > MAX_HEX {
+        write!(f, "...> ({} bytes)", b.len())
+    } else {
+        f.write_char('>')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::value::Value;
+
+    #[test]
+    fn test_display() {
+        let v = bencode!({
+            "comment": "x".repeat(129),
+            "info": { "id": &b"\x00\x01"[..], "name": "a\"b", "pieces": vec![0xabu8; 40] },
+            "list": [1, [], {}],
+        });
+        let hex = "ab".repeat(20);
+        let long = "78".repeat(20);
+        assert_eq!(
+            format!(
+                "{{\"comment\": <{long}...> (129 bytes), \"info\": {{\"id\": <0001>, \
+                 \"name\": \"a\\\"b\", \"pieces\": <{hex}...> (40 bytes)}}, \"list\": [1, [], {{}}]}}"
+            ),
+            v.to_string()
+        );
+        assert_eq!(v.to_string(), format!("{:?}", v));
+    }
+
+    #[test]
+    fn test_display_pretty() {
+        let v = bencode!({ "a": [1, "b"], "c": {} });
+        assert_eq!(
+            "{\n  \"a\": [\n    1,\n    \"b\"\n  ],\n  \"c\": {}\n}",
+            format!("{:#}", v)
+        );
+        assert_eq!(format!("{:#}", v), format!("{:#?}", v));
+        assert_eq!("[]", format!("{:#}", Value::List(vec![])));
+    }
+}
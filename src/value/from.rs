@@ -0,0 +1,223 @@
+use alloc::borrow::Cow;
+
+use super::{byte_string, ByteString, Map, Value};
+use crate::error::Error;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+macro_rules! from_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for Value {
+                fn from(i: $ty) -> Value {
+                    Value::Int(i.into())
+                }
+            }
+        )*
+    };
+}
+
+from_integer!(i8, i16, i32, i64, u8, u16, u32);
+
+// these can be out of range for an `i64`
+macro_rules! try_from_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl TryFrom<$ty> for Value {
+                type Error = Error;
+
+                fn try_from(i: $ty) -> Result<Value, Error> {
+                    i64::try_from(i)
+                        .map(Value::Int)
+                        .map_err(|_| Error::IntegerOutOfRange)
+                }
+            }
+        )*
+    };
+}
+
+try_from_integer!(u64, usize, isize, i128, u128);
+
+// written as i0e and i1e, like the serializer does
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Int(b.into())
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Value {
+        Value::Bytes(s.as_bytes().into())
+    }
+}
+
+impl From<&String> for Value {
+    fn from(s: &String) -> Value {
+        Value::from(s.as_str())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Value {
+        Value::Bytes(byte_string(s.into_bytes()))
+    }
+}
+
+impl From<&[u8]> for Value {
+    fn from(b: &[u8]) -> Value {
+        Value::Bytes(b.into())
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(b: Vec<u8>) -> Value {
+        Value::Bytes(byte_string(b))
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for Value {
+    fn from(b: [u8; N]) -> Value {
+        Value::Bytes(b.into())
+    }
+}
+
+impl<const N: usize> From<&[u8; N]> for Value {
+    fn from(b: &[u8; N]) -> Value {
+        Value::Bytes(b.into())
+    }
+}
+
+impl From<Cow<'_, str>> for Value {
+    fn from(s: Cow<'_, str>) -> Value {
+        Value::from(s.into_owned())
+    }
+}
+
+impl From<Cow<'_, [u8]>> for Value {
+    fn from(b: Cow<'_, [u8]>) -> Value {
+        Value::Bytes(byte_string(b.into_owned()))
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(l: Vec<Value>) -> Value {
+        Value::List(l)
+    }
+}
+
+impl From<&[Value]> for Value {
+    fn from(l: &[Value]) -> Value {
+        Value::List(l.to_vec())
+    }
+}
+
+impl From<Map<ByteString, Value>> for Value {
+    fn from(d: Map<ByteString, Value>) -> Value {
+        Value::Dict(d)
+    }
+}
+
+impl<T: Into<Value>> FromIterator<T> for Value {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Value {
+        Value::List(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<K: Into<ByteString>, V: Into<Value>> FromIterator<(K, V)> for Value {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Value {
+        Value::Dict(
+            iter.into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+}
+
+/// Appends to a list. Panics if this isn't a list.
+impl<T: Into<Value>> Extend<T> for Value {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        match self {
+            Value::List(l) => l.extend(iter.into_iter().map(Into::into)),
+            _ => panic!("cannot extend a non-list value with values"),
+        }
+    }
+}
+
+/// Inserts into a dictionary. Panics if this isn't a dictionary.
+impl<K: Into<ByteString>, V: Into<Value>> Extend<(K, V)> for Value {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        match self {
+            Value::Dict(d) => d.extend(iter.into_iter().map(|(k, v)| (k.into(), v.into()))),
+            _ => panic!("cannot extend a non-dictionary value with entries"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use crate::error::Error;
+    use crate::value::{ByteString, Map, Value};
+
+    fn bytes(b: &[u8]) -> Value {
+        Value::from(b)
+    }
+
+    #[test]
+    fn test_from() {
+        assert_eq!(Value::Int(-1), Value::from(-1i8));
+        assert_eq!(Value::Int(7), Value::from(7u32));
+        assert_eq!(Value::Int(1), Value::from(true));
+        assert_eq!(bytes(b"a"), Value::from("a"));
+        assert_eq!(bytes(b"a"), Value::from(&"a".to_string()));
+        assert_eq!(bytes(b"a"), Value::from(Cow::Borrowed("a")));
+        assert_eq!(bytes(b"ab"), Value::from(*b"ab"));
+        assert_eq!(bytes(b"ab"), Value::from(b"ab"));
+        assert_eq!(bytes(b"ab"), Value::from(Cow::Borrowed(&b"ab"[..])));
+        assert_eq!(
+            Value::List(vec![Value::Int(1)]),
+            Value::from(&[Value::Int(1)][..])
+        );
+        let dict = Map::from([(ByteString::from(b"k"), Value::Int(1))]);
+        assert_eq!(Value::Dict(dict.clone()), Value::from(dict));
+    }
+
+    #[test]
+    fn test_from_iter() {
+        let list: Value = (1..=3).collect();
+        assert_eq!(bencode!([1, 2, 3]), list);
+        let dict: Value = ["a", "b"]
+            .into_iter()
+            .map(|k| (k, k.len() as i64))
+            .collect();
+        assert_eq!(bencode!({ "a": 1, "b": 1 }), dict);
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut list = bencode!([1]);
+        list.extend(vec!["x", "y"]);
+        assert_eq!(bencode!([1, "x", "y"]), list);
+        let mut dict = bencode!({ "a": 1 });
+        dict.extend([
+            (b"b".to_vec(), Value::Int(2)),
+            (b"a".to_vec(), Value::Int(3)),
+        ]);
+        assert_eq!(bencode!({ "a": 3, "b": 2 }), dict);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_extend_non_list() {
+        Value::Int(1).extend([1]);
+    }
+
+    #[test]
+    fn test_try_from() {
+        assert_eq!(Ok(Value::Int(5)), Value::try_from(5u64));
+        assert_eq!(Ok(Value::Int(-5)), Value::try_from(-5isize));
+        assert_eq!(Err(Error::IntegerOutOfRange), Value::try_from(u64::MAX));
+        assert_eq!(Err(Error::IntegerOutOfRange), Value::try_from(i128::MIN));
+    }
+}
@@ -0,0 +1,254 @@
+use core::ops;
+
+use super::{Map, Value};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Something that can index into a `Value`: `usize` for lists, and strings
+/// or byte strings for dictionaries.
+pub trait Index: private::Sealed {
+    #[doc(hidden)]
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value>;
+
+    #[doc(hidden)]
+    fn index_into_mut<'v>(&self, v: &'v mut Value) -> Option<&'v mut Value>;
+
+    // used by IndexMut, which inserts missing dictionary keys
+    #[doc(hidden)]
+    fn index_or_insert<'v>(&self, v: &'v mut Value) -> &'v mut Value;
+}
+
+mod private {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    pub trait Sealed {}
+    impl Sealed for usize {}
+    impl Sealed for str {}
+    impl Sealed for String {}
+    impl Sealed for [u8] {}
+    impl Sealed for Vec<u8> {}
+    impl<const N: usize> Sealed for [u8; N] {}
+    impl<T: ?Sized + Sealed> Sealed for &T {}
+}
+
+impl Index for usize {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        match v {
+            Value::List(l) => l.get(*self),
+            _ => None,
+        }
+    }
+
+    fn index_into_mut<'v>(&self, v: &'v mut Value) -> Option<&'v mut Value> {
+        match v {
+            Value::List(l) => l.get_mut(*self),
+            _ => None,
+        }
+    }
+
+    fn index_or_insert<'v>(&self, v: &'v mut Value) -> &'v mut Value {
+        match v {
+            Value::List(l) => {
+                let len = l.len();
+                l.get_mut(*self).unwrap_or_else(|| {
+                    panic!("index {} out of bounds for list of length {}", self, len)
+                })
+            }
+            _ => panic!("cannot index into a non-list value with {}", self),
+        }
+    }
+}
+
+impl Index for [u8] {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        match v {
+            Value::Dict(d) => d.get(self),
+            _ => None,
+        }
+    }
+
+    fn index_into_mut<'v>(&self, v: &'v mut Value) -> Option<&'v mut Value> {
+        match v {
+            Value::Dict(d) => d.get_mut(self),
+            _ => None,
+        }
+    }
+
+    fn index_or_insert<'v>(&self, v: &'v mut Value) -> &'v mut Value {
+        match v {
+            Value::Dict(d) => d
+                .entry(self.into())
+                .or_insert_with(|| Value::Dict(Map::new())),
+            _ => panic!(
+                "cannot index into a non-dictionary value with {:?}",
+                String::from_utf8_lossy(self)
+            ),
+        }
+    }
+}
+
+// the other key types index like the bytes they hold
+macro_rules! index_as_bytes {
+    ($($ty:ty),*) => {
+        $(
+            impl Index for $ty {
+                fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+                    AsRef::<[u8]>::as_ref(self).index_into(v)
+                }
+
+                fn index_into_mut<'v>(&self, v: &'v mut Value) -> Option<&'v mut Value> {
+                    AsRef::<[u8]>::as_ref(self).index_into_mut(v)
+                }
+
+                fn index_or_insert<'v>(&self, v: &'v mut Value) -> &'v mut Value {
+                    AsRef::<[u8]>::as_ref(self).index_or_insert(v)
+                }
+            }
+        )*
+    };
+}
+
+index_as_bytes!(str, String, Vec<u8>);
+
+impl<const N: usize> Index for [u8; N] {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        self[..].index_into(v)
+    }
+
+    fn index_into_mut<'v>(&self, v: &'v mut Value) -> Option<&'v mut Value> {
+        self[..].index_into_mut(v)
+    }
+
+    fn index_or_insert<'v>(&self, v: &'v mut Value) -> &'v mut Value {
+        self[..].index_or_insert(v)
+    }
+}
+
+impl<T: ?Sized + Index> Index for &T {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        (**self).index_into(v)
+    }
+
+    fn index_into_mut<'v>(&self, v: &'v mut Value) -> Option<&'v mut Value> {
+        (**self).index_into_mut(v)
+    }
+
+    fn index_or_insert<'v>(&self, v: &'v mut Value) -> &'v mut Value {
+        (**self).index_or_insert(v)
+    }
+}
+
+// Bencode has no null, so missing paths index to this one empty list. It is
+// told apart by its address: the accessors all treat it as no value, and
+// indexing further stays missing.
+static MISSING: Value = Value::List(Vec::new());
+
+pub(super) fn is_missing(v: &Value) -> bool {
+    core::ptr::eq(v, &MISSING)
+}
+
+/// Like `serde_json`, a missing key or position, or indexing into something
+/// that isn't a dictionary or list, gives a sentinel rather than panicking,
+/// so paths into unknown data can be chained. Check it with
+/// [`Value::is_missing`], or use [`Value::get`] for an `Option`.
+impl<I: Index> ops::Index<I> for Value {
+    type Output = Value;
+
+    fn index(&self, index: I) -> &Value {
+        index.index_into(self).unwrap_or(&MISSING)
+    }
+}
+
+/// Missing dictionary keys are inserted as an empty dictionary, so nested
+/// paths can be assigned directly. The read-only sentinel can't be handed
+/// out mutably, so the other misses still panic, as in `serde_json`.
+///
+/// # Panics
+///
+/// When indexing a list out of bounds, since there is nothing to insert
+/// in between, or indexing into an integer or byte string.
+impl<I: Index> ops::IndexMut<I> for Value {
+    fn index_mut(&mut self, index: I) -> &mut Value {
+        index.index_or_insert(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::de::from_bytes;
+    use crate::value::Value;
+
+    #[test]
+    fn test_index() {
+        let v: Value = from_bytes(b"d4:infod5:filesld6:lengthi5eeee4:name4:johne").unwrap();
+        assert_eq!(Some("john"), v["name"].as_str());
+        assert_eq!(Some(5), v["info"]["files"][0]["length"].as_int());
+        assert_eq!(
+            Some(5),
+            v[b"info"][&b"files".to_vec()][0]["length"].as_int()
+        );
+        assert_eq!(None, v["info"]["files"].get(1));
+        assert_eq!(None, v.get("missing").and_then(|m| m.get("deeper")));
+        assert_eq!(None, v.get(0));
+        // an empty list that is there is still a list
+        let v: Value = from_bytes(b"d4:listlee").unwrap();
+        assert_eq!(Some(&vec![]), v["list"].as_list());
+    }
+
+    #[test]
+    fn test_index_missing() {
+        let v: Value = from_bytes(b"d4:listle4:name4:johne").unwrap();
+        for missing in [
+            &v["nope"],
+            &v["name"]["x"],
+            &v["list"][0],
+            &v["nope"][0]["x"],
+        ] {
+            assert!(missing.is_missing());
+            assert!(!missing.is_int());
+            assert!(!missing.is_bytes());
+            assert!(!missing.is_str());
+            assert!(!missing.is_list());
+            assert!(!missing.is_dict());
+            assert_eq!(None, missing.as_list());
+            assert_eq!(None, missing.as_dict());
+            assert_eq!(None, missing.get(0));
+        }
+        // an empty list that is there is still a list
+        assert!(v["list"].is_list());
+        assert!(!v["list"].is_missing());
+        assert_ne!(&v["list"], &v["nope"]);
+        assert_ne!(&v["nope"], &v["list"]);
+        assert_eq!(&v["nope"], &v["name"][0]);
+        assert!(v["list"] < v["nope"]);
+        assert!(crate::to_bytes(&v["nope"]).is_err());
+        // an owned clone is just an empty list
+        assert_eq!(Value::List(vec![]), v["nope"].clone());
+    }
+
+    #[test]
+    fn test_index_out_of_bounds() {
+        let v = Value::List(vec![]);
+        assert!(v[0].is_missing());
+        assert!(Value::Int(1)["x"].is_missing());
+    }
+
+    #[test]
+    fn test_index_mut() {
+        let mut v: Value = from_bytes(b"d4:listli1eee").unwrap();
+        v["list"][0] = Value::Int(2);
+        v["a"]["b"] = Value::Int(3);
+        assert_eq!(Some(2), v["list"][0].as_int());
+        assert_eq!(Some(3), v["a"]["b"].as_int());
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn test_index_mut_out_of_bounds() {
+        let mut v = Value::List(vec![]);
+        // reading gives the sentinel, but there's no sentinel to assign to
+        assert!(v[0].is_missing());
+        v[0] = Value::Int(1);
+    }
+}
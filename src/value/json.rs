@@ -0,0 +1,142 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::{Map as JsonMap, Value as Json};
+
+use super::{byte_string, Map, Value};
+use crate::error::{Error, Result};
+
+/// How byte strings become JSON strings, and back. Dictionary keys are
+/// always converted as text, replacing invalid UTF-8.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JsonBytes {
+    /// As text, replacing invalid UTF-8.
+    Lossy,
+    /// Always base64.
+    Base64,
+    /// Always lowercase hex.
+    Hex,
+    /// As text when valid UTF-8, otherwise base64. Read back as text.
+    TextOrBase64,
+    /// As text when valid UTF-8, otherwise hex. Read back as text.
+    TextOrHex,
+}
+
+impl Value {
+    /// Converts to a `serde_json::Value`, encoding byte strings per `bytes`.
+    pub fn to_json(&self, bytes: JsonBytes) -> Json {
+        match self {
+            Value::Int(i) => Json::from(*i),
+            Value::Bytes(b) => Json::String(bytes_to_string(b, bytes)),
+            Value::List(l) => Json::Array(l.iter().map(|v| v.to_json(bytes)).collect()),
+            Value::Dict(d) => Json::Object(
+                d.iter()
+                    .map(|(k, v)| (String::from_utf8_lossy(k).into_owned(), v.to_json(bytes)))
+                    .collect::<JsonMap<_, _>>(),
+            ),
+        }
+    }
+
+    /// Converts from a `serde_json::Value`, decoding strings per `bytes`.
+    /// Booleans become 0 or 1; null and non-integer numbers can't be
+    /// represented.
+    pub fn from_json(json: &Json, bytes: JsonBytes) -> Result<Value> {
+        Ok(match json {
+            Json::Null => return Err(Error::Unsupported("null")),
+            Json::Bool(b) => Value::from(*b),
+            Json::Number(n) => match (n.as_i64(), n.as_u64()) {
+                (Some(i), _) => Value::Int(i),
+                (None, Some(_)) => return Err(Error::IntegerOutOfRange),
+                (None, None) => return Err(Error::Unsupported("float")),
+            },
+            Json::String(s) => Value::Bytes(byte_string(string_to_bytes(s, bytes)?)),
+            Json::Array(a) => Value::List(
+                a.iter()
+                    .map(|v| Value::from_json(v, bytes))
+                    .collect::<Result<_>>()?,
+            ),
+            Json::Object(o) => Value::Dict(
+                o.iter()
+                    .map(|(k, v)| Ok((k.as_bytes().into(), Value::from_json(v, bytes)?)))
+                    .collect::<Result<Map<_, _>>>()?,
+            ),
+        })
+    }
+}
+
+fn bytes_to_string(b: &[u8], bytes: JsonBytes) -> String {
+    match (bytes, std::str::from_utf8(b)) {
+        (JsonBytes::Lossy, _) => String::from_utf8_lossy(b).into_owned(),
+        (JsonBytes::TextOrBase64 | JsonBytes::TextOrHex, Ok(s)) => s.to_owned(),
+        (JsonBytes::Base64 | JsonBytes::TextOrBase64, _) => BASE64.encode(b),
+        (JsonBytes::Hex | JsonBytes::TextOrHex, _) => hex::encode(b),
+    }
+}
+
+fn string_to_bytes(s: &str, bytes: JsonBytes) -> Result<Vec<u8>> {
+    match bytes {
+        JsonBytes::Base64 => BASE64
+            .decode(s)
+            .map_err(|e| Error::Message(format!("invalid base64: {}", e))),
+        JsonBytes::Hex => hex::decode(s).map_err(|e| Error::Message(format!("invalid hex: {}", e))),
+        _ => Ok(s.as_bytes().to_vec()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::JsonBytes;
+    use crate::error::Error;
+    use crate::value::Value;
+
+    fn torrent() -> Value {
+        bencode!({ "info": { "length": 5, "name": "a", "pieces": &b"\xff\x00"[..] } })
+    }
+
+    #[test]
+    fn test_to_json() {
+        let v = torrent();
+        assert_eq!(
+            json!({ "info": { "length": 5, "name": "a", "pieces": "\u{fffd}\u{0}" } }),
+            v.to_json(JsonBytes::Lossy)
+        );
+        assert_eq!(
+            json!({ "info": { "length": 5, "name": "YQ==", "pieces": "/wA=" } }),
+            v.to_json(JsonBytes::Base64)
+        );
+        assert_eq!(
+            json!({ "info": { "length": 5, "name": "a", "pieces": "ff00" } }),
+            v.to_json(JsonBytes::TextOrHex)
+        );
+        assert_eq!(
+            json!({ "info": { "length": 5, "name": "a", "pieces": "/wA=" } }),
+            v.to_json(JsonBytes::TextOrBase64)
+        );
+    }
+
+    #[test]
+    fn test_from_json() {
+        for bytes in [JsonBytes::Base64, JsonBytes::Hex] {
+            let v = torrent();
+            assert_eq!(Ok(v.clone()), Value::from_json(&v.to_json(bytes), bytes));
+        }
+        assert_eq!(
+            Ok(bencode!([1, 0, "x"])),
+            Value::from_json(&json!([true, false, "x"]), JsonBytes::Lossy)
+        );
+        assert_eq!(
+            Err(Error::Unsupported("null")),
+            Value::from_json(&json!({ "a": null }), JsonBytes::Lossy)
+        );
+        assert_eq!(
+            Err(Error::Unsupported("float")),
+            Value::from_json(&json!(1.5), JsonBytes::Lossy)
+        );
+        assert_eq!(
+            Err(Error::IntegerOutOfRange),
+            Value::from_json(&json!(u64::MAX), JsonBytes::Lossy)
+        );
+        assert!(Value::from_json(&json!("zz"), JsonBytes::Hex).is_err());
+    }
+}
@@ -0,0 +1,93 @@
+use super::Value;
+
+/// What `Value::merge_with` does when both sides have the same key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Take the value being merged in.
+    Overwrite,
+    /// Keep the existing value.
+    Keep,
+    /// Merge dictionaries key by key, overwriting anything else.
+    Recurse,
+}
+
+impl Value {
+    /// Merges `other` into this value, recursing into dictionaries present
+    /// on both sides. Same as `merge_with(other, MergeStrategy::Recurse)`.
+    pub fn merge(&mut self, other: Value) {
+        self.merge_with(other, MergeStrategy::Recurse)
+    }
+
+    /// Merges `other` into this value. When both are dictionaries their keys
+    /// are combined with `strategy` deciding conflicts; otherwise `other`
+    /// replaces this value unless the strategy is `Keep`.
+    pub fn merge_with(&mut self, other: Value, strategy: MergeStrategy) {
+        match (self, other) {
+            (Value::Dict(dict), Value::Dict(other)) => {
+                for (key, value) in other {
+                    match dict.get_mut(&key) {
+                        None => {
+                            dict.insert(key, value);
+                        }
+                        Some(existing) => match strategy {
+                            MergeStrategy::Overwrite => *existing = value,
+                            MergeStrategy::Keep => {}
+                            MergeStrategy::Recurse => existing.merge_with(value, strategy),
+                        },
+                    }
+                }
+            }
+            (_, _) if strategy == MergeStrategy::Keep => {}
+            (this, other) => *this = other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MergeStrategy;
+    use crate::value::Value;
+
+    fn base() -> Value {
+        bencode!({ "a": 1, "info": { "name": "x", "length": 5 }, "list": [1] })
+    }
+
+    fn patch() -> Value {
+        bencode!({ "b": 2, "info": { "name": "y" }, "list": [2] })
+    }
+
+    #[test]
+    fn test_merge_recurse() {
+        let mut v = base();
+        v.merge(patch());
+        assert_eq!(
+            bencode!({ "a": 1, "b": 2, "info": { "length": 5, "name": "y" }, "list": [2] }),
+            v
+        );
+    }
+
+    #[test]
+    fn test_merge_overwrite() {
+        let mut v = base();
+        v.merge_with(patch(), MergeStrategy::Overwrite);
+        assert_eq!(
+            bencode!({ "a": 1, "b": 2, "info": { "name": "y" }, "list": [2] }),
+            v
+        );
+    }
+
+    #[test]
+    fn test_merge_keep() {
+        let mut v = base();
+        v.merge_with(patch(), MergeStrategy::Keep);
+        let mut expected = base();
+        expected.insert("b", 2);
+        assert_eq!(expected, v);
+
+        let mut v = Value::Int(1);
+        v.merge_with(Value::Int(2), MergeStrategy::Keep);
+        assert_eq!(Value::Int(1), v);
+        v.merge(Value::Int(2));
+        assert_eq!(Value::Int(2), v);
+    }
+}
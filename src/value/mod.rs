@@ -0,0 +1,654 @@
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
+#[cfg(feature = "arena")]
+mod arena;
+#[cfg(feature = "bendy")]
+mod bendy;
+mod borrowed;
+mod cmp;
+mod de;
+mod diff;
+pub(crate) mod display;
+mod from;
+mod index;
+#[cfg(feature = "json")]
+mod json;
+mod merge;
+mod ser;
+#[cfg(feature = "serde_bencode_02")]
+mod serde_bencode_02;
+#[cfg(feature = "small_bytes")]
+mod small;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+#[cfg(feature = "arena")]
+pub use self::arena::BumpValue;
+pub use self::borrowed::ValueRef;
+pub use self::de::from_value;
+pub use self::diff::{diff, Difference};
+pub use self::index::Index;
+#[cfg(feature = "json")]
+pub use self::json::JsonBytes;
+pub use self::merge::MergeStrategy;
+pub use self::ser::to_value;
+#[cfg(feature = "small_bytes")]
+pub use self::small::SmallBytes;
+
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::len::{byte_array_len, int_len};
+use crate::normalize::DuplicatePolicy;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// The map behind dictionaries: a `BTreeMap`, or with the `preserve_order`
+/// feature an `IndexMap` that keeps keys in the order they were read.
+#[cfg(not(feature = "preserve_order"))]
+pub type Map<K, V> = alloc::collections::BTreeMap<K, V>;
+#[cfg(feature = "preserve_order")]
+pub type Map<K, V> = indexmap::IndexMap<K, V>;
+
+/// The byte strings in a `Value`: a `Vec<u8>`, or with the `small_bytes`
+/// feature a [`SmallBytes`] that stores short ones inline.
+#[cfg(not(feature = "small_bytes"))]
+pub type ByteString = Vec<u8>;
+#[cfg(feature = "small_bytes")]
+pub type ByteString = SmallBytes;
+
+// `Vec<u8>` and `ByteString` are the same type without `small_bytes`, so
+// moves between them go through these rather than a bare `into`.
+#[allow(clippy::useless_conversion)]
+pub(crate) fn byte_string(b: Vec<u8>) -> ByteString {
+    b.into()
+}
+
+#[allow(clippy::useless_conversion)]
+pub(crate) fn into_vec(b: ByteString) -> Vec<u8> {
+    b.into()
+}
+
+/// A vacant or occupied dictionary entry, from [`Value::entry`].
+#[cfg(not(feature = "preserve_order"))]
+pub type Entry<'a> = alloc::collections::btree_map::Entry<'a, ByteString, Value>;
+#[cfg(feature = "preserve_order")]
+pub type Entry<'a> = indexmap::map::Entry<'a, ByteString, Value>;
+
+/// Any bencode value, for when the shape of the data isn't known up front.
+#[derive(Clone, Eq)]
+pub enum Value {
+    Int(i64),
+    Bytes(ByteString),
+    List(Vec<Value>),
+    Dict(Map<ByteString, Value>),
+}
+
+impl Value {
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// The byte string as text, if it is valid UTF-8.
+    pub fn as_str(&self) -> Option<&str> {
+        self.as_bytes().and_then(|b| core::str::from_utf8(b).ok())
+    }
+
+    pub fn as_list(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::List(_) if self.is_missing() => None,
+            Value::List(l) => Some(l),
+            _ => None,
+        }
+    }
+
+    pub fn as_dict(&self) -> Option<&Map<ByteString, Value>> {
+        match self {
+            Value::Dict(d) => Some(d),
+            _ => None,
+        }
+    }
+
+    pub fn as_list_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self {
+            Value::List(l) => Some(l),
+            _ => None,
+        }
+    }
+
+    pub fn as_dict_mut(&mut self) -> Option<&mut Map<ByteString, Value>> {
+        match self {
+            Value::Dict(d) => Some(d),
+            _ => None,
+        }
+    }
+
+    /// Looks up a dictionary key or list position, `None` if it's missing.
+    pub fn get<I: Index>(&self, index: I) -> Option<&Value> {
+        index.index_into(self)
+    }
+
+    pub fn get_mut<I: Index>(&mut self, index: I) -> Option<&mut Value> {
+        index.index_into_mut(self)
+    }
+
+    /// Inserts a dictionary entry, returning the value it replaced. Panics if
+    /// this isn't a dictionary.
+    pub fn insert<K, V>(&mut self, key: K, value: V) -> Option<Value>
+    where
+        K: Into<ByteString>,
+        V: Into<Value>,
+    {
+        match self {
+            Value::Dict(d) => d.insert(key.into(), value.into()),
+            _ => panic!("cannot insert into a non-dictionary value"),
+        }
+    }
+
+    /// Removes a dictionary entry, `None` if it's missing or this isn't a
+    /// dictionary.
+    pub fn remove<K: AsRef<[u8]>>(&mut self, key: K) -> Option<Value> {
+        let d = self.as_dict_mut()?;
+        #[cfg(not(feature = "preserve_order"))]
+        return d.remove(key.as_ref());
+        #[cfg(feature = "preserve_order")]
+        return d.shift_remove(key.as_ref());
+    }
+
+    /// The dictionary entry for `key`, for in-place updates. Panics if this
+    /// isn't a dictionary.
+    pub fn entry<K: Into<ByteString>>(&mut self, key: K) -> Entry<'_> {
+        match self {
+            Value::Dict(d) => d.entry(key.into()),
+            _ => panic!("cannot take an entry of a non-dictionary value"),
+        }
+    }
+
+    /// Appends to a list. Panics if this isn't a list.
+    pub fn push<V: Into<Value>>(&mut self, value: V) {
+        match self {
+            Value::List(l) => l.push(value.into()),
+            _ => panic!("cannot push onto a non-list value"),
+        }
+    }
+
+    /// Looks up a value by a JSON-pointer-like path such as
+    /// `/info/files/0/length`. List positions are decimal, and `~1` and `~0`
+    /// escape `/` and `~` in dictionary keys.
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        let mut target = self;
+        for token in pointer_tokens(pointer)? {
+            target = match target {
+                Value::Dict(d) => d.get(token.as_bytes())?,
+                Value::List(l) => l.get(parse_position(&token)?)?,
+                _ => return None,
+            };
+        }
+        Some(target)
+    }
+
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Value> {
+        let mut target = self;
+        for token in pointer_tokens(pointer)? {
+            target = match target {
+                Value::Dict(d) => d.get_mut(token.as_bytes())?,
+                Value::List(l) => l.get_mut(parse_position(&token)?)?,
+                _ => return None,
+            };
+        }
+        Some(target)
+    }
+
+    /// Looks up a dotted path such as `info.files[0].length` (or
+    /// `info.files.0.length`) and deserializes what's there. `Ok(None)` if
+    /// the path is missing. There is no escaping, so keys containing `.`,
+    /// `[` or `]` can't be reached this way; use `pointer` for those.
+    ///
+    /// A path that can't be parsed, such as `files[0`, `files[]` or
+    /// `files[x]`, is an error rather than missing, whatever the value holds.
+    pub fn get_path<'a, T>(&'a self, path: &str) -> Result<Option<T>>
+    where
+        T: Deserialize<'a>,
+    {
+        match self.lookup_path(path)? {
+            Some(v) => T::deserialize(v).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    // parses all of `path` even once the value runs out, so a malformed
+    // path always fails
+    fn lookup_path(&self, path: &str) -> Result<Option<&Value>> {
+        let invalid = || Error::Message(format!("invalid path {:?}", path));
+        let mut target = Some(self);
+        for segment in path.split('.').filter(|s| !s.is_empty()) {
+            let (key, positions) = match segment.split_once('[') {
+                Some((key, rest)) => (key, Some(rest.strip_suffix(']').ok_or_else(invalid)?)),
+                None => (segment, None),
+            };
+            if key.contains(']') {
+                return Err(invalid());
+            }
+            if !key.is_empty() {
+                target = target.and_then(|t| match t {
+                    Value::List(l) => l.get(parse_position(key)?),
+                    _ => t.get(key),
+                });
+            }
+            for position in positions.into_iter().flat_map(|p| p.split("][")) {
+                let position = parse_position(position).ok_or_else(invalid)?;
+                target = target.and_then(|t| t.get(position));
+            }
+        }
+        Ok(target)
+    }
+
+    /// Moves the value out, leaving an empty byte string in its place.
+    pub fn take(&mut self) -> Value {
+        core::mem::take(self)
+    }
+
+    /// The number of bytes `to_bytes` writes for this value, without
+    /// encoding it.
+    pub fn encoded_len(&self) -> usize {
+        match self {
+            Value::Int(i) => int_len(i),
+            Value::Bytes(b) => byte_array_len(b.len()),
+            Value::List(l) => 2 + l.iter().map(Value::encoded_len).sum::<usize>(),
+            Value::Dict(d) => {
+                2 + d
+                    .iter()
+                    .map(|(k, v)| byte_array_len(k.len()) + v.encoded_len())
+                    .sum::<usize>()
+            }
+        }
+    }
+
+    /// Sorts dictionary keys throughout, which only changes anything with
+    /// the `preserve_order` feature. A `Value` can't hold duplicate keys or
+    /// non-canonical integers to begin with: when one is parsed, the last of
+    /// a duplicated key wins and integers are stored as numbers. To choose
+    /// which duplicate wins, parse with `canonical_from_bytes` instead.
+    pub fn canonicalize(&mut self) {
+        match self {
+            Value::List(l) => l.iter_mut().for_each(Value::canonicalize),
+            Value::Dict(d) => {
+                #[cfg(feature = "preserve_order")]
+                d.sort_keys();
+                d.values_mut().for_each(Value::canonicalize);
+            }
+            _ => {}
+        }
+    }
+
+    /// Parses `input` into a canonical `Value`, with `duplicates` deciding
+    /// which of any duplicate keys to keep, or failing with
+    /// `Error::DuplicateKey`.
+    pub fn canonical_from_bytes(input: &[u8], duplicates: DuplicatePolicy) -> Result<Value> {
+        let mut value = crate::normalize::parse_with(input, duplicates)?;
+        value.canonicalize();
+        Ok(value)
+    }
+
+    /// Whether this is what indexing gives for a missing key or position,
+    /// rather than a value that is there. It compares equal only to itself
+    /// and fails to serialize, but it's an empty list underneath, so a clone
+    /// of it is an ordinary empty list.
+    pub fn is_missing(&self) -> bool {
+        index::is_missing(self)
+    }
+
+    pub fn is_int(&self) -> bool {
+        matches!(self, Value::Int(_))
+    }
+
+    pub fn is_bytes(&self) -> bool {
+        matches!(self, Value::Bytes(_))
+    }
+
+    pub fn is_str(&self) -> bool {
+        self.as_str().is_some()
+    }
+
+    pub fn is_list(&self) -> bool {
+        self.as_list().is_some()
+    }
+
+    pub fn is_dict(&self) -> bool {
+        matches!(self, Value::Dict(_))
+    }
+}
+
+/// An empty byte string, which doesn't allocate.
+impl Default for Value {
+    fn default() -> Value {
+        Value::Bytes(ByteString::new())
+    }
+}
+
+fn pointer_tokens(pointer: &str) -> Option<Vec<String>> {
+    if pointer.is_empty() {
+        return Some(Vec::new());
+    }
+    let tokens = pointer.strip_prefix('/')?.split('/');
+    Some(
+        tokens
+            .map(|t| t.replace("~1", "/").replace("~0", "~"))
+            .collect(),
+    )
+}
+
+// leading zeros and signs aren't positions, as in JSON pointer
+fn parse_position(token: &str) -> Option<usize> {
+    if token.starts_with('+') || (token.len() > 1 && token.starts_with('0')) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{from_value, to_value, Map, Value};
+    use crate::de::from_bytes;
+    use crate::error::Error;
+    use crate::normalize::DuplicatePolicy;
+    use crate::ser::to_bytes;
+    use serde::{Deserialize, Serialize};
+
+    fn bytes(b: &[u8]) -> Value {
+        Value::Bytes(b.into())
+    }
+
+    #[test]
+    fn test_accessors() {
+        let v: Value = from_bytes(b"d4:infod6:lengthi5ee4:name4:john3:rawl1:\xffee").unwrap();
+        assert!(v.is_dict());
+        assert_eq!(Some("john"), v.get("name").and_then(Value::as_str));
+        assert_eq!(Some(&b"john"[..]), v.get(b"name").and_then(Value::as_bytes));
+        assert_eq!(
+            Some(5),
+            v.get("info")
+                .and_then(|i| i.get("length"))
+                .and_then(Value::as_int)
+        );
+        assert_eq!(None, v.get("missing"));
+        assert_eq!(None, v.get("name").and_then(|n| n.get("x")));
+
+        let raw = &v.get("raw").and_then(Value::as_list).unwrap()[0];
+        assert!(raw.is_bytes());
+        assert!(!raw.is_str());
+        assert_eq!(None, raw.as_str());
+        assert!(Value::Int(1).is_int());
+        assert!(Value::List(vec![]).is_list());
+        assert_eq!(None, Value::Int(1).as_dict());
+    }
+
+    #[test]
+    fn test_pointer() {
+        let mut v: Value =
+            from_bytes(b"d4:infod5:filesld6:lengthi5eee3:a/bi1e3:c~di2ee3:keyi3ee").unwrap();
+        assert_eq!(Some(&v), v.pointer(""));
+        assert_eq!(
+            Some(5),
+            v.pointer("/info/files/0/length").and_then(Value::as_int)
+        );
+        assert_eq!(Some(1), v.pointer("/info/a~1b").and_then(Value::as_int));
+        assert_eq!(Some(2), v.pointer("/info/c~0d").and_then(Value::as_int));
+        assert_eq!(None, v.pointer("/info/files/1"));
+        assert_eq!(None, v.pointer("/info/files/00"));
+        assert_eq!(None, v.pointer("/key/0"));
+        assert_eq!(None, v.pointer("key"));
+
+        *v.pointer_mut("/info/files/0/length").unwrap() = Value::Int(6);
+        assert_eq!(Some(6), v["info"]["files"][0]["length"].as_int());
+        assert_eq!(None, v.pointer_mut("/nope"));
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn test_preserve_order() {
+        use crate::ser::{to_bytes_with_options, Canonical, SerializerOptions};
+
+        let input = b"d1:bi1e1:ai2ee";
+        let v: Value = from_bytes(input).unwrap();
+        let keys: Vec<&[u8]> = v.as_dict().unwrap().keys().map(|k| &k[..]).collect();
+        assert_eq!(vec![&b"b"[..], b"a"], keys);
+        let options = SerializerOptions::new().canonical(Canonical::Lenient);
+        assert_eq!(input.to_vec(), to_bytes_with_options(&v, options).unwrap());
+        assert_eq!(b"d1:ai2e1:bi1ee".to_vec(), to_bytes(&v).unwrap());
+    }
+
+    #[test]
+    fn test_mutation() {
+        let mut v: Value =
+            from_bytes(b"d8:announce3:old4:infod6:lengthi5ee8:url-listl1:aee").unwrap();
+        assert_eq!(Some(bytes(b"old")), v.insert("announce", "new"));
+        assert_eq!(None, v.insert(b"comment".to_vec(), "hi"));
+        assert!(v.remove("url-list").is_some());
+        assert_eq!(None, v.remove("url-list"));
+        assert_eq!(None, v["info"]["length"].remove("x"));
+
+        *v.get_mut("info").unwrap().get_mut("length").unwrap() = Value::Int(6);
+        *v.entry("count").or_insert(Value::Int(0)) = Value::Int(1);
+        v.entry("nodes")
+            .or_insert_with(|| Value::List(vec![]))
+            .push("n1");
+        assert_eq!(None, v.get_mut("missing"));
+
+        assert_eq!(
+            b"d8:announce3:new7:comment2:hi5:counti1e4:infod6:lengthi6ee5:nodesl2:n1ee".to_vec(),
+            to_bytes(&v).unwrap()
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_push_non_list() {
+        Value::Int(1).push(2);
+    }
+
+    #[test]
+    fn test_get_path() {
+        let v = bencode!({
+            "info": {
+                "files": [{ "length": 5, "path": ["a", "b"] }],
+                "piece length": 16384,
+            },
+            "nodes": [[1, 2]],
+        });
+        assert_eq!(Ok(Some(16384u32)), v.get_path("info.piece length"));
+        assert_eq!(Ok(Some(5i64)), v.get_path("info.files[0].length"));
+        assert_eq!(Ok(Some(5i64)), v.get_path("info.files.0.length"));
+        assert_eq!(Ok(Some("b")), v.get_path("info.files[0].path[1]"));
+        assert_eq!(Ok(Some(2u8)), v.get_path("nodes[0][1]"));
+        assert_eq!(
+            Ok(Some(vec!["a".to_string(), "b".to_string()])),
+            v.get_path("info.files[0].path")
+        );
+        assert_eq!(Ok(None::<i64>), v.get_path("info.files[1].length"));
+        assert_eq!(Ok(None::<i64>), v.get_path("missing.files[0]"));
+        assert_eq!(Ok(None::<i64>), v.get_path("nodes.01"));
+        for path in [
+            "info.files[x",
+            "files[0",
+            "a[]",
+            "files[x]",
+            "a]",
+            "nodes[0]]",
+            "[01]",
+        ] {
+            assert_eq!(
+                Err(Error::Message(format!("invalid path {:?}", path))),
+                v.get_path::<i64>(path)
+            );
+        }
+        assert!(v.get_path::<i64>("info.files[0].path").is_err());
+    }
+
+    #[test]
+    fn test_take() {
+        let mut v = bencode!({ "info": { "pieces": "abc" }, "n": 1 });
+        let info = v["info"].take();
+        assert_eq!(bencode!({ "pieces": "abc" }), info);
+        assert_eq!(bencode!({ "info": "", "n": 1 }), v);
+        assert_eq!(Value::Int(1), v["n"].take());
+    }
+
+    #[test]
+    fn test_encoded_len() {
+        let v = bencode!({ "a": [-10, "xyz", []], "b": {}, "c": vec![0u8; 100] });
+        assert_eq!(to_bytes(&v).unwrap().len(), v.encoded_len());
+        assert_eq!(3, Value::Int(0).encoded_len());
+        assert_eq!(2, Value::Dict(Map::new()).encoded_len());
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let mut v: Value = from_bytes(b"d1:cli1ed1:zi1e1:yi2eee1:ai1e1:bi0e1:bi3ee").unwrap();
+        v.canonicalize();
+        let order = |v: &Value| -> Vec<Vec<u8>> {
+            v.as_dict().unwrap().keys().map(|k| k.to_vec()).collect()
+        };
+        assert_eq!(vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()], order(&v));
+        assert_eq!(vec![b"y".to_vec(), b"z".to_vec()], order(&v["c"][1]));
+        assert_eq!(Some(3), v["b"].as_int());
+
+        let input = b"d1:cli1ed1:zi1e1:yi2eee1:ai1e1:bi0e1:bi3ee";
+        let last = Value::canonical_from_bytes(input, DuplicatePolicy::Last).unwrap();
+        assert_eq!(v, last);
+        assert_eq!(
+            vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()],
+            order(&last)
+        );
+        let first = Value::canonical_from_bytes(input, DuplicatePolicy::First).unwrap();
+        assert_eq!(Some(0), first["b"].as_int());
+        assert_eq!(
+            Err(Error::DuplicateKey("b".to_string())),
+            Value::canonical_from_bytes(input, DuplicatePolicy::Error)
+        );
+    }
+
+    #[test]
+    fn test_to_value() {
+        assert_eq!(Value::Int(-5), to_value(&-5i8).unwrap());
+        assert_eq!(bytes(b"hello"), to_value("hello").unwrap());
+        assert_eq!(
+            Value::List(vec![Value::Int(1), bytes(b"a")]),
+            to_value(&(1, "a")).unwrap()
+        );
+        // wider than `Value::Int`, though `to_bytes` can write it
+        assert_eq!(
+            b"i18446744073709551615e".to_vec(),
+            to_bytes(&u64::MAX).unwrap()
+        );
+        assert_eq!(Err(Error::IntegerOutOfRange), to_value(&u64::MAX));
+        assert_eq!(Value::Int(i64::MAX), to_value(&(i64::MAX as u64)).unwrap());
+        assert_eq!(Err(Error::Unsupported("none")), to_value(&None::<i64>));
+        assert_eq!(
+            Err(Error::Unsupported("none in a list")),
+            to_value(&vec![Some(1), None])
+        );
+    }
+
+    #[test]
+    fn test_struct_round_trip() {
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Info {
+            length: i64,
+            name: String,
+            private: bool,
+            source: Option<String>,
+        }
+
+        let info = Info {
+            length: 5,
+            name: "john".to_string(),
+            private: true,
+            source: None,
+        };
+        let v = to_value(&info).unwrap();
+        let expected = Value::Dict(Map::from([
+            (b"length".into(), Value::Int(5)),
+            (b"name".into(), bytes(b"john")),
+            (b"private".into(), Value::Int(1)),
+        ]));
+        assert_eq!(expected, v);
+        assert_eq!(info, from_value(v).unwrap());
+    }
+
+    #[test]
+    fn test_enum_round_trip() {
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        enum Enum {
+            Unit,
+            Newtype(i32),
+            Tuple(i32, i32),
+            Struct { a: i32 },
+        }
+
+        for e in [
+            Enum::Unit,
+            Enum::Newtype(-1),
+            Enum::Tuple(1, 10),
+            Enum::Struct { a: 1 },
+        ] {
+            let v = to_value(&e).unwrap();
+            assert_eq!(e, from_value(v).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_subtree() {
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Info {
+            length: i64,
+        }
+
+        let v = Value::Dict(Map::from([(
+            b"info".into(),
+            Value::Dict(Map::from([(b"length".into(), Value::Int(7))])),
+        )]));
+        let info: Info = from_value(v.get("info").unwrap().clone()).unwrap();
+        assert_eq!(Info { length: 7 }, info);
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let b = b"d8:announce5:hello4:infod6:lengthi5e4:name4:johne4:listli-1e0:ee";
+        let v: Value = from_bytes(b).unwrap();
+        assert_eq!(
+            Some(&vec![Value::Int(-1), bytes(b"")]),
+            v.get("list").and_then(Value::as_list)
+        );
+        assert_eq!(b.to_vec(), to_bytes(&v).unwrap());
+    }
+
+    #[test]
+    fn test_nested_in_struct() {
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Test {
+            name: String,
+            #[serde(flatten)]
+            extra: BTreeMap<String, Value>,
+        }
+
+        let b = b"d1:ai1e4:name4:john1:zl1:xee";
+        let v: Test = from_bytes(b).unwrap();
+        assert_eq!("john", v.name);
+        assert_eq!(Value::Int(1), v.extra["a"]);
+        assert_eq!(b.to_vec(), to_bytes(&v).unwrap());
+    }
+}
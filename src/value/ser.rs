@@ -0,0 +1,464 @@
+use super::{byte_string, ByteString, Map, Value};
+use crate::error::{Error, Result};
+use crate::ser::{map_key, BoolPolicy, Canonical, FloatPolicy, NonePolicy, SerializerOptions};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use serde::ser::{self, Serialize, SerializeMap};
+
+impl Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        match self {
+            _ if self.is_missing() => Err(ser::Error::custom(
+                "a missing index result can't be serialized",
+            )),
+            Value::Int(i) => serializer.serialize_i64(*i),
+            Value::Bytes(b) => serializer.serialize_bytes(b),
+            Value::List(l) => serializer.collect_seq(l),
+            Value::Dict(d) => {
+                let mut map = serializer.serialize_map(Some(d.len()))?;
+                for (k, v) in d {
+                    map.serialize_entry(&Bytes(k), v)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+// `Vec<u8>` would otherwise serialize as a list of integers
+pub(super) struct Bytes<'a>(pub(super) &'a [u8]);
+
+impl Serialize for Bytes<'_> {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+/// Converts `value` into a `Value`, following the same rules as `to_bytes`,
+/// except that integers must fit `Value::Int`'s `i64`: `to_bytes` writes a
+/// `u64` above `i64::MAX`, but here it is `Error::IntegerOutOfRange`.
+pub fn to_value<T>(value: &T) -> Result<Value>
+where
+    T: ?Sized + Serialize,
+{
+    let options = SerializerOptions::default();
+    value
+        .serialize(&ValueSerializer { options: &options })?
+        .ok_or(Error::Unsupported("none"))
+}
+
+// Produces `None` for values that are skipped, like `to_bytes` writing
+// nothing for them.
+struct ValueSerializer<'a> {
+    options: &'a SerializerOptions,
+}
+
+impl ValueSerializer<'_> {
+    fn none(&self) -> Result<Option<Value>> {
+        match self.options.none_policy {
+            NonePolicy::Skip => Ok(None),
+            NonePolicy::Error => Err(Error::Unsupported("none")),
+        }
+    }
+
+    fn int<T: TryInto<i64>>(&self, v: T) -> Result<Option<Value>> {
+        let v = v.try_into().map_err(|_| Error::IntegerOutOfRange)?;
+        Ok(Some(Value::Int(v)))
+    }
+
+    fn bytes(&self, v: &[u8]) -> Result<Option<Value>> {
+        Ok(Some(Value::Bytes(v.into())))
+    }
+}
+
+fn variant(name: &'static str, value: Value) -> Value {
+    Value::Dict(Map::from([(name.as_bytes().into(), value)]))
+}
+
+impl<'a> ser::Serializer for &ValueSerializer<'a> {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    type SerializeSeq = SeqBuilder<'a>;
+    type SerializeTuple = SeqBuilder<'a>;
+    type SerializeTupleStruct = SeqBuilder<'a>;
+    type SerializeTupleVariant = SeqBuilder<'a>;
+    type SerializeMap = MapBuilder<'a>;
+    type SerializeStruct = MapBuilder<'a>;
+    type SerializeStructVariant = MapBuilder<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<Option<Value>> {
+        match self.options.bool_policy {
+            BoolPolicy::Int => self.int(v as i64),
+            BoolPolicy::Error => Err(Error::Unsupported("bool")),
+        }
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Option<Value>> {
+        self.int(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Option<Value>> {
+        self.int(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Option<Value>> {
+        self.int(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Option<Value>> {
+        self.int(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Option<Value>> {
+        self.int(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Option<Value>> {
+        self.int(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Option<Value>> {
+        self.int(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Option<Value>> {
+        self.int(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Option<Value>> {
+        self.int(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Option<Value>> {
+        self.int(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Option<Value>> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Option<Value>> {
+        if self.options.canonical == Canonical::Strict {
+            return Err(Error::NonCanonical("float"));
+        }
+        match self.options.float_policy {
+            FloatPolicy::Error => Err(Error::Unsupported("float")),
+            FloatPolicy::String => self.bytes(v.to_string().as_bytes()),
+        }
+    }
+
+    fn serialize_char(self, v: char) -> Result<Option<Value>> {
+        self.bytes(v.encode_utf8(&mut [0; 4]).as_bytes())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Option<Value>> {
+        self.bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Option<Value>> {
+        self.bytes(v)
+    }
+
+    fn serialize_none(self) -> Result<Option<Value>> {
+        self.none()
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Option<Value>>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Option<Value>> {
+        self.none()
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Option<Value>> {
+        self.none()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Option<Value>> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Option<Value>>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant_name: &'static str,
+        value: &T,
+    ) -> Result<Option<Value>>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(value.serialize(self)?.map(|v| variant(variant_name, v)))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Ok(SeqBuilder {
+            options: self.options,
+            items: Vec::with_capacity(len.unwrap_or(0)),
+            variant: None,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        let mut seq = self.serialize_seq(Some(len))?;
+        seq.variant = Some(variant);
+        Ok(seq)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Ok(MapBuilder {
+            options: self.options,
+            entries: Map::new(),
+            key: None,
+            variant: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        let mut map = self.serialize_map(Some(len))?;
+        map.variant = Some(variant);
+        Ok(map)
+    }
+}
+
+struct SeqBuilder<'a> {
+    options: &'a SerializerOptions,
+    items: Vec<Value>,
+    variant: Option<&'static str>,
+}
+
+impl SeqBuilder<'_> {
+    fn push<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let ser = ValueSerializer {
+            options: self.options,
+        };
+        let v = value
+            .serialize(&ser)?
+            .ok_or(Error::Unsupported("none in a list"))?;
+        self.items.push(v);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Option<Value>> {
+        let list = Value::List(self.items);
+        Ok(Some(match self.variant {
+            Some(name) => variant(name, list),
+            None => list,
+        }))
+    }
+}
+
+impl ser::SerializeSeq for SeqBuilder<'_> {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Option<Value>> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SeqBuilder<'_> {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Option<Value>> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqBuilder<'_> {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Option<Value>> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SeqBuilder<'_> {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Option<Value>> {
+        self.finish()
+    }
+}
+
+struct MapBuilder<'a> {
+    options: &'a SerializerOptions,
+    entries: Map<ByteString, Value>,
+    key: Option<Vec<u8>>,
+    variant: Option<&'static str>,
+}
+
+impl MapBuilder<'_> {
+    fn insert<T>(&mut self, key: Vec<u8>, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let ser = ValueSerializer {
+            options: self.options,
+        };
+        let value = match value.serialize(&ser)? {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+        if self.entries.contains_key(&key[..]) {
+            return Err(Error::DuplicateKey(
+                String::from_utf8_lossy(&key).into_owned(),
+            ));
+        }
+        self.entries.insert(byte_string(key), value);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Option<Value>> {
+        let dict = Value::Dict(self.entries);
+        Ok(Some(match self.variant {
+            Some(name) => variant(name, dict),
+            None => dict,
+        }))
+    }
+}
+
+impl ser::SerializeMap for MapBuilder<'_> {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.key = Some(map_key(key, self.options, |k| Ok(k.to_vec()))?);
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error::Message("value serialized before its key".to_string()))?;
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<Option<Value>> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for MapBuilder<'_> {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.insert(key.as_bytes().to_vec(), value)
+    }
+
+    fn end(self) -> Result<Option<Value>> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for MapBuilder<'_> {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.insert(key.as_bytes().to_vec(), value)
+    }
+
+    fn end(self) -> Result<Option<Value>> {
+        self.finish()
+    }
+}
@@ -0,0 +1,72 @@
+//! Conversions to and from the `Value` of `serde_bencode` 0.2, for code
+//! that still passes those around. Both hold the same data, so neither
+//! way can fail.
+
+use serde_bencode_02::value::Value as Other;
+
+use super::{byte_string, into_vec, Map, Value};
+
+impl From<Other> for Value {
+    fn from(v: Other) -> Value {
+        match v {
+            Other::Int(i) => Value::Int(i),
+            Other::Bytes(b) => Value::Bytes(byte_string(b)),
+            Other::List(l) => Value::List(l.into_iter().map(Value::from).collect()),
+            Other::Dict(d) => {
+                // sorted, or with `preserve_order` keys would come in
+                // `HashMap` order
+                let mut entries: Vec<_> = d.into_iter().collect();
+                entries.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+                Value::Dict(
+                    entries
+                        .into_iter()
+                        .map(|(k, v)| (byte_string(k), Value::from(v)))
+                        .collect::<Map<_, _>>(),
+                )
+            }
+        }
+    }
+}
+
+impl From<Value> for Other {
+    fn from(v: Value) -> Other {
+        match v {
+            Value::Int(i) => Other::Int(i),
+            Value::Bytes(b) => Other::Bytes(into_vec(b)),
+            Value::List(l) => Other::List(l.into_iter().map(Other::from).collect()),
+            Value::Dict(d) => Other::Dict(
+                d.into_iter()
+                    .map(|(k, v)| (into_vec(k), Other::from(v)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_bencode_02::value::Value as Other;
+
+    use crate::value::Value;
+    use crate::{from_bytes, to_bytes};
+
+    #[test]
+    fn test_convert() {
+        let input = include_bytes!("../../debian.torrent");
+        let value: Value = from_bytes(input).unwrap();
+        let other: Other = serde_bencode_02::from_bytes(input).unwrap();
+
+        assert_eq!(value, Value::from(other.clone()));
+        let converted = Other::from(value);
+        assert_eq!(other, converted);
+        assert_eq!(&input[..], serde_bencode_02::to_bytes(&converted).unwrap());
+
+        let value = bencode!({ "b": [1, "x"], "a": {}, "c": -2 });
+        let round_trip = Value::from(Other::from(value.clone()));
+        assert_eq!(value, round_trip);
+        assert_eq!(
+            b"d1:ade1:bli1e1:xe1:ci-2ee".to_vec(),
+            to_bytes(&round_trip).unwrap()
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use alloc::borrow::{Borrow, Cow};
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::mem;
+use core::ops::{Deref, DerefMut};
+use serde::ser::{Serialize, Serializer};
+
+// leaves room for the length and the enum's tag
+const INLINE: usize = mem::size_of::<Vec<u8>>() - 2;
+
+/// A byte string that keeps up to 22 bytes (10 on 32-bit targets) inline
+/// rather than on the heap, which covers most dictionary keys. It's no bigger than a `Vec<u8>` and
+/// compares, orders and hashes like the bytes it holds.
+#[derive(Clone)]
+pub struct SmallBytes(Repr);
+
+#[derive(Clone)]
+enum Repr {
+    Inline(u8, [u8; INLINE]),
+    Heap(Box<[u8]>),
+}
+
+impl SmallBytes {
+    pub const fn new() -> SmallBytes {
+        SmallBytes(Repr::Inline(0, [0; INLINE]))
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        match &self.0 {
+            Repr::Inline(len, buf) => &buf[..*len as usize],
+            Repr::Heap(b) => b,
+        }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        match &mut self.0 {
+            Repr::Inline(len, buf) => &mut buf[..*len as usize],
+            Repr::Heap(b) => b,
+        }
+    }
+
+    /// Whether the bytes are stored inline, without a heap allocation.
+    pub fn is_inline(&self) -> bool {
+        matches!(self.0, Repr::Inline(..))
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        match self.0 {
+            Repr::Inline(len, buf) => buf[..len as usize].to_vec(),
+            Repr::Heap(b) => b.into_vec(),
+        }
+    }
+
+    fn inline(b: &[u8]) -> Option<SmallBytes> {
+        let mut buf = [0; INLINE];
+        buf.get_mut(..b.len())?.copy_from_slice(b);
+        Some(SmallBytes(Repr::Inline(b.len() as u8, buf)))
+    }
+}
+
+impl Default for SmallBytes {
+    fn default() -> SmallBytes {
+        SmallBytes::new()
+    }
+}
+
+impl Deref for SmallBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl DerefMut for SmallBytes {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        self.as_mut_slice()
+    }
+}
+
+impl AsRef<[u8]> for SmallBytes {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl Borrow<[u8]> for SmallBytes {
+    fn borrow(&self) -> &[u8] {
+        self
+    }
+}
+
+impl fmt::Debug for SmallBytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_slice().fmt(f)
+    }
+}
+
+impl PartialEq for SmallBytes {
+    fn eq(&self, other: &SmallBytes) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl Eq for SmallBytes {}
+
+impl PartialOrd for SmallBytes {
+    fn partial_cmp(&self, other: &SmallBytes) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SmallBytes {
+    fn cmp(&self, other: &SmallBytes) -> Ordering {
+        self.as_slice().cmp(other.as_slice())
+    }
+}
+
+impl Hash for SmallBytes {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_slice().hash(state)
+    }
+}
+
+impl PartialEq<[u8]> for SmallBytes {
+    fn eq(&self, other: &[u8]) -> bool {
+        self.as_slice() == other
+    }
+}
+
+impl PartialEq<Vec<u8>> for SmallBytes {
+    fn eq(&self, other: &Vec<u8>) -> bool {
+        self.as_slice() == &other[..]
+    }
+}
+
+impl From<&[u8]> for SmallBytes {
+    fn from(b: &[u8]) -> SmallBytes {
+        SmallBytes::inline(b).unwrap_or_else(|| SmallBytes(Repr::Heap(b.into())))
+    }
+}
+
+impl<const N: usize> From<&[u8; N]> for SmallBytes {
+    fn from(b: &[u8; N]) -> SmallBytes {
+        SmallBytes::from(&b[..])
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for SmallBytes {
+    fn from(b: [u8; N]) -> SmallBytes {
+        SmallBytes::from(&b[..])
+    }
+}
+
+impl From<&str> for SmallBytes {
+    fn from(s: &str) -> SmallBytes {
+        SmallBytes::from(s.as_bytes())
+    }
+}
+
+impl From<String> for SmallBytes {
+    fn from(s: String) -> SmallBytes {
+        SmallBytes::from(s.into_bytes())
+    }
+}
+
+impl From<Vec<u8>> for SmallBytes {
+    fn from(b: Vec<u8>) -> SmallBytes {
+        SmallBytes::inline(&b).unwrap_or_else(|| SmallBytes(Repr::Heap(b.into_boxed_slice())))
+    }
+}
+
+impl From<Box<[u8]>> for SmallBytes {
+    fn from(b: Box<[u8]>) -> SmallBytes {
+        SmallBytes::inline(&b).unwrap_or(SmallBytes(Repr::Heap(b)))
+    }
+}
+
+impl From<Cow<'_, [u8]>> for SmallBytes {
+    fn from(b: Cow<'_, [u8]>) -> SmallBytes {
+        match b {
+            Cow::Borrowed(b) => SmallBytes::from(b),
+            Cow::Owned(b) => SmallBytes::from(b),
+        }
+    }
+}
+
+impl From<SmallBytes> for Vec<u8> {
+    fn from(b: SmallBytes) -> Vec<u8> {
+        b.into_vec()
+    }
+}
+
+impl Serialize for SmallBytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SmallBytes, INLINE};
+    use std::mem::size_of;
+
+    #[test]
+    fn test_small_bytes() {
+        assert_eq!(size_of::<Vec<u8>>(), size_of::<SmallBytes>());
+
+        let short = SmallBytes::from("name");
+        assert!(short.is_inline());
+        assert_eq!(b"name", &short[..]);
+        assert!(SmallBytes::from(vec![7; INLINE]).is_inline());
+
+        let long = SmallBytes::from(vec![7; INLINE + 1]);
+        assert!(!long.is_inline());
+        assert_eq!(vec![7; INLINE + 1], long.clone().into_vec());
+
+        assert_eq!(SmallBytes::from(&[7; 22]), SmallBytes::from(vec![7; 22]));
+        let (a, b) = (SmallBytes::from("a"), SmallBytes::from(vec![b'b'; 30]));
+        assert!(a < b);
+        assert!(SmallBytes::new().is_empty());
+    }
+}
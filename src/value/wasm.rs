@@ -0,0 +1,113 @@
+use js_sys::{Array, BigInt, Map as JsMap, Object, Uint8Array};
+use wasm_bindgen::{JsCast, JsValue};
+
+use super::{byte_string, Map, Value};
+use crate::error::{Error, Result};
+
+// the largest integer a JS number holds exactly, 2^53 - 1
+const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+
+impl Value {
+    /// Converts to a JS value: integers become numbers, or `BigInt`s when
+    /// they're too large to be exact; byte strings become `Uint8Array`s,
+    /// lists `Array`s, and dictionaries `Map`s with string keys, or
+    /// `Uint8Array` keys where they aren't UTF-8.
+    pub fn to_js(&self) -> JsValue {
+        match self {
+            Value::Int(i) if (-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(i) => {
+                JsValue::from_f64(*i as f64)
+            }
+            Value::Int(i) => BigInt::from(*i).into(),
+            Value::Bytes(b) => Uint8Array::from(&b[..]).into(),
+            Value::List(l) => l.iter().map(Value::to_js).collect::<Array>().into(),
+            Value::Dict(d) => {
+                let map = JsMap::new();
+                for (k, v) in d {
+                    let key = match core::str::from_utf8(k) {
+                        Ok(s) => JsValue::from_str(s),
+                        Err(_) => Uint8Array::from(&k[..]).into(),
+                    };
+                    map.set(&key, &v.to_js());
+                }
+                map.into()
+            }
+        }
+    }
+
+    /// Converts from a JS value, the reverse of `to_js`. Strings are encoded
+    /// as UTF-8, plain objects are read as dictionaries, and booleans
+    /// become 0 or 1; `null`, `undefined` and non-integer numbers can't be
+    /// represented.
+    pub fn from_js(js: &JsValue) -> Result<Value> {
+        if let Some(n) = js.as_f64() {
+            if n.fract() != 0.0 || !n.is_finite() {
+                return Err(Error::Unsupported("float"));
+            }
+            if n.abs() > MAX_SAFE_INTEGER as f64 {
+                return Err(Error::IntegerOutOfRange);
+            }
+            return Ok(Value::Int(n as i64));
+        }
+        if js.is_bigint() {
+            return i64::try_from(js.clone())
+                .map(Value::Int)
+                .map_err(|_| Error::IntegerOutOfRange);
+        }
+        if let Some(b) = js.as_bool() {
+            return Ok(Value::from(b));
+        }
+        if let Some(s) = js.as_string() {
+            return Ok(Value::Bytes(byte_string(s.into_bytes())));
+        }
+        if let Some(b) = js.dyn_ref::<Uint8Array>() {
+            return Ok(Value::Bytes(byte_string(b.to_vec())));
+        }
+        if Array::is_array(js) {
+            let list = js.unchecked_ref::<Array>();
+            return list
+                .iter()
+                .map(|v| Value::from_js(&v))
+                .collect::<Result<_>>()
+                .map(Value::List);
+        }
+        let entries = if let Some(map) = js.dyn_ref::<JsMap>() {
+            Array::from(map.as_ref())
+        } else if js.is_object() && !js.is_function() {
+            Object::entries(js.unchecked_ref())
+        } else if js.is_null() {
+            return Err(Error::Unsupported("null"));
+        } else {
+            return Err(Error::Unsupported("JS value"));
+        };
+        let mut dict = Map::new();
+        for entry in entries.iter() {
+            let entry = entry.unchecked_into::<Array>();
+            let key = match Value::from_js(&entry.get(0))? {
+                Value::Bytes(k) => k,
+                _ => return Err(Error::KeyMustBeByteString),
+            };
+            dict.insert(key, Value::from_js(&entry.get(1))?);
+        }
+        Ok(Value::Dict(dict))
+    }
+}
+
+impl From<&Value> for JsValue {
+    fn from(v: &Value) -> JsValue {
+        v.to_js()
+    }
+}
+
+impl From<Value> for JsValue {
+    fn from(v: Value) -> JsValue {
+        v.to_js()
+    }
+}
+
+impl TryFrom<JsValue> for Value {
+    type Error = Error;
+
+    fn try_from(js: JsValue) -> Result<Value> {
+        Value::from_js(&js)
+    }
+}
@@ -0,0 +1,23 @@
+use serde_bencode::Deserializer;
+
+fn transcode(input: &[u8]) -> String {
+    let mut de = Deserializer::from_bytes(input);
+    let mut out = Vec::new();
+    serde_transcode::transcode(&mut de, &mut serde_json::Serializer::new(&mut out)).unwrap();
+    de.end().unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn test_transcode_to_json() {
+    assert_eq!(
+        r#"{"list":[1,-2,"ab"],"name":"john","nested":{"a":[]}}"#,
+        transcode(b"d4:listli1ei-2e2:abe4:name4:john6:nestedd1:aleee")
+    );
+
+    // JSON has no bytes type, so serde_json writes them as an array
+    assert_eq!(
+        r#"{"peer":[255,0,1]}"#,
+        transcode(b"d4:peer3:\xff\x00\x01e")
+    );
+}